@@ -25,7 +25,7 @@ app.get("/api/stats", (req, res) => {
     const creation_date = fs.statSync("./bulletin.db").birthtimeMs;
 
     db.get(`
-        SELECT COUNT(*) FROM UndergraduateCourse;
+        SELECT COUNT(*) FROM UndergraduateCourse WHERE retired_at IS NULL;
     `, (err, row) => {
         if (err) {
             console.log("Error with database: ", err);
@@ -171,7 +171,7 @@ app.get("/api/search/courses", (req, res) => {
     const { query } = req.query;
     db.all(`
             SELECT * FROM UndergraduateCourse
-            WHERE (
+            WHERE retired_at IS NULL AND (
                 CONCAT(code, ' ', number, suffix) LIKE '%${query}%' OR
                 title LIKE '%${query}%' OR
                 description LIKE '%${query}%'
@@ -54,6 +54,9 @@ type RequirementTreeNode = {
   course_id: number | null;
   req_course_id: number | null;
   parent: number | null;
+  min_grade: string | null; // e.g. 'C' for 'C or better in MATH 140'
+  detail: string | null; // what a clause is about, e.g. '5' for semester standing
+  required_count: number | null; // for 'at least N of the following'
 };
 
 type UndergraduateCourse = {
@@ -66,6 +69,7 @@ type UndergraduateCourse = {
   description: string;
   credits: number;
   min_credits: number | null;
+  depth: number | null; // semesters of prerequisites needed at the very least
 
   GA: boolean; // Arts
   GHW: boolean; // Health and Wellness
@@ -207,6 +211,16 @@ export default function CoursePage({ id }: CoursePageProps) {
               stack.push(node.id.toString());
 
               return 'OR';
+            } else if (node.logic === 'N') {
+              if (stack.length === 0) {
+                edges.push({
+                  source: '1',
+                  target: node.id.toString(),
+                });
+              }
+              stack.push(node.id.toString());
+
+              return `AT LEAST ${node.required_count} OF`;
             } else if (node.logic === 'C') {
               const data = await fetch(
                 `http://localhost:3001/api/course/${node.req_course_id}`,
@@ -221,7 +235,27 @@ export default function CoursePage({ id }: CoursePageProps) {
                 });
               }
 
-              return `${data.code} ${data.number}${data.suffix || ''}`;
+              const label = `${data.code} ${data.number}${data.suffix || ''}`;
+              return node.min_grade ? `${label} (${node.min_grade} OR BETTER)` : label;
+            } else {
+              // clauses that aren't courses
+              if (stack.length > 0) {
+                edges.push({
+                  source: stack[stack.length - 1],
+                  target: node.id.toString(),
+                });
+              }
+
+              switch (node.logic) {
+                case 'S':
+                  return `SEMESTER STANDING: ${node.detail}`;
+                case 'E':
+                  return `ENROLLMENT IN ${node.detail}`;
+                case 'P':
+                  return `PERMISSION OF ${node.detail}`;
+                default:
+                  return node.detail ?? '';
+              }
             }
           })(),
         },
@@ -241,10 +275,19 @@ export default function CoursePage({ id }: CoursePageProps) {
   }
 
   const { nodes, edges } = getLayoutedElements(flowNodes, flowEdges, 'TB');
+  // null until a full sync has computed it
+  const depth = searchResults.data?.depth;
 
   return (
     <>
       <Header />
+      {depth != null && (
+        <p className="px-4 pt-2 text-sm text-psu-300">
+          {depth === 0
+            ? 'No prerequisite courses needed'
+            : `At least ${depth} semester${depth === 1 ? '' : 's'} of prerequisites`}
+        </p>
+      )}
       <div className="w-full">
         <ReactFlow
           proOptions={{ hideAttribution: true }}
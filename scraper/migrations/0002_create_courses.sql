@@ -0,0 +1,96 @@
+-- TODO: add safety attributes (UNIQUE, NOT NULL, etc)
+CREATE TABLE IF NOT EXISTS UndergraduateCourse (
+  id INTEGER PRIMARY KEY,
+  code VARCHAR(5),
+  number INT,
+  suffix VARCHAR(1),
+  title VARCHAR(255),
+  description VARCHAR(65535),
+  credits REAL,
+  min_credits REAL,
+  GA bool,
+  GHW bool,
+  GH bool,
+  GN bool,
+  GQ bool,
+  GS bool,
+  GWS bool,
+  ITD bool,
+  LKD bool,
+  FYS bool,
+  IC bool,
+  US bool,
+  WCC bool,
+  BA bool,
+  BH bool,
+  BN bool,
+  BO bool,
+  BQ bool,
+  BS bool,
+  BF1 bool,
+  BF2 bool,
+  HNR bool,
+  is_prerequisite_concurrent_separate bool,
+  empty_crosslist bool,
+  unknown_requirement bool,
+
+  -- FOREIGN KEY (id) REFERENCES UndergraduateCourseCrossLists (course_id)
+  -- FOREIGN KEY (id) REFERENCES UndergraduateCoursePrerequisites (course_id)
+  -- FOREIGN KEY (id) REFERENCES UndergraduateCourseConcurrent (course_id)
+  -- FOREIGN KEY (id) REFERENCES UndergraduateCourseCorequisites (course_id)
+  -- FOREIGN KEY (id) REFERENCES UndergraduateCourseRecommended (course_id)
+  UNIQUE (code, number, suffix)
+);
+
+CREATE TABLE IF NOT EXISTS UndergraduateCourseCrossLists (
+  id INTEGER PRIMARY KEY,
+  course_id INT,
+  crossed_course_id INT,
+
+  FOREIGN KEY (crossed_course_id) REFERENCES UndergraduateCourse (id)
+);
+
+-- TODO: merge all requirements into one table with type key to differentiate?
+CREATE TABLE IF NOT EXISTS UndergraduateCoursePrerequisites (
+  id INTEGER PRIMARY KEY,
+  logic VARCHAR(1),
+  course_id INT,
+  req_course_id INT,
+  parent INT,
+
+  FOREIGN KEY (req_course_id) REFERENCES UndergraduateCourse (id)
+  FOREIGN KEY (parent) REFERENCES UndergraduateCoursePrerequisites (id)
+);
+
+CREATE TABLE IF NOT EXISTS UndergraduateCourseConcurrent (
+  id INTEGER PRIMARY KEY,
+  logic VARCHAR(1),
+  course_id INT,
+  req_course_id INT,
+  parent INT,
+
+  FOREIGN KEY (req_course_id) REFERENCES UndergraduateCourse (id)
+  FOREIGN KEY (parent) REFERENCES UndergraduateCourseConcurrent (id)
+);
+
+CREATE TABLE IF NOT EXISTS UndergraduateCourseCorequisites (
+  id INTEGER PRIMARY KEY,
+  logic VARCHAR(1),
+  course_id INT,
+  req_course_id INT,
+  parent INT,
+
+  FOREIGN KEY (req_course_id) REFERENCES UndergraduateCourse (id)
+  FOREIGN KEY (parent) REFERENCES UndergraduateCourseCorequisites (id)
+);
+
+CREATE TABLE IF NOT EXISTS UndergraduateCourseRecommended (
+  id INTEGER PRIMARY KEY,
+  logic VARCHAR(1),
+  course_id INT,
+  req_course_id INT,
+  parent INT,
+
+  FOREIGN KEY (req_course_id) REFERENCES UndergraduateCourse (id)
+  FOREIGN KEY (parent) REFERENCES UndergraduateCourseRecommended (id)
+);
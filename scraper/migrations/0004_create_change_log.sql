@@ -0,0 +1,31 @@
+CREATE TABLE IF NOT EXISTS SyncRun (
+  id INTEGER PRIMARY KEY,
+  target VARCHAR(255) NOT NULL, -- programs or courses
+  started_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  finished_at DATETIME
+);
+
+-- course_id isn't a foreign key, deleted courses keep their history
+CREATE TABLE IF NOT EXISTS CourseChange (
+  id INTEGER PRIMARY KEY,
+  run_id INT NOT NULL,
+  course_id INT NOT NULL,
+  identifier VARCHAR(255) NOT NULL,
+  field VARCHAR(255) NOT NULL,
+  old_value TEXT,
+  new_value TEXT,
+
+  FOREIGN KEY (run_id) REFERENCES SyncRun (id)
+);
+
+CREATE TABLE IF NOT EXISTS ProgramChange (
+  id INTEGER PRIMARY KEY,
+  run_id INT NOT NULL,
+  program_id INT NOT NULL,
+  link VARCHAR(255) NOT NULL,
+  field VARCHAR(255) NOT NULL,
+  old_value TEXT,
+  new_value TEXT,
+
+  FOREIGN KEY (run_id) REFERENCES SyncRun (id)
+);
@@ -0,0 +1,10 @@
+-- requirement trees are walked from their root (course_id) down through parent
+CREATE INDEX IF NOT EXISTS UndergraduateCoursePrerequisites_course_id ON UndergraduateCoursePrerequisites (course_id);
+CREATE INDEX IF NOT EXISTS UndergraduateCoursePrerequisites_parent ON UndergraduateCoursePrerequisites (parent);
+CREATE INDEX IF NOT EXISTS UndergraduateCourseConcurrent_course_id ON UndergraduateCourseConcurrent (course_id);
+CREATE INDEX IF NOT EXISTS UndergraduateCourseConcurrent_parent ON UndergraduateCourseConcurrent (parent);
+CREATE INDEX IF NOT EXISTS UndergraduateCourseCorequisites_course_id ON UndergraduateCourseCorequisites (course_id);
+CREATE INDEX IF NOT EXISTS UndergraduateCourseCorequisites_parent ON UndergraduateCourseCorequisites (parent);
+CREATE INDEX IF NOT EXISTS UndergraduateCourseRecommended_course_id ON UndergraduateCourseRecommended (course_id);
+CREATE INDEX IF NOT EXISTS UndergraduateCourseRecommended_parent ON UndergraduateCourseRecommended (parent);
+CREATE INDEX IF NOT EXISTS UndergraduateCourseCrossLists_course_id ON UndergraduateCourseCrossLists (course_id);
@@ -0,0 +1,61 @@
+-- reverse of the requirement trees: the requirements of dependent_id mention course_id.
+-- mandatory is false if the course is one option among alternatives (under an OR or AT LEAST)
+CREATE TABLE IF NOT EXISTS UndergraduateCourseDependents (
+  id INTEGER PRIMARY KEY,
+  course_id INT NOT NULL,
+  dependent_id INT NOT NULL,
+  kind VARCHAR(255) NOT NULL, -- prerequisite, concurrent, corequisite or recommended
+  mandatory BOOLEAN NOT NULL,
+
+  FOREIGN KEY (course_id) REFERENCES UndergraduateCourse (id)
+  FOREIGN KEY (dependent_id) REFERENCES UndergraduateCourse (id)
+  UNIQUE (course_id, dependent_id, kind)
+);
+
+CREATE INDEX IF NOT EXISTS UndergraduateCourseDependents_course_id ON UndergraduateCourseDependents (course_id);
+
+-- fill it from the trees that are already stored. A course is mandatory if every group above
+-- it is an AND, open tells whether that's still true below a node
+WITH RECURSIVE walk (id, dependent_id, req_course_id, logic, mandatory, open) AS (
+  SELECT id, course_id, req_course_id, logic, 1, logic = '&' FROM UndergraduateCoursePrerequisites WHERE parent IS NULL
+  UNION ALL
+  SELECT node.id, walk.dependent_id, node.req_course_id, node.logic, walk.open, walk.open AND node.logic = '&'
+  FROM UndergraduateCoursePrerequisites node JOIN walk ON node.parent = walk.id
+)
+INSERT OR IGNORE INTO UndergraduateCourseDependents (course_id, dependent_id, kind, mandatory)
+SELECT req_course_id, dependent_id, 'prerequisite', MAX(mandatory) FROM walk
+WHERE logic = 'C' AND req_course_id IS NOT NULL AND dependent_id IS NOT NULL
+GROUP BY req_course_id, dependent_id;
+
+WITH RECURSIVE walk (id, dependent_id, req_course_id, logic, mandatory, open) AS (
+  SELECT id, course_id, req_course_id, logic, 1, logic = '&' FROM UndergraduateCourseConcurrent WHERE parent IS NULL
+  UNION ALL
+  SELECT node.id, walk.dependent_id, node.req_course_id, node.logic, walk.open, walk.open AND node.logic = '&'
+  FROM UndergraduateCourseConcurrent node JOIN walk ON node.parent = walk.id
+)
+INSERT OR IGNORE INTO UndergraduateCourseDependents (course_id, dependent_id, kind, mandatory)
+SELECT req_course_id, dependent_id, 'concurrent', MAX(mandatory) FROM walk
+WHERE logic = 'C' AND req_course_id IS NOT NULL AND dependent_id IS NOT NULL
+GROUP BY req_course_id, dependent_id;
+
+WITH RECURSIVE walk (id, dependent_id, req_course_id, logic, mandatory, open) AS (
+  SELECT id, course_id, req_course_id, logic, 1, logic = '&' FROM UndergraduateCourseCorequisites WHERE parent IS NULL
+  UNION ALL
+  SELECT node.id, walk.dependent_id, node.req_course_id, node.logic, walk.open, walk.open AND node.logic = '&'
+  FROM UndergraduateCourseCorequisites node JOIN walk ON node.parent = walk.id
+)
+INSERT OR IGNORE INTO UndergraduateCourseDependents (course_id, dependent_id, kind, mandatory)
+SELECT req_course_id, dependent_id, 'corequisite', MAX(mandatory) FROM walk
+WHERE logic = 'C' AND req_course_id IS NOT NULL AND dependent_id IS NOT NULL
+GROUP BY req_course_id, dependent_id;
+
+WITH RECURSIVE walk (id, dependent_id, req_course_id, logic, mandatory, open) AS (
+  SELECT id, course_id, req_course_id, logic, 1, logic = '&' FROM UndergraduateCourseRecommended WHERE parent IS NULL
+  UNION ALL
+  SELECT node.id, walk.dependent_id, node.req_course_id, node.logic, walk.open, walk.open AND node.logic = '&'
+  FROM UndergraduateCourseRecommended node JOIN walk ON node.parent = walk.id
+)
+INSERT OR IGNORE INTO UndergraduateCourseDependents (course_id, dependent_id, kind, mandatory)
+SELECT req_course_id, dependent_id, 'recommended', MAX(mandatory) FROM walk
+WHERE logic = 'C' AND req_course_id IS NOT NULL AND dependent_id IS NOT NULL
+GROUP BY req_course_id, dependent_id;
@@ -0,0 +1,28 @@
+-- what a program's own page adds to its card, one row per program
+CREATE TABLE IF NOT EXISTS UndergraduateProgramDetails (
+  id INTEGER PRIMARY KEY,
+  program_id INT NOT NULL UNIQUE,
+  overview TEXT,
+  entrance_requirements TEXT, -- "How to Get In" tab
+  contact TEXT,
+
+  FOREIGN KEY (program_id) REFERENCES UndergraduateProgram (id)
+);
+
+-- rows of the degree requirement tables, position keeps them in page order.
+-- identifier is text (e.g. "MATH 140"), the course may not be synced
+CREATE TABLE IF NOT EXISTS UndergraduateProgramRequirement (
+  id INTEGER PRIMARY KEY,
+  program_id INT NOT NULL,
+  position INT NOT NULL,
+  section VARCHAR(255),
+  identifier VARCHAR(255),
+  title TEXT NOT NULL,
+  credits REAL,
+  min_credits REAL,
+  is_alternative BOOLEAN NOT NULL, -- "or" row, can replace the row above it
+
+  FOREIGN KEY (program_id) REFERENCES UndergraduateProgram (id)
+);
+
+CREATE INDEX IF NOT EXISTS UndergraduateProgramRequirement_program_id ON UndergraduateProgramRequirement (program_id);
@@ -1,10 +1,57 @@
-use std::fmt;
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+};
 
-use ego_tree::{NodeId, NodeRef, Tree};
+use ego_tree::{NodeMut, NodeRef, Tree};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{error::Error, requirement};
 
 pub const BASE_URL: &str = "https://bulletins.psu.edu";
 
-#[derive(Debug)]
+/// Serializes the type as its `Display` string and deserializes it with its `TryFrom<&str>`,
+/// e.g. "MATH 140H" for a `CourseIdentifier`
+macro_rules! serde_as_string {
+    ($($type:ty),*) => {$(
+        impl Serialize for $type {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $type {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = String::deserialize(deserializer)?;
+                Self::try_from(value.as_str()).map_err(serde::de::Error::custom)
+            }
+        }
+    )*};
+}
+
+/// Serializes the list as the names in it, e.g. `["Abington", "World Campus"]`
+macro_rules! serde_as_names {
+    ($($list:ident),*) => {$(
+        impl<const T: usize> Serialize for $list<T> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_seq(self.disassemble())
+            }
+        }
+
+        impl<'de, const T: usize> Deserialize<'de> for $list<T> {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let names = Vec::<String>::deserialize(deserializer)?;
+                let names: Vec<&str> = names.iter().map(String::as_str).collect();
+                Self::try_from(names.as_slice()).map_err(serde::de::Error::custom)
+            }
+        }
+    )*};
+}
+
+serde_as_string!(College, CourseIdentifier, Grade);
+serde_as_names!(CampusList, AttributeList);
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct UndergraduateProgram {
     pub title: String,
     pub link: String,
@@ -16,11 +63,33 @@ pub struct UndergraduateProgram {
                                   // pub field: Option<String>,
 }
 
+/// What a program's own page adds to its card on the program listing
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UndergraduateProgramDetails {
+    pub link: String, // same as `UndergraduateProgram::link`
+    pub overview: Option<String>,
+    pub entrance_requirements: Option<String>, // "How to Get In" tab, entrance to major
+    pub contact: Option<String>,
+    pub requirements: Vec<ProgramRequirement>, // in page order
+}
+
+/// A row of a degree requirement table, e.g. "MATH 140 Calculus With Analytic Geometry I 4"
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProgramRequirement {
+    pub section: Option<String>, // heading of the rows, e.g. "Prescribed Courses"
+    pub course: Option<CourseIdentifier>, // `None` for rows like "Select 6 credits of GN"
+    pub title: String,
+    pub credits: Option<f32>,
+    pub min_credits: Option<f32>, // some rows have a credit range
+    pub is_alternative: bool,     // "or" row, can replace the row above it
+}
+
 type CampusListFull = CampusList<21>; // 21 campuses
 type RawProgramType = String; // TODO: better name?
 
 #[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "details", rename_all = "snake_case")]
 pub enum UndergraduateProgramType {
     // degrees available at 21 campuses
     BaccalaureateDegree(RawProgramType, CampusListFull),
@@ -32,11 +101,29 @@ pub enum UndergraduateProgramType {
     ROTC(RawProgramType),
 }
 
+#[cfg(test)]
+impl UndergraduateProgram {
+    /// A baccalaureate degree of the Eberly College of Science offered at `campuses`
+    pub fn baccalaureate(title: &str, link: &str, campuses: &[&str]) -> Self {
+        Self {
+            title: title.into(),
+            link: link.into(),
+            program_type: UndergraduateProgramType::BaccalaureateDegree(
+                "B.S.".into(),
+                CampusListFull::try_from(campuses).unwrap(),
+            ),
+            image: format!("{}.jpg", link),
+            keywords: vec![title.to_lowercase()],
+            college: Some(College::Science),
+        }
+    }
+}
+
 impl fmt::Display for UndergraduateProgram {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut string_buffer = String::new();
 
-        string_buffer += &format!("{}", self.title);
+        string_buffer += &self.title.to_string();
         if let Some(ref college) = self.college {
             string_buffer += &format!(" [{}]", college);
         }
@@ -56,19 +143,20 @@ impl fmt::Display for UndergraduateProgram {
                 string_buffer += &format!("\n\t -> [{}] @ {}", degree_type, campus)
             }
             UndergraduateProgramType::Minor(_) => {
-                string_buffer += &format!("\n\t -> Minor");
+                string_buffer += "\n\t -> Minor";
             }
             UndergraduateProgramType::Certificate(_) => {
-                string_buffer += &format!("\n\t -> Certificate");
+                string_buffer += "\n\t -> Certificate";
             }
             UndergraduateProgramType::ROTC(_) => {
-                string_buffer += &format!("\n\t -> ROTC");
+                string_buffer += "\n\t -> ROTC";
             }
         }
         write!(f, "{}", string_buffer)
     }
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug)]
 pub enum College {
     AgriculturalSciences,
@@ -92,9 +180,10 @@ pub enum College {
     UniversityCollege,
 }
 
-impl From<&str> for College {
-    fn from(value: &str) -> Self {
-        match value {
+impl TryFrom<&str> for College {
+    type Error = Error;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value {
             "Agricultural Sciences" => College::AgriculturalSciences,
             "Arts and Architecture" => College::ArtsAndArchitecture,
             "Donald P. Bellisario College of Communications" => College::Communications,
@@ -114,8 +203,8 @@ impl From<&str> for College {
             "Penn State Harrisburg, The Capital College" => College::Capital,
             "Smeal College of Business" => College::Business,
             "University College" => College::UniversityCollege,
-            _ => panic!("Invalid College: {}. (Check noisy data)", value),
-        }
+            _ => return Err(Error::unknown("college", value)),
+        })
     }
 }
 
@@ -151,6 +240,7 @@ impl fmt::Display for College {
     }
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug)]
 pub enum Campus {
     Abington,
@@ -176,9 +266,10 @@ pub enum Campus {
     York,
 }
 
-impl From<&str> for Campus {
-    fn from(value: &str) -> Self {
-        match value {
+impl TryFrom<&str> for Campus {
+    type Error = Error;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value {
             "Abington" => Campus::Abington,
             "Altoona" => Campus::Altoona,
             "Beaver" => Campus::Beaver,
@@ -200,8 +291,8 @@ impl From<&str> for Campus {
             "Wilkes-Barre" => Campus::WilkesBarre,
             "World Campus" => Campus::WorldCampus,
             "York" => Campus::York,
-            _ => panic!("Invalid Campus: {}. (Check noisy data)", value),
-        }
+            _ => return Err(Error::unknown("campus", value)),
+        })
     }
 }
 
@@ -229,7 +320,7 @@ impl From<usize> for Campus {
             18 => Campus::WilkesBarre,
             19 => Campus::WorldCampus,
             20 => Campus::York,
-            _ => panic!("Invalid Campus: {}. (Check noisy data)", value),
+            _ => unreachable!("Invalid Campus index: {}", value),
         }
     }
 }
@@ -262,13 +353,12 @@ impl From<Campus> for &str {
     }
 }
 
-// TODO: implement this, add `from` with From trait
+// TODO: implement this
 // pub trait EnumList {
 //     fn new() -> Self;
-//     fn from(list: &[&str]) -> Self;
-//     fn contains(&self, item: &str) -> bool;
-//     fn add(&mut self, item: &str);
-//     fn remove(&mut self, item: &str);
+//     fn contains(&self, item: &str) -> Result<bool, Error>;
+//     fn add(&mut self, item: &str) -> Result<(), Error>;
+//     fn remove(&mut self, item: &str) -> Result<(), Error>;
 //     fn disassemble<'a>(&self) -> Vec<&'a str>;
 // }
 
@@ -283,33 +373,18 @@ impl<const T: usize> CampusList<T> {
         Self { list: [false; T] }
     }
 
-    pub fn from(list: &[&str]) -> Self {
-        let mut campus_list = Self { list: [false; T] };
-
-        for campus in list {
-            // TODO: USE CAMPUS::TRY_FROM INSTEAD
-            // ignore list
-            match *campus {
-                "Hershey Med Ctr" | "Nurses at Hershey" => continue,
-                _ => (),
-            }
-
-            campus_list.list[Campus::from(*campus) as usize] = true;
-        }
-
-        campus_list
-    }
-
-    pub fn contains(&self, item: &str) -> bool {
-        self.list[Campus::from(item) as usize]
+    pub fn contains(&self, item: &str) -> Result<bool, Error> {
+        Ok(self.list[Campus::try_from(item)? as usize])
     }
 
-    pub fn add(&mut self, item: &str) {
-        self.list[Campus::from(item) as usize] = true;
+    pub fn add(&mut self, item: &str) -> Result<(), Error> {
+        self.list[Campus::try_from(item)? as usize] = true;
+        Ok(())
     }
 
-    pub fn remove(&mut self, item: &str) {
-        self.list[Campus::from(item) as usize] = false;
+    pub fn remove(&mut self, item: &str) -> Result<(), Error> {
+        self.list[Campus::try_from(item)? as usize] = false;
+        Ok(())
     }
 
     pub fn disassemble<'a>(&self) -> Vec<&'a str> {
@@ -325,13 +400,32 @@ impl<const T: usize> CampusList<T> {
     }
 }
 
+impl<const T: usize> TryFrom<&[&str]> for CampusList<T> {
+    type Error = Error;
+    fn try_from(list: &[&str]) -> Result<Self, Self::Error> {
+        let mut campus_list = Self { list: [false; T] };
+
+        for campus in list {
+            // ignore list
+            match *campus {
+                "Hershey Med Ctr" | "Nurses at Hershey" => continue,
+                _ => (),
+            }
+
+            campus_list.add(campus)?;
+        }
+
+        Ok(campus_list)
+    }
+}
+
 impl<const T: usize> fmt::Display for CampusList<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.disassemble().join(", "))
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct CourseIdentifier {
     pub code: String,         // MATH
     pub number: u16,          // 140
@@ -339,7 +433,7 @@ pub struct CourseIdentifier {
 }
 
 impl TryFrom<&str> for CourseIdentifier {
-    type Error = &'static str;
+    type Error = Error;
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         let (code, number_suffix) = match value.split_once(|s| match s {
             // remove any non-breaking-spaces
@@ -347,12 +441,24 @@ impl TryFrom<&str> for CourseIdentifier {
             _ => false,
         }) {
             Some((code, number_suffix)) => (code, number_suffix),
-            None => return Err("Couldn't find a space in the course identifier."),
+            None => {
+                return Err(Error::malformed(
+                    "course identifier",
+                    value,
+                    "Couldn't find a space in the course identifier.",
+                ))
+            }
         };
 
         let last_number_suffix_char = match number_suffix.chars().last() {
             Some(last_number_suffix_char) => last_number_suffix_char,
-            None => return Err("Number suffix is empty."),
+            None => {
+                return Err(Error::malformed(
+                    "course identifier",
+                    value,
+                    "Number suffix is empty.",
+                ))
+            }
         };
 
         let (number, suffix): (&str, Option<char>) = if last_number_suffix_char.is_alphabetic() {
@@ -360,7 +466,13 @@ impl TryFrom<&str> for CourseIdentifier {
 
             let suffix = match suffix.chars().next() {
                 Some(suffix) => Some(suffix),
-                None => return Err("Suffix is empty."),
+                None => {
+                    return Err(Error::malformed(
+                        "course identifier",
+                        value,
+                        "Suffix is empty.",
+                    ))
+                }
             };
 
             (number, suffix)
@@ -370,12 +482,18 @@ impl TryFrom<&str> for CourseIdentifier {
 
         let number = match number.parse::<u16>() {
             Ok(number) => number,
-            Err(_) => return Err("Couldn't parse number."),
+            Err(_) => {
+                return Err(Error::malformed(
+                    "course identifier",
+                    value,
+                    "Couldn't parse number.",
+                ))
+            }
         };
 
         Ok(Self {
             code: code.into(),
-            number: number,
+            number,
             suffix,
         })
     }
@@ -391,9 +509,61 @@ impl fmt::Display for CourseIdentifier {
     }
 }
 
-pub type FullAttributeList = AttributeList<22>;
+/// Letter grade, ordered from worst to best so a grade meets a minimum if it's `>=` to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Grade {
+    F,
+    D,
+    C,
+    CPlus,
+    BMinus,
+    B,
+    BPlus,
+    AMinus,
+    A,
+}
+
+impl TryFrom<&str> for Grade {
+    type Error = Error;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.trim().to_ascii_uppercase().as_str() {
+            "A" => Ok(Grade::A),
+            "A-" => Ok(Grade::AMinus),
+            "B+" => Ok(Grade::BPlus),
+            "B" => Ok(Grade::B),
+            "B-" => Ok(Grade::BMinus),
+            "C+" => Ok(Grade::CPlus),
+            "C" => Ok(Grade::C),
+            "D" => Ok(Grade::D),
+            "F" => Ok(Grade::F),
+            _ => Err(Error::unknown("grade", value)),
+        }
+    }
+}
+
+impl fmt::Display for Grade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let grade = match self {
+            Grade::A => "A",
+            Grade::AMinus => "A-",
+            Grade::BPlus => "B+",
+            Grade::B => "B",
+            Grade::BMinus => "B-",
+            Grade::CPlus => "C+",
+            Grade::C => "C",
+            Grade::D => "D",
+            Grade::F => "F",
+        };
+
+        write!(f, "{}", grade)
+    }
+}
+
+pub const ATTRIBUTE_COUNT: usize = 22; // 22 attributes
+pub type FullAttributeList = AttributeList<ATTRIBUTE_COUNT>;
 // TODO: abstract into struct
 
+#[derive(Serialize, Deserialize)]
 pub struct UndergraduateCourseRequirements {
     pub prerequisites: Option<CourseRequirementTree>,
     pub concurrent: Option<CourseRequirementTree>,
@@ -401,11 +571,13 @@ pub struct UndergraduateCourseRequirements {
     pub recommended: Option<CourseRequirementTree>,
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct UndergraduateCourseDeviantFlags {
     pub empty_crosslist: bool,
     pub unknown_requirement: bool, // TODO: just extra string that cannot be parsed at the moment. Ex. "FIFTH SEMESTER STANDING"
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct UndergraduateCourseFlags {
     // mostly false.
     // most courses have prerequisite requirements AND concurrent requirements
@@ -414,6 +586,7 @@ pub struct UndergraduateCourseFlags {
     pub deviant: UndergraduateCourseDeviantFlags,
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct UndergraduateCourse {
     pub identifier: CourseIdentifier,
     pub title: String, // Calculus I
@@ -429,6 +602,79 @@ pub struct UndergraduateCourse {
     pub flags: UndergraduateCourseFlags,
 }
 
+#[cfg(test)]
+impl UndergraduateCourse {
+    /// A 3 credit course with only prerequisites, if any, e.g. "MATH 140 or MATH 140H"
+    pub fn with_prerequisites(identifier: &str, prerequisites: Option<&str>) -> Self {
+        Self {
+            identifier: CourseIdentifier::try_from(identifier).unwrap(),
+            title: identifier.into(),
+            description: None,
+            credits: 3.0,
+            min_credits: None,
+            attribute_list: FullAttributeList::new(),
+            crosslist: None,
+            requirements: UndergraduateCourseRequirements {
+                prerequisites: prerequisites
+                    .map(|requirement| CourseRequirementTree::try_from(requirement).unwrap()),
+                concurrent: None,
+                corequisites: None,
+                recommended: None,
+            },
+            flags: UndergraduateCourseFlags {
+                is_prerequisite_concurrent_separate: false,
+                deviant: UndergraduateCourseDeviantFlags {
+                    empty_crosslist: false,
+                    unknown_requirement: false,
+                },
+            },
+        }
+    }
+}
+
+/// Crosslisted courses are the same course under different codes, e.g. MATH 484 and CMPSC 484
+#[derive(Debug, Clone, Default)]
+pub struct Crosslists {
+    equivalents: HashMap<CourseIdentifier, BTreeSet<CourseIdentifier>>,
+}
+
+impl Crosslists {
+    pub fn new(courses: &[UndergraduateCourse]) -> Self {
+        let mut crosslists = Self::default();
+        crosslists.add(courses);
+        crosslists
+    }
+
+    /// Adds the crosslists of `courses`, either side listing the other is enough
+    pub fn add(&mut self, courses: &[UndergraduateCourse]) {
+        for course in courses {
+            for other in course.crosslist.iter().flatten() {
+                self.equivalents
+                    .entry(course.identifier.clone())
+                    .or_default()
+                    .insert(other.clone());
+                self.equivalents
+                    .entry(other.clone())
+                    .or_default()
+                    .insert(course.identifier.clone());
+            }
+        }
+    }
+
+    /// `course` and every course crosslisted with it
+    pub fn equivalents<'a>(
+        &'a self,
+        course: &'a CourseIdentifier,
+    ) -> impl Iterator<Item = &'a CourseIdentifier> {
+        std::iter::once(course).chain(self.equivalents.get(course).into_iter().flatten())
+    }
+
+    /// The same course for all of its crosslists, the first one alphabetically
+    pub fn canonical<'a>(&'a self, course: &'a CourseIdentifier) -> &'a CourseIdentifier {
+        self.equivalents(course).min().unwrap_or(course)
+    }
+}
+
 enum Attribute {
     // General Requirements
     GA,  // Arts
@@ -461,9 +707,44 @@ enum Attribute {
     HNR, // Honors
 }
 
-impl From<&str> for Attribute {
-    fn from(value: &str) -> Self {
-        match value {
+impl Attribute {
+    /// Short code of the attribute, e.g. "GN", also the name of its database column
+    fn code(&self) -> &'static str {
+        match self {
+            Attribute::GA => "GA",
+            Attribute::GHW => "GHW",
+            Attribute::GH => "GH",
+            Attribute::GN => "GN",
+            Attribute::GQ => "GQ",
+            Attribute::GS => "GS",
+            Attribute::GWS => "GWS",
+
+            Attribute::ITD => "ITD",
+            Attribute::LKD => "LKD",
+
+            Attribute::FYS => "FYS",
+            Attribute::IC => "IC",
+            Attribute::US => "US",
+            Attribute::WCC => "WCC",
+
+            Attribute::BA => "BA",
+            Attribute::BH => "BH",
+            Attribute::BN => "BN",
+            Attribute::BO => "BO",
+            Attribute::BQ => "BQ",
+            Attribute::BS => "BS",
+            Attribute::BF1 => "BF1",
+            Attribute::BF2 => "BF2",
+
+            Attribute::HNR => "HNR",
+        }
+    }
+}
+
+impl TryFrom<&str> for Attribute {
+    type Error = Error;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(match value {
             "General Education: Arts (GA)" => Attribute::GA,
             "General Education: Health and Wellness (GHW)" => Attribute::GHW,
             "General Education: Humanities (GH)" => Attribute::GH,
@@ -491,8 +772,8 @@ impl From<&str> for Attribute {
 
             "Honors" => Attribute::HNR,
 
-            _ => panic!("Invalid Attribute: {}. (Check noisy data)", value),
-        }
+            _ => return Err(Error::unknown("attribute", value)),
+        })
     }
 }
 
@@ -526,7 +807,7 @@ impl From<usize> for Attribute {
 
             21 => Attribute::HNR,
 
-            _ => panic!("Invalid Attribute: {}. (Check noisy data)", value),
+            _ => unreachable!("Invalid Attribute index: {}", value),
         }
     }
 }
@@ -575,30 +856,30 @@ impl<const T: usize> AttributeList<T> {
         Self { list: [false; T] }
     }
 
-    pub fn from(list: &[&str]) -> Self {
-        let mut attr_list = Self { list: [false; T] };
-
-        for attr in list {
-            attr_list.list[Attribute::from(*attr) as usize] = true;
-        }
-
-        attr_list
-    }
-
     pub fn get(&self, idx: usize) -> bool {
         self.list[idx]
     }
 
-    pub fn contains(&self, item: &str) -> bool {
-        self.list[Attribute::from(item) as usize]
+    pub fn contains(&self, item: &str) -> Result<bool, Error> {
+        Ok(self.list[Attribute::try_from(item)? as usize])
+    }
+
+    /// Same as `contains`, with the attribute's short code, e.g. "GN" or "gws"
+    pub fn contains_code(&self, code: &str) -> Result<bool, Error> {
+        match (0..T).find(|&idx| Attribute::from(idx).code().eq_ignore_ascii_case(code)) {
+            Some(idx) => Ok(self.list[idx]),
+            None => Err(Error::unknown("attribute", code)),
+        }
     }
 
-    pub fn add(&mut self, item: &str) {
-        self.list[Attribute::from(item) as usize] = true;
+    pub fn add(&mut self, item: &str) -> Result<(), Error> {
+        self.list[Attribute::try_from(item)? as usize] = true;
+        Ok(())
     }
 
-    pub fn remove(&mut self, item: &str) {
-        self.list[Attribute::from(item) as usize] = false;
+    pub fn remove(&mut self, item: &str) -> Result<(), Error> {
+        self.list[Attribute::try_from(item)? as usize] = false;
+        Ok(())
     }
 
     pub fn clear(&mut self) {
@@ -609,6 +890,11 @@ impl<const T: usize> AttributeList<T> {
         self.list.iter().all(|&x| !x)
     }
 
+    /// Name of every attribute, in the order of the list
+    pub fn names<'a>() -> Vec<&'a str> {
+        (0..T).map(|idx| Attribute::from(idx).into()).collect()
+    }
+
     pub fn disassemble<'a>(&self) -> Vec<&'a str> {
         let mut result: Vec<&str> = Vec::new();
 
@@ -622,170 +908,364 @@ impl<const T: usize> AttributeList<T> {
     }
 }
 
+impl<const T: usize> TryFrom<&[&str]> for AttributeList<T> {
+    type Error = Error;
+    fn try_from(list: &[&str]) -> Result<Self, Self::Error> {
+        let mut attr_list = Self { list: [false; T] };
+
+        for attr in list {
+            attr_list.add(attr)?;
+        }
+
+        Ok(attr_list)
+    }
+}
+
 impl<const T: usize> fmt::Display for AttributeList<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.disassemble().join(", "))
     }
 }
 
-#[derive(Debug, Clone)]
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum CourseRequirementNode {
     AND,
     OR,
-    COURSE(CourseIdentifier),
+    /// At least this many of the requirements below it, OR is the same as `AT_LEAST(1)`
+    AT_LEAST(u8),
+    /// A course, with the minimum grade needed in it if there is one
+    COURSE(CourseIdentifier, Option<Grade>),
+    /// Semester standing, e.g. 5 for "FIFTH SEMESTER STANDING"
+    STANDING(u8),
+    /// Enrollment in a major or program, e.g. "MECHANICAL ENGINEERING MAJOR"
+    ENROLLMENT(String),
+    /// Whose permission is needed, e.g. "INSTRUCTOR" or "PROGRAM"
+    PERMISSION(String),
+    /// Anything the parser couldn't make sense of, kept as is
+    TEXT(String),
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CourseRequirementTree {
     pub tree: Tree<CourseRequirementNode>,
 }
 
-impl CourseRequirementTree {
-    fn construct(tokens: &[String]) -> Self {
-        // root always starts with AND
-        let mut tree = ego_tree::Tree::new(CourseRequirementNode::AND);
-        let mut tree_depth_stack: Vec<NodeId> = Vec::new(); // LIFO
-
-        // push root to stack
-        tree_depth_stack.push(tree.root().id());
-
-        // check mode & create tree
-        for text in tokens {
-            // last node in stack
-            let mut parent = tree.get_mut(*tree_depth_stack.last().unwrap()).unwrap();
+impl TryFrom<&str> for CourseRequirementTree {
+    type Error = Error;
+    /// Parses a single requirement, e.g. "MATH 140 OR (MATH 141 AND PHYS 211)".
+    /// See `requirement` for the grammar.
+    fn try_from(requirement: &str) -> Result<Self, Self::Error> {
+        requirement::parse_tree(requirement)
+    }
+}
 
-            // // some text here may be wrapped in `{}`
-            // let text = text.trim_matches('{').trim_end_matches("}");
+impl fmt::Display for CourseRequirementNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CourseRequirementNode::AND => write!(f, "AND"),
+            CourseRequirementNode::OR => write!(f, "OR"),
+            CourseRequirementNode::AT_LEAST(count) => write!(f, "AT LEAST {} OF", count),
+            CourseRequirementNode::COURSE(course_identifier, None) => {
+                write!(f, "{}", course_identifier)
+            }
+            CourseRequirementNode::COURSE(course_identifier, Some(grade)) => {
+                write!(f, "{} OR BETTER IN {}", grade, course_identifier)
+            }
+            CourseRequirementNode::STANDING(semester) => {
+                let suffix = match (semester % 100, semester % 10) {
+                    (11..=13, _) => "TH",
+                    (_, 1) => "ST",
+                    (_, 2) => "ND",
+                    (_, 3) => "RD",
+                    _ => "TH",
+                };
+                write!(f, "{}{} SEMESTER STANDING", semester, suffix)
+            }
+            CourseRequirementNode::ENROLLMENT(program) => write!(f, "ENROLLMENT IN {}", program),
+            CourseRequirementNode::PERMISSION(whom) => write!(f, "PERMISSION OF {}", whom),
+            CourseRequirementNode::TEXT(text) => write!(f, "{}", text),
+        }
+    }
+}
 
-            match text.as_str() {
-                "(" | "[" => {
-                    // create new stack
-                    // by default AND, however, once OR is encountered, node is changed to OR
-                    let node = parent.append(CourseRequirementNode::AND);
-                    // push ID
-                    tree_depth_stack.push(node.id());
+impl fmt::Display for CourseRequirementTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // "MATH 140 OR [MATH 141 AND PHYS 121] OR 5TH SEMESTER STANDING"
+        fn node_to_string(node: &NodeRef<CourseRequirementNode>) -> String {
+            let separator = match node.value() {
+                CourseRequirementNode::AND => " AND ",
+                CourseRequirementNode::OR | CourseRequirementNode::AT_LEAST(_) => " OR ",
+                // the grade would otherwise read as if it applied to its siblings too
+                CourseRequirementNode::COURSE(_, Some(_))
+                    if node.parent().map_or(0, |parent| parent.children().count()) > 1 =>
+                {
+                    return format!("[{}]", node.value())
                 }
+                leaf => return leaf.to_string(),
+            };
 
-                ")" | "]" => {
-                    // pop stack
-                    tree_depth_stack.pop();
-                }
+            let mut buffer: Vec<String> = node
+                .children()
+                .map(|child| node_to_string(&child))
+                .collect();
+            buffer.retain(|s| !s.trim().is_empty()); // remove empty strings
 
-                "|" => {
-                    // get closest node and change it to OR
-                    let parent_value = parent.value();
-                    *parent_value = CourseRequirementNode::OR;
+            // ignore root or if less than 2 requirements are in array
+            let should_wrap = node.parent().is_none() || buffer.len() < 2;
+            match node.value() {
+                CourseRequirementNode::AT_LEAST(_) if buffer.is_empty() => String::new(),
+                // always wrapped, the options would otherwise run into whatever comes after
+                CourseRequirementNode::AT_LEAST(count) => {
+                    format!("AT LEAST {} OF ({})", count, buffer.join(separator))
                 }
-
-                "&" => {
-                    // get closest node and change it to AND
-                    let parent_value = parent.value();
-                    *parent_value = CourseRequirementNode::AND;
+                CourseRequirementNode::AND if !should_wrap => {
+                    format!("[{}]", buffer.join(separator))
                 }
-
-                // course
-                _ => {
-                    // push to closest node
-                    parent.append(CourseRequirementNode::COURSE(
-                        text.as_str().try_into().unwrap(),
-                    ));
+                CourseRequirementNode::OR if !should_wrap => {
+                    format!("({})", buffer.join(separator))
                 }
+                _ => buffer.join(separator),
             }
         }
-        Self { tree }
+
+        // TODO: remove later?
+
+        write!(f, "{}", node_to_string(&self.tree.root()))
     }
 }
 
-impl TryFrom<&str> for CourseRequirementTree {
-    type Error = &'static str;
-    /// All non-logical characters (!= OR, AND, (, ), [, ]) should be wrapped with curly braces
-    /// E.g. ({MATH 140}AND { MATH 360 })
-    fn try_from(requirements_string: &str) -> Result<Self, Self::Error> {
-        // tokenize requirements_string
-        let mut tokens = Vec::<String>::new();
-        let mut token_buffer = String::new();
-        let mut is_inside_escaped = false; // if current cursor is inside `{}`
-
-        for char in requirements_string.chars() {
-            if is_inside_escaped && char == '}' {
-                // trim, push and flush buffer
-                tokens.push(token_buffer.trim().to_string());
-                token_buffer.clear();
-                // set `is_inside_escape` flag to false
-                is_inside_escaped = false;
-            } else if is_inside_escaped {
-                token_buffer.push(char);
-            } else {
-                match char {
-                    '{' => {
-                        // set `is_inside_escape` flag to true
-                        is_inside_escaped = true;
-                    }
-                    '}' => {
-                        return Err("Unmatched closing brace");
-                    }
-                    '|' | '&' | '(' | ')' | '[' | ']' => {
-                        // push token
-                        tokens.push(char.to_string());
-                    }
-                    // ignore everything else
-                    _ => (),
-                }
+/// A requirement tree node as it's serialized, e.g.
+/// `{"type": "or", "children": [{"type": "course", "course": "MATH 140"}, ...]}`
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SerializedNode {
+    And {
+        children: Vec<SerializedNode>,
+    },
+    Or {
+        children: Vec<SerializedNode>,
+    },
+    AtLeast {
+        count: u8,
+        children: Vec<SerializedNode>,
+    },
+    Course {
+        course: CourseIdentifier,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min_grade: Option<Grade>,
+    },
+    Standing {
+        semester: u8,
+    },
+    Enrollment {
+        program: String,
+    },
+    Permission {
+        whom: String,
+    },
+    Text {
+        text: String,
+    },
+}
+
+impl SerializedNode {
+    fn from_ref(node: NodeRef<CourseRequirementNode>) -> Self {
+        let children = || node.children().map(SerializedNode::from_ref).collect();
+
+        match node.value() {
+            CourseRequirementNode::AND => SerializedNode::And {
+                children: children(),
+            },
+            CourseRequirementNode::OR => SerializedNode::Or {
+                children: children(),
+            },
+            CourseRequirementNode::AT_LEAST(count) => SerializedNode::AtLeast {
+                count: *count,
+                children: children(),
+            },
+            CourseRequirementNode::COURSE(course, min_grade) => SerializedNode::Course {
+                course: course.clone(),
+                min_grade: *min_grade,
+            },
+            CourseRequirementNode::STANDING(semester) => SerializedNode::Standing {
+                semester: *semester,
+            },
+            CourseRequirementNode::ENROLLMENT(program) => SerializedNode::Enrollment {
+                program: program.clone(),
+            },
+            CourseRequirementNode::PERMISSION(whom) => {
+                SerializedNode::Permission { whom: whom.clone() }
+            }
+            CourseRequirementNode::TEXT(text) => SerializedNode::Text { text: text.clone() },
+        }
+    }
+
+    /// The node's value and its children
+    fn into_parts(self) -> (CourseRequirementNode, Vec<SerializedNode>) {
+        match self {
+            SerializedNode::And { children } => (CourseRequirementNode::AND, children),
+            SerializedNode::Or { children } => (CourseRequirementNode::OR, children),
+            SerializedNode::AtLeast { count, children } => {
+                (CourseRequirementNode::AT_LEAST(count), children)
+            }
+            SerializedNode::Course { course, min_grade } => {
+                (CourseRequirementNode::COURSE(course, min_grade), vec![])
+            }
+            SerializedNode::Standing { semester } => {
+                (CourseRequirementNode::STANDING(semester), vec![])
             }
+            SerializedNode::Enrollment { program } => {
+                (CourseRequirementNode::ENROLLMENT(program), vec![])
+            }
+            SerializedNode::Permission { whom } => {
+                (CourseRequirementNode::PERMISSION(whom), vec![])
+            }
+            SerializedNode::Text { text } => (CourseRequirementNode::TEXT(text), vec![]),
         }
+    }
 
-        Ok(Self::construct(&tokens))
+    fn append_to(self, parent: &mut NodeMut<CourseRequirementNode>) {
+        let (value, children) = self.into_parts();
+
+        let mut node = parent.append(value);
+        for child in children {
+            child.append_to(&mut node);
+        }
     }
 }
 
-impl fmt::Display for CourseRequirementTree {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // TODO:    fix bug:   "{MATH 140}|{MATH 141}&{PHYS 121}"
-        //          expected:   MATH 140 OR (MATH 141 AND PHYS 121)
-        //          result:     MATH 140 AND MATH 141 AND PHYS 121
-        fn node_to_string(node: &NodeRef<CourseRequirementNode>) -> String {
-            let mut buffer = Vec::<String>::new();
+impl Serialize for CourseRequirementTree {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedNode::from_ref(self.tree.root()).serialize(serializer)
+    }
+}
 
-            // if node doesn't have children just return empty string
-            if !node.has_children() {
-                return String::new();
+impl<'de> Deserialize<'de> for CourseRequirementTree {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (root, children) = SerializedNode::deserialize(deserializer)?.into_parts();
+
+        let mut tree = match root {
+            CourseRequirementNode::AND
+            | CourseRequirementNode::OR
+            | CourseRequirementNode::AT_LEAST(_) => Tree::new(root),
+            // the root is always a group, a lone requirement gets wrapped in an AND
+            leaf => {
+                let mut tree = Tree::new(CourseRequirementNode::AND);
+                tree.root_mut().append(leaf);
+                tree
             }
+        };
 
-            // go through each child
-            for child in node.children() {
-                // recursively call this function on types that have AND or OR types
-                // else push anything else to current buffer
-                match child.value() {
-                    CourseRequirementNode::AND | CourseRequirementNode::OR => {
-                        buffer.push(node_to_string(&child));
-                    }
-                    CourseRequirementNode::COURSE(course_identifier) => {
-                        buffer.push(course_identifier.to_string());
-                    }
-                }
-            }
+        for child in children {
+            child.append_to(&mut tree.root_mut());
+        }
 
-            buffer.retain(|s| !s.trim().is_empty()); // remove empty strings
+        Ok(CourseRequirementTree { tree })
+    }
+}
 
-            let should_wrap = node.parent().is_none() || buffer.len() < 2;
-            match node.value() {
-                CourseRequirementNode::AND => {
-                    match should_wrap {
-                        true => format!("{}", buffer.join(" AND ")), // ignore root or if less than 2 courses are in array
-                        false => format!("[{}]", buffer.join(" AND ")),
-                    }
-                }
-                CourseRequirementNode::OR => match should_wrap {
-                    true => format!("{}", buffer.join(" OR ")),
-                    false => format!("({})", buffer.join(" OR ")),
-                },
-                CourseRequirementNode::COURSE(course_identifier) => {
-                    panic!("Node value {} is not AND or OR", course_identifier)
-                }
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
 
-        // TODO: remove later?
+    use super::*;
 
-        write!(f, "{}", node_to_string(&self.tree.root()))
+    /// Deserializes what `value` serializes to and serializes it again, which has to give back
+    /// the same JSON
+    fn round_trip<T: Serialize + for<'de> Deserialize<'de>>(value: &T) -> (serde_json::Value, T) {
+        let serialized = serde_json::to_value(value).unwrap();
+        let deserialized: T = serde_json::from_value(serialized.clone()).unwrap();
+        assert_eq!(serialized, serde_json::to_value(&deserialized).unwrap());
+
+        (serialized, deserialized)
+    }
+
+    #[test]
+    fn programs_round_trip() {
+        let program = UndergraduateProgram::baccalaureate(
+            "Mathematics",
+            "/undergraduate/colleges/science/mathematics-bs/",
+            &["University Park", "Abington"],
+        );
+
+        let (serialized, deserialized) = round_trip(&program);
+
+        assert_eq!(
+            json!({"kind": "baccalaureate_degree", "details": ["B.S.", ["Abington", "University Park"]]}),
+            serialized["program_type"]
+        );
+        assert_eq!(program.title, deserialized.title);
+        assert_eq!(program.link, deserialized.link);
+        assert_eq!(program.keywords, deserialized.keywords);
+        assert_eq!(
+            program.college.map(|college| college.to_string()),
+            deserialized.college.map(|college| college.to_string())
+        );
+    }
+
+    #[test]
+    fn courses_round_trip() {
+        let mut course = UndergraduateCourse::with_prerequisites(
+            "MATH 484",
+            Some("C or better in MATH 230 or MATH 231"),
+        );
+        course.min_credits = Some(1.0);
+        course
+            .attribute_list
+            .add("General Education: Quantification (GQ)")
+            .unwrap();
+        course.crosslist = Some(vec![CourseIdentifier::try_from("CMPSC 484").unwrap()]);
+        course.requirements.concurrent = Some(CourseRequirementTree::try_from("STAT 200").unwrap());
+
+        let (serialized, deserialized) = round_trip(&course);
+
+        assert_eq!(json!("MATH 484"), serialized["identifier"]);
+        assert_eq!(json!(["CMPSC 484"]), serialized["crosslist"]);
+        assert_eq!(course.identifier, deserialized.identifier);
+        assert_eq!(course.min_credits, deserialized.min_credits);
+        assert_eq!(course.crosslist, deserialized.crosslist);
+        assert_eq!(
+            course.attribute_list.disassemble(),
+            deserialized.attribute_list.disassemble()
+        );
+        assert_eq!(
+            course.requirements.prerequisites,
+            deserialized.requirements.prerequisites
+        );
+        assert_eq!(
+            course.requirements.concurrent,
+            deserialized.requirements.concurrent
+        );
+        assert!(deserialized.requirements.corequisites.is_none());
+    }
+
+    #[test]
+    fn requirement_trees_round_trip() {
+        let tree = CourseRequirementTree::try_from(
+            "C or better in MATH 140 and at least 2 of the following: (MATH 220, MATH 230, MATH 231) or 5th semester standing or permission of instructor or prior exposure to R",
+        )
+        .unwrap();
+
+        let (serialized, deserialized) = round_trip(&tree);
+        assert_eq!(tree, deserialized);
+
+        let (serialized_leaf, _) =
+            round_trip(&CourseRequirementTree::try_from("MATH 140").unwrap());
+        assert_eq!(
+            json!({"type": "and", "children": [{"type": "course", "course": "MATH 140"}]}),
+            serialized_leaf
+        );
+        assert_eq!(json!("or"), serialized["type"]);
+        assert_eq!(
+            json!({"type": "course", "course": "MATH 140", "min_grade": "C"}),
+            serialized["children"][0]["children"][0]
+        );
+
+        // trees written by hand may start with a lone requirement, which still gets a group
+        let tree: CourseRequirementTree =
+            serde_json::from_value(json!({"type": "standing", "semester": 5})).unwrap();
+        assert_eq!("5TH SEMESTER STANDING", tree.to_string());
+        assert_eq!(CourseRequirementNode::AND, *tree.tree.root().value());
     }
 }
@@ -0,0 +1,140 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::{database::StalePolicy, export::ExportFormat, fetch::DEFAULT_CACHE_TTL};
+
+/// Scrapes the Penn State undergraduate bulletin into a SQLite database
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+
+    /// SQLite database to sync into
+    #[arg(long, global = true, default_value = "./bulletin.db")]
+    pub database: String,
+
+    /// Directory where fetched pages are cached
+    #[arg(long, global = true, default_value = "./cache")]
+    pub cache_dir: PathBuf,
+
+    /// Hours before a cached page is fetched again
+    #[arg(long, global = true, default_value_t = DEFAULT_CACHE_TTL.as_secs() / 3600)]
+    pub cache_ttl: u64,
+
+    /// Only read pages already saved in the cache directory, never the live site
+    #[arg(long, global = true)]
+    pub offline: bool,
+
+    /// What to do with stored courses and programs that are no longer in the bulletin
+    #[arg(long, global = true, value_enum, default_value_t = StalePolicy::default())]
+    pub stale: StalePolicy,
+
+    /// Skip records that can't be scraped and list them in this report (.json or .md)
+    #[arg(long, global = true)]
+    pub report: Option<PathBuf>,
+
+    /// Also write the scraped programs and courses to this directory (programs.json, courses.json, ...)
+    #[arg(long, global = true)]
+    pub export: Option<PathBuf>,
+
+    /// Format of the exported files
+    #[arg(long, global = true, value_enum, default_value_t = ExportFormat::default())]
+    pub export_format: ExportFormat,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Scrape and sync every undergraduate program
+    Programs {
+        /// Also scrape the page of every program (overview, entrance to major, contact, degree requirements)
+        #[arg(long)]
+        details: bool,
+    },
+    /// Scrape and sync undergraduate courses
+    Courses {
+        /// Only scrape these subject codes (e.g. --subject MATH CMPSC)
+        #[arg(long = "subject", num_args = 1..)]
+        subjects: Vec<String>,
+    },
+    /// Scrape and sync every program and course
+    All {
+        /// Also scrape the page of every program (overview, entrance to major, contact, degree requirements)
+        #[arg(long)]
+        details: bool,
+    },
+    /// Scrape courses and draw their prerequisites as a Graphviz DOT graph
+    Graph {
+        /// Only draw the prerequisites of these subject codes (e.g. --subject MATH CMPSC)
+        #[arg(long = "subject", num_args = 1.., conflicts_with = "root")]
+        subjects: Vec<String>,
+        /// Only draw this course and what it needs (e.g. --root "CMPSC 465")
+        #[arg(long)]
+        root: Option<String>,
+        /// How many courses deep to follow the prerequisites of --root
+        #[arg(long, requires = "root")]
+        depth: Option<usize>,
+        /// File to write the graph to, printed if not given
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// List the synced courses whose requirements mention a course, e.g. what taking it opens up
+    Unlocks {
+        /// Course identifier, e.g. "STAT 200"
+        course: String,
+    },
+    /// List the synced courses whose prerequisites a student meets, e.g. what to take next
+    Eligible {
+        /// Completed courses, with their grade if it matters (e.g. --completed "MATH 140:B" "CMPSC 121")
+        #[arg(long, short, num_args = 1..)]
+        completed: Vec<String>,
+        /// Semester standing, e.g. 3 for a student in their third semester
+        #[arg(long, default_value_t = 1)]
+        standing: u8,
+        /// Only list courses of these subject codes (e.g. --subject MATH CMPSC)
+        #[arg(long = "subject", num_args = 1..)]
+        subjects: Vec<String>,
+        /// Only list courses with one of these attributes (e.g. --attribute GN GWS)
+        #[arg(long = "attribute", num_args = 1..)]
+        attributes: Vec<String>,
+        /// Only list courses that can be taken for this many credits
+        #[arg(long)]
+        credits: Option<f32>,
+    },
+    /// Plan the semesters it takes a student to get to some synced courses
+    Plan {
+        /// Courses to get to (e.g. "CMPSC 465" "MATH 230")
+        #[arg(required_unless_present = "program")]
+        targets: Vec<String>,
+        /// Get to the degree requirement courses of a program synced with its details, by link
+        /// or the end of it (e.g. "math-bs")
+        #[arg(long)]
+        program: Option<String>,
+        /// Only take courses required by the programs offered at a campus (e.g. "University
+        /// Park"). The bulletin doesn't say where a course is taught, so electives and general
+        /// education courses no program there requires can't be planned for.
+        #[arg(long)]
+        campus: Option<String>,
+        /// Completed courses, with their grade if it matters (e.g. --completed "MATH 140:B" "CMPSC 121")
+        #[arg(long, short, num_args = 1..)]
+        completed: Vec<String>,
+        /// Semester standing of the first planned semester
+        #[arg(long, default_value_t = 1)]
+        standing: u8,
+        /// Most credits taken in a semester
+        #[arg(long, default_value_t = 18.0)]
+        max_credits: f32,
+    },
+    /// Manage the page cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// Delete every cached page
+    Clear,
+}
@@ -1,10 +1,79 @@
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fmt,
+};
+
 use ego_tree::NodeId;
-use libsql_client::{args, de, Statement};
+use libsql_client::{args, de, local::Client, ResultSet, Statement, Value};
+
+pub mod catalog;
+mod changes;
+pub mod dependents;
+mod migrations;
+
+use changes::ChangeLog;
+use dependents::{RequirementKind, REQUIREMENT_KINDS};
 
-use crate::bulletin::{self, CourseIdentifier, CourseRequirementTree, UndergraduateCourse, UndergraduateProgram};
+use crate::{
+    bulletin::{
+        self, CourseIdentifier, CourseRequirementTree, Crosslists, UndergraduateCourse,
+        UndergraduateProgram, UndergraduateProgramDetails,
+    },
+    error::{Context, Error, Result},
+    export::ExportFormat,
+    graph::CourseGraph,
+};
 
 pub trait Synchronizable<T> {
-    fn sync(url: &str, items: &[T]);
+    fn sync(url: &str, items: &[T], options: &SyncOptions) -> Result<SyncSummary>;
+}
+
+/// What a sync wrote, for the caller to report
+#[derive(Debug, Default)]
+pub struct SyncSummary {
+    /// Sync run the changes were recorded in, if the sync keeps a change log
+    pub run_id: Option<i64>,
+    /// Items inserted or updated
+    pub saved: usize,
+    /// Stored rows retired or deleted under the stale policy
+    pub removed: usize,
+    /// Field changes recorded in the change log
+    pub changes: usize,
+}
+
+/// What happens to stored rows that weren't found by the latest scrape
+#[derive(Clone, Copy, Debug, Default, clap::ValueEnum)]
+pub enum StalePolicy {
+    /// Leave them as they are
+    Keep,
+    /// Set their `retired_at` timestamp
+    #[default]
+    Retire,
+    /// Delete them along with their campuses, keywords, details, crosslists and requirements
+    Delete,
+}
+
+impl fmt::Display for StalePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StalePolicy::Keep => write!(f, "Kept"),
+            StalePolicy::Retire => write!(f, "Retired"),
+            StalePolicy::Delete => write!(f, "Deleted"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SyncOptions<'a> {
+    pub stale: StalePolicy,
+    /// Course subjects (e.g. "MATH") that were fully scraped. Only their courses can be
+    /// considered stale. `None` means every subject was scraped.
+    pub subjects: Option<Vec<String>>,
+    /// Requirement graph of the courses being synced, for their depth. Built by the sync
+    /// itself if the caller doesn't already have one.
+    pub graph: Option<&'a CourseGraph>,
+    /// Format the export syncs write their file in. The database ignores it.
+    pub export_format: ExportFormat,
 }
 
 pub struct SyncUndergraduatePrograms;
@@ -14,174 +83,269 @@ struct IdRecord {
     id: i64,
 }
 
-impl Synchronizable<UndergraduateProgram> for SyncUndergraduatePrograms {
-    fn sync(url: &str, items: &[UndergraduateProgram]) {
-        let db = match libsql_client::local::Client::new(url) {
-            Ok(db) => db,
-            Err(e) => panic!("{}", e),
-        };
+#[derive(serde::Deserialize)]
+struct CourseCodeRecord {
+    id: i64,
+    code: String,
+    identifier: String,
+}
 
-        // create tables if they don't already exist
-        match db.batch([
-            Statement::new(
-                r#"
-                    CREATE TABLE IF NOT EXISTS UndergraduateProgram (
-                        id INTEGER PRIMARY KEY,
-                        title VARCHAR(255) NOT NULL,
-                        link VARCHAR(255) NOT NULL UNIQUE,
-                        image VARCHAR(255),
-                        type_id INT NOT NULL,
-                        college_id INT, -- can be null
-                    
-                        FOREIGN KEY (type_id) REFERENCES UndergraduateProgramType(id),
-                        FOREIGN KEY (college_id) REFERENCES College(id)
-                    );
-                "#,
-            ),
-            // check if it exists, if not create it.
-            Statement::new(
-                r#"
-                    CREATE TABLE IF NOT EXISTS UndergraduateProgramType (
-                        id INTEGER PRIMARY KEY,
-                        type VARCHAR(255) UNIQUE NOT NULL
-                    );
-                "#,
-            ),
-            // check if it exists, if not create it.
-            Statement::new(
-                r#"
-                    CREATE TABLE IF NOT EXISTS College (
-                        id INTEGER PRIMARY KEY,
-                        name VARCHAR(255) UNIQUE NOT NULL
-                    );
-                "#,
-            ),
-            Statement::new(
-                r#"
-                    CREATE TABLE IF NOT EXISTS Keywords (
-                        id INTEGER PRIMARY KEY,
-                        keyword VARCHAR(255),
-                        program_id INT NOT NULL,
-                    
-                        FOREIGN KEY (program_id) REFERENCES UndergraduateProgram(id)
-                    );
-                "#,
-            ),
-            Statement::new(
-                r#"
-                    CREATE TABLE IF NOT EXISTS Campus (
-                        id INTEGER PRIMARY KEY,
-                        name VARCHAR(255),
-                        program_id INT NOT NULL,
-                    
-                        FOREIGN KEY (program_id) REFERENCES UndergraduateProgram(id)
-                    );
-                "#,
+#[derive(serde::Deserialize)]
+struct LabelRecord {
+    id: i64,
+    label: String,
+}
+
+/// Opens the database and brings its schema up to date
+fn connect(url: &str) -> Result<Client> {
+    let db = Client::new(url)
+        .map_err(Error::database)
+        .with_context(|| url)?;
+
+    // the caller reports migrations with `migrate`, there's nothing left to apply by now
+    migrations::migrate(&db).with_context(|| url)?;
+
+    Ok(db)
+}
+
+/// Brings the schema of the database at `url` up to date, returns the migrations that were
+/// applied, e.g. "1 (create program tables)"
+pub fn migrate(url: &str) -> Result<Vec<String>> {
+    let db = Client::new(url)
+        .map_err(Error::database)
+        .with_context(|| url)?;
+
+    migrations::migrate(&db).with_context(|| url)
+}
+
+/// An open transaction, rolled back when dropped without `commit` (an early return through `?`
+/// or a panic), so a failed sync leaves the previous database untouched
+struct Transaction<'a> {
+    db: &'a Client,
+    committed: bool,
+}
+
+impl<'a> Transaction<'a> {
+    fn begin(db: &'a Client) -> Result<Self> {
+        db.execute("BEGIN").map_err(Error::database)?;
+
+        Ok(Self {
+            db,
+            committed: false,
+        })
+    }
+
+    fn commit(mut self) -> Result<()> {
+        self.db.execute("COMMIT").map_err(Error::database)?;
+        self.committed = true;
+
+        Ok(())
+    }
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        if !self.committed {
+            // nothing else can be done if this fails, SQLite discards the
+            // uncommitted changes once the connection closes anyway
+            let _ = self.db.execute("ROLLBACK");
+        }
+    }
+}
+
+/// Runs `statements` in order. Unlike `Client::batch`, it doesn't open a transaction of its own,
+/// so it can be used inside a `Transaction`.
+fn execute_all(
+    db: &Client,
+    statements: impl IntoIterator<Item = Statement>,
+) -> Result<Vec<ResultSet>> {
+    statements
+        .into_iter()
+        .map(|statement| db.execute(statement).map_err(Error::database))
+        .collect()
+}
+
+/// Returns the `id` of the first row in `result`, if there is one
+fn first_id(result: Option<&ResultSet>) -> Result<Option<i64>> {
+    let rows = match result {
+        Some(result) => &result.rows,
+        None => return Ok(None),
+    };
+
+    Ok(rows
+        .iter()
+        .map(de::from_row)
+        .collect::<std::result::Result<Vec<IdRecord>, _>>()
+        .map_err(Error::database)?
+        .first()
+        .map(|record| record.id))
+}
+
+/// Looks up the database id of a course by its identifier
+fn find_course_id(db: &Client, course: &CourseIdentifier) -> Result<Option<i64>> {
+    let res = db
+        .execute(Statement::with_args(
+            match course.suffix {
+                Some(_) => "SELECT id FROM UndergraduateCourse WHERE code = ? AND number = ? AND suffix = ?",
+                None => "SELECT id FROM UndergraduateCourse WHERE code = ? AND number = ? AND suffix IS NULL",
+            },
+            args!(
+                course.code.clone(),
+                course.number,
+                course.suffix.map(|s| s.to_string())
             ),
-        ]) {
-            Ok(res) => res,
-            Err(e) => panic!("{}", e),
+        ))
+        .map_err(Error::database)?;
+
+    first_id(Some(&res))
+}
+
+impl Synchronizable<UndergraduateProgram> for SyncUndergraduatePrograms {
+    fn sync(
+        url: &str,
+        items: &[UndergraduateProgram],
+        options: &SyncOptions,
+    ) -> Result<SyncSummary> {
+        let db = connect(url)?;
+
+        // everything below is written at once, or not at all
+        let transaction = Transaction::begin(&db)?;
+
+        let run_id = changes::start_run(&db, "programs")?;
+        let mut summary = SyncSummary {
+            run_id: Some(run_id),
+            ..Default::default()
         };
 
+        // programs found by this scrape
+        let mut seen = HashSet::<i64>::new();
+
         for item in items {
             let (raw_type, campus_list): (&str, Option<Vec<&str>>) = match &item.program_type {
                 bulletin::UndergraduateProgramType::BaccalaureateDegree(raw_type, campus_list) => {
-                    (&raw_type, Some(campus_list.disassemble()))
+                    (raw_type, Some(campus_list.disassemble()))
                 }
                 bulletin::UndergraduateProgramType::AssociateDegree(raw_type, campus_list) => {
-                    (&raw_type, Some(campus_list.disassemble()))
+                    (raw_type, Some(campus_list.disassemble()))
                 }
-                bulletin::UndergraduateProgramType::Certificate(raw_type) => (&raw_type, None),
-                bulletin::UndergraduateProgramType::Minor(raw_type) => (&raw_type, None),
-                bulletin::UndergraduateProgramType::ROTC(raw_type) => (&raw_type, None),
+                bulletin::UndergraduateProgramType::Certificate(raw_type) => (raw_type, None),
+                bulletin::UndergraduateProgramType::Minor(raw_type) => (raw_type, None),
+                bulletin::UndergraduateProgramType::ROTC(raw_type) => (raw_type, None),
             };
 
             let undergraduate_program_type_id: i64 = {
-                let res = match db.batch([
-                    Statement::with_args(
-                        "INSERT OR IGNORE INTO UndergraduateProgramType (type) VALUES (?)",
-                        args!(raw_type),
-                    ),
-                    Statement::with_args(
-                        "SELECT id FROM UndergraduateProgramType WHERE type = ?",
-                        args!(raw_type),
-                    ),
-                ]) {
-                    Ok(res) => res,
-                    Err(e) => panic!("{}", e),
-                };
-
-                res.last()
-                    .unwrap()
-                    .rows
-                    .iter()
-                    .map(de::from_row)
-                    .collect::<Result<Vec<IdRecord>, _>>()
-                    .unwrap()
-                    .get(0)
-                    .unwrap()
-                    .id
+                let res = execute_all(
+                    &db,
+                    [
+                        Statement::with_args(
+                            "INSERT OR IGNORE INTO UndergraduateProgramType (type) VALUES (?)",
+                            args!(raw_type),
+                        ),
+                        Statement::with_args(
+                            "SELECT id FROM UndergraduateProgramType WHERE type = ?",
+                            args!(raw_type),
+                        ),
+                    ],
+                )?;
+
+                match first_id(res.last())? {
+                    Some(id) => id,
+                    None => {
+                        return Err(Error::database(
+                            "UndergraduateProgramType id couldn't be found.",
+                        ))
+                    }
+                }
             };
 
             let college_id: Option<i64> = match &item.college {
                 Some(college) => {
                     let college = &college.to_string();
-                    let res = match db.batch([
-                        Statement::with_args(
-                            "INSERT OR IGNORE INTO College (name) VALUES (?)",
-                            args!(college),
-                        ),
-                        Statement::with_args(
-                            "SELECT id FROM College WHERE name = ?",
-                            args!(college),
-                        ),
-                    ]) {
-                        Ok(res) => res,
-                        Err(e) => panic!("{}", e),
-                    };
+                    let res = execute_all(
+                        &db,
+                        [
+                            Statement::with_args(
+                                "INSERT OR IGNORE INTO College (name) VALUES (?)",
+                                args!(college),
+                            ),
+                            Statement::with_args(
+                                "SELECT id FROM College WHERE name = ?",
+                                args!(college),
+                            ),
+                        ],
+                    )?;
 
-                    Some(
-                        res.last()
-                            .unwrap()
-                            .rows
-                            .iter()
-                            .map(de::from_row)
-                            .collect::<Result<Vec<IdRecord>, _>>()
-                            .unwrap()
-                            .get(0)
-                            .unwrap()
-                            .id,
-                    )
+                    match first_id(res.last())? {
+                        Some(id) => Some(id),
+                        None => return Err(Error::database("College id couldn't be found.")),
+                    }
                 }
                 None => None,
             };
 
-            let program_res = match db.execute(Statement::with_args(
-                "INSERT OR IGNORE INTO UndergraduateProgram (title, link, image, type_id, college_id) VALUES (?, ?, ?, ?, ?)",
-                args!(
-                    *item.title,
-                    *item.link,
-                    *item.image,
-                    undergraduate_program_type_id,
-                    college_id,
-                ),
-            )) {
-                Ok(res) => res,
-                Err(e) => panic!("{}", e),
+            // stored state of the program, to diff against once it's updated
+            let before = match first_id(Some(
+                &db.execute(Statement::with_args(
+                    "SELECT id FROM UndergraduateProgram WHERE link = ?",
+                    args!(*item.link),
+                ))
+                .map_err(Error::database)?,
+            ))? {
+                Some(program_id) => Some(changes::program_snapshot(&db, program_id)?),
+                None => None,
             };
 
-            let program_id = match program_res.last_insert_rowid {
-                Some(id) => {
-                    if id == 0 {
-                        continue;
-                    } else {
-                        id
-                    }
-                }
+            // insert new programs, update the ones that are already stored (matched by link)
+            let program_res = execute_all(
+                &db,
+                [
+                    Statement::with_args(
+                        r#"INSERT INTO UndergraduateProgram (title, link, image, type_id, college_id) VALUES (?, ?, ?, ?, ?)
+                    ON CONFLICT (link) DO UPDATE SET
+                        title = excluded.title,
+                        image = excluded.image,
+                        type_id = excluded.type_id,
+                        college_id = excluded.college_id,
+                        retired_at = NULL"#,
+                        args!(
+                            *item.title,
+                            *item.link,
+                            *item.image,
+                            undergraduate_program_type_id,
+                            college_id,
+                        ),
+                    ),
+                    Statement::with_args(
+                        "SELECT id FROM UndergraduateProgram WHERE link = ?",
+                        args!(*item.link),
+                    ),
+                ],
+            )?;
+
+            let program_id = match first_id(program_res.last())? {
+                Some(id) => id,
                 None => {
-                    panic!("program_id couldn't be found.")
+                    return Err(Error::database("program_id couldn't be found."))
+                        .with_context(|| &item.link)
                 }
             };
+            seen.insert(program_id);
+            summary.saved += 1;
+
+            // campuses and keywords are replaced, not merged
+            execute_all(
+                &db,
+                [
+                    Statement::with_args(
+                        "DELETE FROM Campus WHERE program_id = ?",
+                        args!(program_id),
+                    ),
+                    Statement::with_args(
+                        "DELETE FROM Keywords WHERE program_id = ?",
+                        args!(program_id),
+                    ),
+                ],
+            )?;
 
             let mut batch_statements = Vec::new();
 
@@ -194,10 +358,7 @@ impl Synchronizable<UndergraduateProgram> for SyncUndergraduatePrograms {
                 }
             }
 
-            match db.batch(batch_statements) {
-                Ok(res) => res,
-                Err(e) => panic!("{}", e),
-            };
+            execute_all(&db, batch_statements)?;
 
             let mut batch_statements = Vec::new();
 
@@ -208,528 +369,904 @@ impl Synchronizable<UndergraduateProgram> for SyncUndergraduatePrograms {
                 ));
             }
 
-            match db.batch(batch_statements) {
-                Ok(res) => res,
-                Err(e) => panic!("{}", e),
+            execute_all(&db, batch_statements)?;
+
+            let after = changes::program_snapshot(&db, program_id)?;
+            summary.changes += changes::record_changes(
+                &db,
+                ChangeLog::Program,
+                run_id,
+                program_id,
+                &item.link,
+                before.as_ref(),
+                Some(&after),
+            )?;
+        }
+
+        remove_stale_programs(&db, run_id, &seen, options.stale, &mut summary)?;
+
+        changes::finish_run(&db, run_id)?;
+        transaction.commit()?;
+
+        Ok(summary)
+    }
+}
+
+pub struct SyncUndergraduateProgramDetails;
+
+impl Synchronizable<UndergraduateProgramDetails> for SyncUndergraduateProgramDetails {
+    /// Programs have to be synced first, details of a program that isn't stored are skipped.
+    /// Details of programs that weren't scraped are deleted with `StalePolicy::Delete`, and
+    /// left as they are otherwise (they go with their program when it's retired).
+    fn sync(
+        url: &str,
+        items: &[UndergraduateProgramDetails],
+        options: &SyncOptions,
+    ) -> Result<SyncSummary> {
+        let db = connect(url)?;
+
+        // everything below is written at once, or not at all
+        let transaction = Transaction::begin(&db)?;
+        let mut summary = SyncSummary::default();
+
+        // programs whose details were found by this scrape
+        let mut seen = HashSet::<i64>::new();
+
+        for item in items {
+            let program_id = match first_id(Some(
+                &db.execute(Statement::with_args(
+                    "SELECT id FROM UndergraduateProgram WHERE link = ?",
+                    args!(*item.link),
+                ))
+                .map_err(Error::database)?,
+            ))? {
+                Some(program_id) => program_id,
+                None => continue,
             };
+
+            // requirement rows are replaced, not merged
+            let mut statements = vec![
+                Statement::with_args(
+                    r#"INSERT INTO UndergraduateProgramDetails (program_id, overview, entrance_requirements, contact) VALUES (?, ?, ?, ?)
+                    ON CONFLICT (program_id) DO UPDATE SET
+                        overview = excluded.overview,
+                        entrance_requirements = excluded.entrance_requirements,
+                        contact = excluded.contact"#,
+                    args!(
+                        program_id,
+                        item.overview.clone(),
+                        item.entrance_requirements.clone(),
+                        item.contact.clone(),
+                    ),
+                ),
+                Statement::with_args(
+                    "DELETE FROM UndergraduateProgramRequirement WHERE program_id = ?",
+                    args!(program_id),
+                ),
+            ];
+
+            for (position, requirement) in item.requirements.iter().enumerate() {
+                statements.push(Statement::with_args(
+                    r#"INSERT INTO UndergraduateProgramRequirement (program_id, position, section, identifier, title, credits, min_credits, is_alternative)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?)"#,
+                    args!(
+                        program_id,
+                        position as i64,
+                        requirement.section.clone(),
+                        requirement.course.as_ref().map(|course| course.to_string()),
+                        *requirement.title,
+                        requirement.credits,
+                        requirement.min_credits,
+                        requirement.is_alternative as i64,
+                    ),
+                ));
+            }
+
+            execute_all(&db, statements)?;
+            seen.insert(program_id);
+            summary.saved += 1;
         }
 
-        println!("Sync complete.")
+        if let StalePolicy::Delete = options.stale {
+            let res = db
+                .execute("SELECT program_id AS id FROM UndergraduateProgramDetails")
+                .map_err(Error::database)?;
+            let stale: Vec<i64> = res
+                .rows
+                .iter()
+                .map(de::from_row)
+                .collect::<std::result::Result<Vec<IdRecord>, _>>()
+                .map_err(Error::database)?
+                .into_iter()
+                .map(|record| record.id)
+                .filter(|program_id| !seen.contains(program_id))
+                .collect();
+
+            for program_id in &stale {
+                execute_all(
+                    &db,
+                    [
+                        Statement::with_args(
+                            "DELETE FROM UndergraduateProgramDetails WHERE program_id = ?",
+                            args!(*program_id),
+                        ),
+                        Statement::with_args(
+                            "DELETE FROM UndergraduateProgramRequirement WHERE program_id = ?",
+                            args!(*program_id),
+                        ),
+                    ],
+                )?;
+            }
+
+            summary.removed = stale.len();
+        }
+
+        transaction.commit()?;
+
+        Ok(summary)
     }
 }
 
 pub struct SyncUndergraduateCourses;
 
-impl Synchronizable<UndergraduateCourse> for SyncUndergraduateCourses {
-    fn sync(url: &str, items: &[UndergraduateCourse]) {
-        let db = match libsql_client::local::Client::new(url) {
-            Ok(db) => db,
-            Err(e) => panic!("{}", e),
-        };
+/// Course fields stored as they are
+const FIELD_COLUMNS: [&str; 4] = ["title", "description", "credits", "min_credits"];
 
-        // create tables if they don't already exist
-        match db.batch([
-            // TODO: add safety attributes (UNIQUE, NOT NULL, etc)
-            Statement::new(
-                r#"
-                    CREATE TABLE IF NOT EXISTS UndergraduateCourse (
-                        id INTEGER PRIMARY KEY,
-                        code VARCHAR(5),
-                        number INT,
-                        suffix VARCHAR(1),
-                        title VARCHAR(255),
-                        description VARCHAR(65535),
-                        credits REAL,
-                        min_credits REAL,
-                        GA bool,
-                        GHW bool,
-                        GH bool,
-                        GN bool,
-                        GQ bool,
-                        GS bool,
-                        GWS bool,
-                        ITD bool,
-                        LKD bool,
-                        FYS bool,
-                        IC bool,
-                        US bool,
-                        WCC bool,
-                        BA bool,
-                        BH bool,
-                        BN bool,
-                        BO bool,
-                        BQ bool,
-                        BS bool,
-                        BF1 bool,
-                        BF2 bool,
-                        HNR bool,
-                        is_prerequisite_concurrent_separate bool,
-                        empty_crosslist bool,
-                        unknown_requirement bool,
-
-                        -- FOREIGN KEY (id) REFERENCES UndergraduateCourseCrossLists (course_id)
-                        -- FOREIGN KEY (id) REFERENCES UndergraduateCoursePrerequisites (course_id)
-                        -- FOREIGN KEY (id) REFERENCES UndergraduateCourseConcurrent (course_id)
-                        -- FOREIGN KEY (id) REFERENCES UndergraduateCourseCorequisites (course_id)
-                        -- FOREIGN KEY (id) REFERENCES UndergraduateCourseRecommended (course_id)
-                        UNIQUE (code, number, suffix)
-                    );
-                "#,
-            ),
-            Statement::new(
-                r#"
-                    CREATE TABLE IF NOT EXISTS UndergraduateCourseCrossLists (
-                        id INTEGER PRIMARY KEY,
-                        course_id INT,
-                        crossed_course_id INT,
-
-                        FOREIGN KEY (crossed_course_id) REFERENCES UndergraduateCourse (id)
-                    )
-                "#,
-            ),
-            // TODO: merge all requirements into one table with type key to differentiate?
-            Statement::new(
-                r#"
-                    CREATE TABLE IF NOT EXISTS UndergraduateCoursePrerequisites (
-                        id INTEGER PRIMARY KEY,
-                        logic VARCHAR(1),
-                        course_id INT,
-                        req_course_id INT,
-                        parent INT,
-
-                        FOREIGN KEY (req_course_id) REFERENCES UndergraduateCourse (id)
-                        FOREIGN KEY (parent) REFERENCES UndergraduateCoursePrerequisites (id)
+/// One flag per attribute, in `Attribute` order
+const ATTRIBUTE_COLUMNS: [&str; bulletin::ATTRIBUTE_COUNT] = [
+    "GA", "GHW", "GH", "GN", "GQ", "GS", "GWS", "ITD", "LKD", "FYS", "IC", "US", "WCC", "BA", "BH",
+    "BN", "BO", "BQ", "BS", "BF1", "BF2", "HNR",
+];
 
-                    )
-                "#,
-            ),
-            Statement::new(
-                r#"
-                    CREATE TABLE IF NOT EXISTS UndergraduateCourseConcurrent (
-                        id INTEGER PRIMARY KEY,
-                        logic VARCHAR(1),
-                        course_id INT,
-                        req_course_id INT,
-                        parent INT,
-
-                        FOREIGN KEY (req_course_id) REFERENCES UndergraduateCourse (id)
-                        FOREIGN KEY (parent) REFERENCES UndergraduateCourseConcurrent (id)
+/// In `UndergraduateCourseFlags` order
+const FLAG_COLUMNS: [&str; 3] = [
+    "is_prerequisite_concurrent_separate",
+    "empty_crosslist",
+    "unknown_requirement",
+];
 
-                    )
-                "#,
-            ),
-            Statement::new(
-                r#"
-                    CREATE TABLE IF NOT EXISTS UndergraduateCourseCorequisites (
-                        id INTEGER PRIMARY KEY,
-                        logic VARCHAR(1),
-                        course_id INT,
-                        req_course_id INT,
-                        parent INT,
-
-                        FOREIGN KEY (req_course_id) REFERENCES UndergraduateCourse (id)
-                        FOREIGN KEY (parent) REFERENCES UndergraduateCourseCorequisites (id)
+/// Every `UndergraduateCourse` column besides the id and the identifier: the fields, the
+/// attributes then the flags, in `course_values` order
+const COURSE_COLUMNS: [&str; FIELD_COLUMNS.len() + ATTRIBUTE_COLUMNS.len() + FLAG_COLUMNS.len()] = {
+    let groups: [&[&str]; 3] = [&FIELD_COLUMNS, &ATTRIBUTE_COLUMNS, &FLAG_COLUMNS];
+    let mut columns = [""; FIELD_COLUMNS.len() + ATTRIBUTE_COLUMNS.len() + FLAG_COLUMNS.len()];
 
-                    )
-                "#,
-            ),
-            Statement::new(
-                r#"
-                    CREATE TABLE IF NOT EXISTS UndergraduateCourseRecommended (
-                        id INTEGER PRIMARY KEY,
-                        logic VARCHAR(1),
-                        course_id INT,
-                        req_course_id INT,
-                        parent INT,
-
-                        FOREIGN KEY (req_course_id) REFERENCES UndergraduateCourse (id)
-                        FOREIGN KEY (parent) REFERENCES UndergraduateCourseRecommended (id)
+    let mut idx = 0;
+    let mut group = 0;
+    while group < groups.len() {
+        let mut column = 0;
+        while column < groups[group].len() {
+            columns[idx] = groups[group][column];
+            idx += 1;
+            column += 1;
+        }
+        group += 1;
+    }
 
-                    )
-                "#,
-            ),
-        ]) {
-            Ok(res) => res,
-            Err(e) => panic!("{}", e),
+    columns
+};
+
+/// Prerequisites, concurrent, corequisites and recommended, in `UndergraduateCourseRequirements` order
+const REQUIREMENT_TABLES: [&str; 4] = [
+    "UndergraduateCoursePrerequisites",
+    "UndergraduateCourseConcurrent",
+    "UndergraduateCourseCorequisites",
+    "UndergraduateCourseRecommended",
+];
+
+fn course_values(item: &UndergraduateCourse) -> Vec<Value> {
+    let mut values: Vec<Value> = vec![
+        item.title.clone().into(),
+        item.description.clone().into(),
+        item.credits.into(),
+        item.min_credits.into(),
+    ];
+
+    for idx in 0..bulletin::ATTRIBUTE_COUNT {
+        values.push((item.attribute_list.get(idx) as i64).into());
+    }
+
+    for flag in [
+        item.flags.is_prerequisite_concurrent_separate,
+        item.flags.deviant.empty_crosslist,
+        item.flags.deviant.unknown_requirement,
+    ] {
+        values.push((flag as i64).into());
+    }
+
+    values
+}
+
+impl Synchronizable<UndergraduateCourse> for SyncUndergraduateCourses {
+    fn sync(
+        url: &str,
+        items: &[UndergraduateCourse],
+        options: &SyncOptions,
+    ) -> Result<SyncSummary> {
+        let db = connect(url)?;
+
+        // everything below is written at once, or not at all
+        let transaction = Transaction::begin(&db)?;
+
+        let run_id = changes::start_run(&db, "courses")?;
+        let mut summary = SyncSummary {
+            run_id: Some(run_id),
+            ..Default::default()
         };
 
-        // TODO: TOO MANY CLONES
+        let crosslists = Crosslists::new(items);
+
+        // stored state of every course that is about to be updated, to diff against afterwards
+        let mut before = HashMap::<i64, changes::Snapshot>::new();
+
         for item in items {
-            // crosslist table
-            // for crosslist in
-            let CourseIdentifier {
-                code,
-                number,
-                suffix,
-            } = item.identifier.clone();
-
-            // libSQL doesn't support char conversion?
-            let suffix = suffix.map(|c| c.to_string());
-
-            let _course_res = match db.execute(Statement::with_args(
-                r#"INSERT OR IGNORE INTO UndergraduateCourse (
-                    code,
-                    number,
-                    suffix,
-                    title,
-                    description,
-                    credits,
-                    min_credits,
-                    GA,
-                    GHW,
-                    GH,
-                    GN,
-                    GQ,
-                    GS,
-                    GWS,
-                    ITD,
-                    LKD,
-                    FYS,
-                    IC,
-                    US,
-                    WCC,
-                    BA,
-                    BH,
-                    BN,
-                    BO,
-                    BQ,
-                    BS,
-                    BF1,
-                    BF2,
-                    HNR,
-                    is_prerequisite_concurrent_separate,
-                    empty_crosslist,
-                    unknown_requirement
-                ) VALUES (
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?,
-                    ?
-                )"#,
-                args!(
-                    code,
-                    number,
-                    suffix,
-                    item.title.clone(),
-                    item.description.clone(),
-                    item.credits,
-                    item.min_credits,
-                    if item.attribute_list.get(0) { 1 } else { 0 },
-                    if item.attribute_list.get(1) { 1 } else { 0 },
-                    if item.attribute_list.get(2) { 1 } else { 0 },
-                    if item.attribute_list.get(3) { 1 } else { 0 },
-                    if item.attribute_list.get(4) { 1 } else { 0 },
-                    if item.attribute_list.get(5) { 1 } else { 0 },
-                    if item.attribute_list.get(6) { 1 } else { 0 },
-                    if item.attribute_list.get(7) { 1 } else { 0 },
-                    if item.attribute_list.get(8) { 1 } else { 0 },
-                    if item.attribute_list.get(9) { 1 } else { 0 },
-                    if item.attribute_list.get(10) { 1 } else { 0 },
-                    if item.attribute_list.get(11) { 1 } else { 0 },
-                    if item.attribute_list.get(12) { 1 } else { 0 },
-                    if item.attribute_list.get(13) { 1 } else { 0 },
-                    if item.attribute_list.get(14) { 1 } else { 0 },
-                    if item.attribute_list.get(15) { 1 } else { 0 },
-                    if item.attribute_list.get(16) { 1 } else { 0 },
-                    if item.attribute_list.get(17) { 1 } else { 0 },
-                    if item.attribute_list.get(18) { 1 } else { 0 },
-                    if item.attribute_list.get(19) { 1 } else { 0 },
-                    if item.attribute_list.get(20) { 1 } else { 0 },
-                    if item.attribute_list.get(21) { 1 } else { 0 },
-                    if item.flags.is_prerequisite_concurrent_separate {
-                        1
-                    } else {
-                        0
-                    },
-                    if item.flags.deviant.empty_crosslist {
-                        1
-                    } else {
-                        0
-                    },
-                    if item.flags.deviant.unknown_requirement {
-                        1
-                    } else {
-                        0
+            let values = course_values(item);
+
+            // `suffix` is usually NULL and NULLs never conflict in a UNIQUE index,
+            // so existing courses are looked up instead of relying on ON CONFLICT
+            let statement = match find_course_id(&db, &item.identifier)? {
+                Some(course_id) => {
+                    if let Entry::Vacant(entry) = before.entry(course_id) {
+                        entry.insert(changes::course_snapshot(&db, course_id)?);
                     }
-                ),
-            )) {
-                Ok(res) => res,
-                Err(e) => panic!("{}", e),
+
+                    Statement::with_args(
+                        format!(
+                            "UPDATE UndergraduateCourse SET {} = ?, retired_at = NULL WHERE id = ?",
+                            COURSE_COLUMNS.join(" = ?, ")
+                        ),
+                        &[values, vec![course_id.into()]].concat(),
+                    )
+                }
+                None => {
+                    let CourseIdentifier {
+                        code,
+                        number,
+                        suffix,
+                    } = item.identifier.clone();
+
+                    // libSQL doesn't support char conversion?
+                    let suffix = suffix.map(|c| c.to_string());
+
+                    Statement::with_args(
+                        format!(
+                            "INSERT INTO UndergraduateCourse (code, number, suffix, {}) VALUES (?, ?, ?{})",
+                            COURSE_COLUMNS.join(", "),
+                            ", ?".repeat(COURSE_COLUMNS.len())
+                        ),
+                        &[args!(code, number, suffix).to_vec(), values].concat(),
+                    )
+                }
             };
+
+            db.execute(statement)
+                .map_err(Error::database)
+                .with_context(|| &item.identifier)?;
         }
 
-        let extract_and_sync_tree = |course_id: i64, table: &str, requirement_tree: &CourseRequirementTree| {
-
-                // TODO: Shouldn't need to be 2 vectors. 
-                let mut node_stack = Vec::<NodeId>::new();
-                let mut node_id_stack = Vec::<i64>::new();
-
-                for node in requirement_tree.tree.nodes() {
-                    let node_val = node.value();
-                    match node_val {
-                        bulletin::CourseRequirementNode::AND => {
-                            if node_id_stack.len() == 0 {
-                                // insert root node
-                                let res = match db.execute(Statement::with_args(
-                                    format!("INSERT OR IGNORE INTO {} (logic, course_id, req_course_id, parent) VALUES (?, ?, ?, ?)", table),
-                                    args!(
-                                        "&", // and
-                                        course_id, // only root points to course
-                                        None::<i64>, // not "C" logic
-                                        None::<i64>, // no parent
-                                    ),
-                                )) {
-                                    Ok(res) => res,
-                                    Err(e) => panic!("{}", e),
-                                };
-
-                                node_stack.push(node.id());
-                                node_id_stack.push(
-                                    res.last_insert_rowid.unwrap()
-                                );
-                            } else {
-                                // insert root node
-                                let res = match db.execute(Statement::with_args(
-                                    format!("INSERT OR IGNORE INTO {} (logic, course_id, req_course_id, parent) VALUES (?, ?, ?, ?)", table),                                    args!(
-                                        "&", // and
-                                        None::<i64>, // not root
-                                        None::<i64>, // not "C" logic
-                                        *node_id_stack.last().unwrap(), // parent
-                                    ),
-                                )) {
-                                    Ok(res) => res,
-                                    Err(e) => panic!("{}", e),
-                                };
-
-                                node_stack.push(node.id());
-                                node_id_stack.push(
-                                    res.last_insert_rowid.unwrap()
-                                );
-                            }
-                        }
-                        bulletin::CourseRequirementNode::OR => {
-
-                            if node_id_stack.len() == 0 {
-                                // insert root node
-                                let res = match db.execute(Statement::with_args(
-                                    format!("INSERT OR IGNORE INTO {} (logic, course_id, req_course_id, parent) VALUES (?, ?, ?, ?)", table),                                    args!(
-                                        "|", // or
-                                        course_id, // only root points to course
-                                        None::<i64>, // not "C" logic
-                                        None::<i64>, // no parent
-                                    ),
-                                )) {
-                                    Ok(res) => res,
-                                    Err(e) => panic!("{}", e),
-                                };
-
-                                node_stack.push(node.id());
-                                node_id_stack.push(
-                                    res.last_insert_rowid.unwrap()
-                                );
-                            } else {
-                                let res = match db.execute(Statement::with_args(
-                                    format!("INSERT OR IGNORE INTO {} (logic, course_id, req_course_id, parent) VALUES (?, ?, ?, ?)", table),                                    args!(
-                                        "|", // or
-                                        None::<i64>, // not root
-                                        None::<i64>, // not "C" logic
-                                        *node_id_stack.last().unwrap(), // parent
-                                    ),
-                                )) {
-                                    Ok(res) => res,
-                                    Err(e) => panic!("{}", e),
-                                };
-
-                                node_stack.push(node.id());
-                                node_id_stack.push(
-                                    res.last_insert_rowid.unwrap(),
-                                );
-                            }
-                        }
-                        bulletin::CourseRequirementNode::COURSE(course) => {
-                            // if current course isn't equal to the last 
-                            // keep popping until match is found
-                            while *node_stack.last().unwrap() != node.parent().unwrap().id() {
-                                node_stack.pop().unwrap();
-                                node_id_stack.pop().unwrap();
-                            }
-
-                            // find ref_course_id
-                            let ref_course_id = {
-                                let res = match db.execute(Statement::with_args(
-                                    match course.suffix {
-                                        Some(_) => "SELECT id FROM UndergraduateCourse WHERE code = ? AND number = ? AND suffix = ?",
-                                        None => "SELECT id FROM UndergraduateCourse WHERE code = ? AND number = ? AND suffix IS NULL",
-                                    }
-                                    ,
-                                    args!(
-                                        course.code.clone(),
-                                        course.number,
-                                        course.suffix.map(|s| s.to_string())
-                                    ),
-                                    )) {
-                                        Ok(res) => res,
-                                        Err(e) => panic!("{}", e),
-                                    };
-
-                               match res.rows
-                                        .iter() 
-                                        .map(de::from_row)
-                                        .collect::<Result<Vec<IdRecord>, _>>()
-                                        .unwrap()
-                                        .get(0) {  
-                                        Some(id) => id,
-                                        None => return, // skip courses that don't exist
-                                    }
-                                    .id
-                            };
-                           match db.execute(Statement::with_args(
-                                format!("INSERT OR IGNORE INTO {} (logic, course_id, req_course_id, parent) VALUES (?, ?, ?, ?)", table),                                args!(
-                                    "C", // course
-                                    None::<i64>, // not root
-                                    ref_course_id, // course id in db
-                                    *node_id_stack.last().unwrap(), // parent
-                                ),
-                            )) {
-                                Ok(res) => res,
-                                Err(e) => panic!("{}", e),
-                            };
-                        }
-                    }
+        // courses found by this scrape
+        let mut seen = HashSet::<i64>::new();
+
+        // depths go through courses of other subjects, they're only right if every subject
+        // was scraped
+        let built;
+        let graph = match (&options.subjects, options.graph) {
+            (Some(_), _) => None,
+            (None, Some(graph)) => Some(graph),
+            (None, None) => {
+                built = CourseGraph::new(items);
+                Some(&built)
             }
         };
 
-
         // once courses are added do crosslist and requirements
         for item in items {
             // get item record id
-            let course_id = {
-                let res = match db.execute(Statement::with_args(
-                    match item.identifier.suffix {
-                        Some(_) => "SELECT id FROM UndergraduateCourse WHERE code = ? AND number = ? AND suffix = ?",
-                        None => "SELECT id FROM UndergraduateCourse WHERE code = ? AND number = ? AND suffix IS NULL",
-                    },                        
-                    args!(
-                        item.identifier.code.clone(),
-                        item.identifier.number,
-                        item.identifier.suffix.map(|s| s.to_string())
-                    ),
-                )) {
-                    Ok(res) => res,
-                    Err(e) => panic!("{}", e),
-                };
-
-                res.rows
-                    .iter()
-                    .map(de::from_row)
-                    .collect::<Result<Vec<IdRecord>, _>>()
-                    .unwrap()
-                    .get(0)
-                    .unwrap()
-                    .id
+            let course_id = match find_course_id(&db, &item.identifier)? {
+                Some(id) => id,
+                None => {
+                    return Err(Error::database("course_id couldn't be found."))
+                        .with_context(|| &item.identifier)
+                }
             };
+            // the bulletin sometimes lists a course twice
+            let first_visit = seen.insert(course_id);
+
+            // crosslists and requirement trees are replaced, not merged
+            db.execute(Statement::with_args(
+                "DELETE FROM UndergraduateCourseCrossLists WHERE course_id = ?",
+                args!(course_id),
+            ))
+            .map_err(Error::database)?;
+
+            for table in REQUIREMENT_TABLES {
+                delete_requirement_tree(&db, course_id, table)?;
+            }
+            // the reverse index is written along with the trees
+            db.execute(Statement::with_args(
+                "DELETE FROM UndergraduateCourseDependents WHERE dependent_id = ?",
+                args!(course_id),
+            ))
+            .map_err(Error::database)?;
 
             // crosslist
             if let Some(crosslist) = &item.crosslist {
                 let mut batch_statements = Vec::new();
                 for course in crosslist {
-                    let crossed_course_id = {
-                        let res: libsql_client::ResultSet = match db.execute(Statement::with_args(
-                            match course.suffix {
-                                Some(_) => "SELECT id FROM UndergraduateCourse WHERE code = ? AND number = ? AND suffix = ?",
-                                None => "SELECT id FROM UndergraduateCourse WHERE code = ? AND number = ? AND suffix IS NULL",
-                            },
-                            args!(
-                                course.code.clone(),
-                                course.number,
-                                course.suffix.map(|s| s.to_string())
-                            ),
-                        )) {
-                            Ok(res) => res,
-                            Err(e) => panic!("{}", e),
-                        };
-
-                        match res.rows
-                            .iter()
-                            .map(de::from_row)
-                            .collect::<Result<Vec<IdRecord>, _>>()
-                            .unwrap()
-                            .get(0)
-                            {
-                                Some(id) => id,
-                                None => continue, // some courses don't exist??
-                            }
-                            .id
+                    let crossed_course_id = match find_course_id(&db, course)? {
+                        Some(id) => id,
+                        None => continue, // some courses don't exist??
                     };
 
                     batch_statements.push(Statement::with_args(
-                    "INSERT INTO UndergraduateCourseCrossLists (course_id, crossed_course_id) VALUES (?, ?)",
-                    args!(
-                        course_id,
-                        crossed_course_id
-                    ),
-                ));
+                        "INSERT INTO UndergraduateCourseCrossLists (course_id, crossed_course_id) VALUES (?, ?)",
+                        args!(course_id, crossed_course_id),
+                    ));
                 }
 
-                match db.batch(batch_statements) {
-                    Ok(res) => res,
-                    Err(e) => panic!("{}", e),
-                };
+                execute_all(&db, batch_statements)?;
             }
 
             // requirements
-            match &item.requirements.prerequisites {
-                Some(prerequisites) => {
-                    extract_and_sync_tree(course_id, "UndergraduateCoursePrerequisites", prerequisites);
+            for ((table, kind), tree) in
+                REQUIREMENT_TABLES.into_iter().zip(REQUIREMENT_KINDS).zip([
+                    &item.requirements.prerequisites,
+                    &item.requirements.concurrent,
+                    &item.requirements.corequisites,
+                    &item.requirements.recommended,
+                ])
+            {
+                if let Some(tree) = tree {
+                    sync_requirement_tree(&db, course_id, table, kind, tree, &crosslists)
+                        .with_context(|| &item.identifier)?;
                 }
-                None => (),
             }
-            match &item.requirements.concurrent {
-                Some(concurrent) => {
-                    extract_and_sync_tree(course_id, "UndergraduateCourseConcurrent", concurrent);
-                }
-                None => (),
+
+            if let Some(graph) = graph {
+                let depth = graph.depth(&item.identifier).map(|depth| depth as i64);
+                db.execute(Statement::with_args(
+                    "UPDATE UndergraduateCourse SET depth = ? WHERE id = ?",
+                    args!(depth, course_id),
+                ))
+                .map_err(Error::database)?;
+            }
+
+            if first_visit {
+                summary.saved += 1;
+
+                let after = changes::course_snapshot(&db, course_id)?;
+                summary.changes += changes::record_changes(
+                    &db,
+                    ChangeLog::Course,
+                    run_id,
+                    course_id,
+                    &item.identifier.to_string(),
+                    before.get(&course_id),
+                    Some(&after),
+                )?;
+            }
+        }
+
+        remove_stale_courses(&db, run_id, &seen, options, &mut summary)?;
+
+        changes::finish_run(&db, run_id)?;
+        transaction.commit()?;
+
+        Ok(summary)
+    }
+}
+
+/// Retires or deletes every stored program that isn't in `seen`, counting them and the changes
+/// recorded in `summary`
+fn remove_stale_programs(
+    db: &Client,
+    run_id: i64,
+    seen: &HashSet<i64>,
+    policy: StalePolicy,
+    summary: &mut SyncSummary,
+) -> Result<()> {
+    if let StalePolicy::Keep = policy {
+        return Ok(());
+    }
+
+    let res = db
+        .execute("SELECT id, link AS label FROM UndergraduateProgram")
+        .map_err(Error::database)?;
+    let stale: Vec<LabelRecord> = res
+        .rows
+        .iter()
+        .map(de::from_row)
+        .collect::<std::result::Result<Vec<LabelRecord>, _>>()
+        .map_err(Error::database)?
+        .into_iter()
+        .filter(|record| !seen.contains(&record.id))
+        .collect();
+
+    for LabelRecord {
+        id: program_id,
+        label: link,
+    } in stale
+    {
+        let before = changes::program_snapshot(db, program_id)?;
+        let statements = match policy {
+            StalePolicy::Keep => unreachable!("kept rows return early"),
+            StalePolicy::Retire => vec![Statement::with_args(
+                "UPDATE UndergraduateProgram SET retired_at = CURRENT_TIMESTAMP WHERE id = ? AND retired_at IS NULL",
+                args!(program_id),
+            )],
+            StalePolicy::Delete => vec![
+                Statement::with_args("DELETE FROM Campus WHERE program_id = ?", args!(program_id)),
+                Statement::with_args(
+                    "DELETE FROM Keywords WHERE program_id = ?",
+                    args!(program_id),
+                ),
+                Statement::with_args(
+                    "DELETE FROM UndergraduateProgramDetails WHERE program_id = ?",
+                    args!(program_id),
+                ),
+                Statement::with_args(
+                    "DELETE FROM UndergraduateProgramRequirement WHERE program_id = ?",
+                    args!(program_id),
+                ),
+                Statement::with_args(
+                    "DELETE FROM UndergraduateProgram WHERE id = ?",
+                    args!(program_id),
+                ),
+            ],
+        };
+
+        let res = execute_all(db, statements)?;
+        if res.last().map_or(0, |res| res.rows_affected) == 0 {
+            continue; // already retired
+        }
+        summary.removed += 1;
+
+        let after = match policy {
+            StalePolicy::Delete => None,
+            _ => Some(changes::program_snapshot(db, program_id)?),
+        };
+        summary.changes += changes::record_changes(
+            db,
+            ChangeLog::Program,
+            run_id,
+            program_id,
+            &link,
+            Some(&before),
+            after.as_ref(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Retires or deletes every stored course of the scraped subjects that isn't in `seen`,
+/// counting them and the changes recorded in `summary`
+fn remove_stale_courses(
+    db: &Client,
+    run_id: i64,
+    seen: &HashSet<i64>,
+    options: &SyncOptions,
+    summary: &mut SyncSummary,
+) -> Result<()> {
+    if let StalePolicy::Keep = options.stale {
+        return Ok(());
+    }
+
+    let res = db
+        .execute(format!(
+            "SELECT id, code, {} AS identifier FROM UndergraduateCourse",
+            changes::IDENTIFIER_SQL
+        ))
+        .map_err(Error::database)?;
+    let stale: Vec<CourseCodeRecord> = res
+        .rows
+        .iter()
+        .map(de::from_row)
+        .collect::<std::result::Result<Vec<CourseCodeRecord>, _>>()
+        .map_err(Error::database)?
+        .into_iter()
+        .filter(|record| match &options.subjects {
+            Some(subjects) => subjects
+                .iter()
+                .any(|subject| subject.eq_ignore_ascii_case(&record.code)),
+            None => true,
+        })
+        .filter(|record| !seen.contains(&record.id))
+        .collect();
+
+    for CourseCodeRecord {
+        id: course_id,
+        identifier,
+        ..
+    } in stale
+    {
+        let before = changes::course_snapshot(db, course_id)?;
+
+        let rows_affected = match options.stale {
+            StalePolicy::Keep => unreachable!("kept rows return early"),
+            StalePolicy::Retire => {
+                let res = db
+                    .execute(Statement::with_args(
+                        "UPDATE UndergraduateCourse SET retired_at = CURRENT_TIMESTAMP WHERE id = ? AND retired_at IS NULL",
+                        args!(course_id),
+                    ))
+                    .map_err(Error::database)?;
+                res.rows_affected
             }
-            match &item.requirements.corequisites {
-                Some(corequisites) => {
-                    extract_and_sync_tree(course_id, "UndergraduateCourseCorequisites", corequisites);
+            StalePolicy::Delete => {
+                for table in REQUIREMENT_TABLES {
+                    delete_requirement_tree(db, course_id, table)?;
+
+                    // other courses' requirements can't point to it anymore, it becomes text
+                    // like any course that wasn't scraped, so their groups still ask for as
+                    // many requirements
+                    db.execute(Statement::with_args(
+                        format!(
+                            r#"UPDATE {table} SET
+                                logic = 'T',
+                                detail = IFNULL(min_grade || ' OR BETTER IN ', '') || ?,
+                                req_course_id = NULL,
+                                min_grade = NULL
+                            WHERE req_course_id = ?"#
+                        ),
+                        args!(identifier.clone(), course_id),
+                    ))
+                    .map_err(Error::database)?;
                 }
-                None => (),
+
+                let res = execute_all(db, [
+                        Statement::with_args(
+                            "DELETE FROM UndergraduateCourseCrossLists WHERE course_id = ? OR crossed_course_id = ?",
+                            args!(course_id, course_id),
+                        ),
+                        Statement::with_args(
+                            "DELETE FROM UndergraduateCourseDependents WHERE course_id = ? OR dependent_id = ?",
+                            args!(course_id, course_id),
+                        ),
+                        Statement::with_args(
+                            "DELETE FROM UndergraduateCourse WHERE id = ?",
+                            args!(course_id),
+                        ),
+                    ])?;
+                res.last().map_or(0, |res| res.rows_affected)
             }
-            match &item.requirements.recommended {
-                Some(recommended) => {
-                    extract_and_sync_tree(course_id, "UndergraduateCourseRecommended", recommended);
+        };
+
+        if rows_affected == 0 {
+            continue; // already retired
+        }
+        summary.removed += 1;
+
+        let after = match options.stale {
+            StalePolicy::Delete => None,
+            _ => Some(changes::course_snapshot(db, course_id)?),
+        };
+        summary.changes += changes::record_changes(
+            db,
+            ChangeLog::Course,
+            run_id,
+            course_id,
+            &identifier,
+            Some(&before),
+            after.as_ref(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Deletes the requirement tree of `course_id` from `table`, starting at its root
+fn delete_requirement_tree(db: &Client, course_id: i64, table: &str) -> Result<()> {
+    db.execute(Statement::with_args(
+        format!(
+            r#"WITH RECURSIVE subtree (id) AS (
+                SELECT id FROM {table} WHERE course_id = ?
+                UNION ALL
+                SELECT {table}.id FROM {table} JOIN subtree ON {table}.parent = subtree.id
+            )
+            DELETE FROM {table} WHERE id IN subtree"#
+        ),
+        args!(course_id),
+    ))
+    .map_err(Error::database)?;
+
+    Ok(())
+}
+
+/// Inserts every node of `requirement_tree` into `table`. Only the root points to `course_id`,
+/// every other node points to its parent row. Every course in it gets `course_id` as a
+/// dependent of `kind`, courses that weren't scraped are stored as text.
+fn sync_requirement_tree(
+    db: &Client,
+    course_id: i64,
+    table: &str,
+    kind: RequirementKind,
+    requirement_tree: &CourseRequirementTree,
+    crosslists: &Crosslists,
+) -> Result<()> {
+    // stored canonical, so the same requirement is always stored the same way
+    let mut requirement_tree = requirement_tree.clone();
+    requirement_tree.normalize(crosslists);
+
+    // tree node -> database row
+    let mut row_ids = HashMap::<NodeId, i64>::new();
+
+    // nodes are stored in insertion order, so parents always come before their children
+    for node in requirement_tree.tree.nodes() {
+        let parent = node
+            .parent()
+            .and_then(|parent| row_ids.get(&parent.id()).copied());
+        let root_course_id = match node.parent() {
+            Some(_) => None,         // not root
+            None => Some(course_id), // only root points to course
+        };
+
+        // clauses that aren't courses keep what they're about in `detail`
+        let (logic, req_course_id, min_grade, detail, required_count) = match node.value() {
+            bulletin::CourseRequirementNode::AND => ("&", None, None, None, None),
+            bulletin::CourseRequirementNode::OR => ("|", None, None, None, None),
+            bulletin::CourseRequirementNode::AT_LEAST(count) => {
+                ("N", None, None, None, Some(*count as i64))
+            }
+            bulletin::CourseRequirementNode::COURSE(course, min_grade) => {
+                match find_course_id(db, course)? {
+                    Some(id) => {
+                        dependents::insert(
+                            db,
+                            id,
+                            course_id,
+                            kind,
+                            dependents::is_mandatory(node),
+                        )?;
+                        (
+                            "C",
+                            Some(id),
+                            min_grade.map(|grade| grade.to_string()),
+                            None,
+                            None,
+                        )
+                    }
+                    // courses that don't exist can't be referenced, they're kept as text so
+                    // the group around them still asks for as many requirements
+                    None => ("T", None, None, Some(node.value().to_string()), None),
                 }
-                None => (),
             }
+            bulletin::CourseRequirementNode::STANDING(semester) => {
+                ("S", None, None, Some(semester.to_string()), None)
+            }
+            bulletin::CourseRequirementNode::ENROLLMENT(program) => {
+                ("E", None, None, Some(program.clone()), None)
+            }
+            bulletin::CourseRequirementNode::PERMISSION(whom) => {
+                ("P", None, None, Some(whom.clone()), None)
+            }
+            bulletin::CourseRequirementNode::TEXT(text) => {
+                ("T", None, None, Some(text.clone()), None)
+            }
+        };
+
+        let res = db
+            .execute(Statement::with_args(
+                format!(
+                    "INSERT OR IGNORE INTO {} (logic, course_id, req_course_id, parent, min_grade, detail, required_count) VALUES (?, ?, ?, ?, ?, ?, ?)",
+                    table
+                ),
+                args!(
+                    logic,
+                    root_course_id,
+                    req_course_id,
+                    parent,
+                    min_grade,
+                    detail,
+                    required_count
+                ),
+            ))
+            .map_err(Error::database)?;
+
+        if let Some(row_id) = res.last_insert_rowid {
+            row_ids.insert(node.id(), row_id);
         }
     }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh database file of its own for every test
+    pub(crate) fn database(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("scrapper-database-{name}.db"));
+        let _ = std::fs::remove_file(&path);
+
+        path.to_string_lossy().into_owned()
+    }
+
+    fn courses(courses: &[(&str, Option<&str>)]) -> Vec<UndergraduateCourse> {
+        courses
+            .iter()
+            .map(|(identifier, prerequisites)| {
+                UndergraduateCourse::with_prerequisites(identifier, *prerequisites)
+            })
+            .collect()
+    }
+
+    /// The single number `sql` selects, as `count`
+    pub(crate) fn count(db: &Client, sql: &str) -> i64 {
+        #[derive(serde::Deserialize)]
+        struct CountRecord {
+            count: i64,
+        }
+
+        let res = db.execute(sql).unwrap();
+        de::from_row::<CountRecord>(&res.rows[0]).unwrap().count
+    }
+
+    /// Rows of every table a course sync writes to
+    fn course_rows(db: &Client) -> Vec<i64> {
+        [
+            "UndergraduateCourse",
+            "UndergraduateCourseCrossLists",
+            "UndergraduateCoursePrerequisites",
+            "UndergraduateCourseDependents",
+        ]
+        .iter()
+        .map(|table| count(db, &format!("SELECT COUNT(*) AS count FROM {table}")))
+        .collect()
+    }
+
+    /// Prerequisite rows of `course`, below the root, as (logic, requirement)
+    fn prerequisite_rows(db: &Client, course: &str) -> Vec<(String, String)> {
+        #[derive(serde::Deserialize)]
+        struct Row {
+            logic: String,
+            requirement: String,
+        }
+
+        let course_id = find_course_id(db, &CourseIdentifier::try_from(course).unwrap())
+            .unwrap()
+            .unwrap();
+        let res = db
+            .execute(Statement::with_args(
+                format!(
+                    r#"SELECT child.logic, IFNULL(child.detail, {}) AS requirement
+                    FROM UndergraduateCoursePrerequisites AS root
+                    JOIN UndergraduateCoursePrerequisites AS child ON child.parent = root.id
+                    LEFT JOIN UndergraduateCourse ON child.req_course_id = UndergraduateCourse.id
+                    WHERE root.course_id = ?
+                    ORDER BY child.id"#,
+                    changes::IDENTIFIER_SQL
+                ),
+                args!(course_id),
+            ))
+            .unwrap();
+
+        res.rows
+            .iter()
+            .map(|row| de::from_row::<Row>(row).unwrap())
+            .map(|row| (row.logic, row.requirement))
+            .collect()
+    }
+
+    #[test]
+    fn deleted_courses_stay_in_other_requirements_as_text() {
+        let url = database("deleted-requirement");
+        let options = SyncOptions {
+            stale: StalePolicy::Delete,
+            ..Default::default()
+        };
+
+        SyncUndergraduateCourses::sync(
+            &url,
+            &courses(&[
+                ("MATH 140", None),
+                ("MATH 141", None),
+                ("MATH 230", Some("MATH 140 and C or better in MATH 141")),
+            ]),
+            &options,
+        )
+        .unwrap();
+        SyncUndergraduateCourses::sync(
+            &url,
+            &courses(&[
+                ("MATH 140", None),
+                ("MATH 230", Some("MATH 140 and C or better in MATH 141")),
+            ]),
+            &options,
+        )
+        .unwrap();
+
+        let db = connect(&url).unwrap();
+        assert_eq!(
+            None,
+            find_course_id(&db, &"MATH 141".try_into().unwrap()).unwrap()
+        );
+        assert_eq!(
+            vec![
+                ("C".to_string(), "MATH 140".to_string()),
+                ("T".to_string(), "C OR BETTER IN MATH 141".to_string())
+            ],
+            prerequisite_rows(&db, "MATH 230")
+        );
+    }
+
+    #[test]
+    fn syncing_again_updates_in_place() {
+        let url = database("resync");
+        let mut items = courses(&[
+            ("MATH 140", None),
+            ("MATH 141", Some("MATH 140")),
+            ("MATH 484", Some("MATH 141 or MATH 140")),
+            ("CMPSC 484", Some("MATH 141 or MATH 140")),
+        ]);
+        items[2].crosslist = Some(vec![CourseIdentifier::try_from("CMPSC 484").unwrap()]);
+        let programs = [UndergraduateProgram::baccalaureate(
+            "Mathematics",
+            "/undergraduate/programs/majors/science/mathematics/",
+            &["University Park", "Altoona"],
+        )];
+
+        let first = SyncUndergraduateCourses::sync(&url, &items, &SyncOptions::default()).unwrap();
+        SyncUndergraduatePrograms::sync(&url, &programs, &SyncOptions::default()).unwrap();
+        let db = connect(&url).unwrap();
+        let rows = course_rows(&db);
+        let campus_rows = count(&db, "SELECT COUNT(*) AS count FROM Campus");
+
+        // nothing changed, nothing is written twice
+        let second = SyncUndergraduateCourses::sync(&url, &items, &SyncOptions::default()).unwrap();
+        let program_sync =
+            SyncUndergraduatePrograms::sync(&url, &programs, &SyncOptions::default()).unwrap();
+
+        assert_eq!((4, 4), (first.saved, second.saved));
+        assert_eq!((0, 0), (second.changes, program_sync.changes));
+        assert_eq!(vec![4, 1, 8, 5], rows);
+        assert_eq!(rows, course_rows(&db));
+        assert_eq!(2, campus_rows);
+        assert_eq!(
+            campus_rows,
+            count(&db, "SELECT COUNT(*) AS count FROM Campus")
+        );
+
+        // child rows are replaced, not merged
+        items[1].requirements.prerequisites = None;
+        SyncUndergraduateCourses::sync(&url, &items, &SyncOptions::default()).unwrap();
+        assert_eq!(vec![4, 1, 6, 4], course_rows(&db));
+    }
+
+    #[test]
+    fn a_failed_sync_changes_nothing() {
+        let url = database("rollback");
+        let mut items = courses(&[("MATH 140", None), ("MATH 141", Some("MATH 140"))]);
+        SyncUndergraduateCourses::sync(&url, &items, &SyncOptions::default()).unwrap();
+
+        // the last course can't be written, after every other one was
+        let db = connect(&url).unwrap();
+        db.execute(
+            r#"CREATE TRIGGER fail_sync BEFORE INSERT ON UndergraduateCourse WHEN NEW.code = 'STAT'
+            BEGIN SELECT RAISE(ABORT, 'no statistics'); END"#,
+        )
+        .unwrap();
+        let rows = course_rows(&db);
+
+        items[0].title = "Calculus I".into();
+        items[1].requirements.prerequisites = None;
+        items.push(UndergraduateCourse::with_prerequisites("STAT 200", None));
+        let options = SyncOptions {
+            stale: StalePolicy::Delete,
+            ..Default::default()
+        };
+        assert!(SyncUndergraduateCourses::sync(&url, &items, &options).is_err());
+
+        assert_eq!(rows, course_rows(&db));
+        assert_eq!(
+            1,
+            count(
+                &db,
+                "SELECT COUNT(*) AS count FROM UndergraduateCourse WHERE title = 'MATH 140'"
+            )
+        );
+        // the run isn't recorded either
+        assert_eq!(1, count(&db, "SELECT COUNT(*) AS count FROM SyncRun"));
+    }
 }
@@ -0,0 +1,498 @@
+//! Reads synced courses back out of the database, so the catalog can be queried without
+//! scraping it again.
+
+use std::collections::{HashMap, HashSet};
+
+use ego_tree::{NodeId, Tree};
+use libsql_client::{args, de, local::Client, Statement, Value};
+
+use super::{connect, ATTRIBUTE_COLUMNS, COURSE_COLUMNS, FIELD_COLUMNS, REQUIREMENT_TABLES};
+use crate::{
+    bulletin::{
+        Campus, CourseIdentifier, CourseRequirementNode, CourseRequirementTree, FullAttributeList,
+        Grade, UndergraduateCourse, UndergraduateCourseDeviantFlags, UndergraduateCourseFlags,
+        UndergraduateCourseRequirements,
+    },
+    database::changes::IDENTIFIER_SQL,
+    error::{Error, Result},
+};
+
+/// A row of a requirement table, with the identifier of the course it requires
+#[derive(serde::Deserialize)]
+pub struct RequirementRecord {
+    pub id: i64,
+    /// Only set on roots
+    pub course_id: Option<i64>,
+    pub logic: String,
+    pub parent: Option<i64>,
+    pub identifier: Option<String>,
+    pub min_grade: Option<String>,
+    pub detail: Option<String>,
+    pub required_count: Option<i64>,
+}
+
+impl RequirementRecord {
+    /// The node stored in this row, `None` if it can't be read back (e.g. a deleted course)
+    fn node(&self) -> Option<CourseRequirementNode> {
+        let detail = self.detail.clone();
+
+        match self.logic.as_str() {
+            "&" => Some(CourseRequirementNode::AND),
+            "|" => Some(CourseRequirementNode::OR),
+            "N" => u8::try_from(self.required_count?)
+                .ok()
+                .map(CourseRequirementNode::AT_LEAST),
+            "C" => {
+                let course = CourseIdentifier::try_from(self.identifier.as_deref()?).ok()?;
+                let min_grade = match &self.min_grade {
+                    Some(grade) => Some(Grade::try_from(grade.as_str()).ok()?),
+                    None => None,
+                };
+
+                Some(CourseRequirementNode::COURSE(course, min_grade))
+            }
+            "S" => detail?.parse().ok().map(CourseRequirementNode::STANDING),
+            "E" => detail.map(CourseRequirementNode::ENROLLMENT),
+            "P" => detail.map(CourseRequirementNode::PERMISSION),
+            "T" => detail.map(CourseRequirementNode::TEXT),
+            _ => None,
+        }
+    }
+}
+
+/// Rebuilds the trees stored in `records`, with the course id of their root. Records have to be
+/// in id order so parents come before their children.
+pub fn build_trees(records: &[RequirementRecord]) -> Vec<(Option<i64>, CourseRequirementTree)> {
+    let mut trees = Vec::<(Option<i64>, Tree<CourseRequirementNode>)>::new();
+    // row -> (tree, node)
+    let mut nodes = HashMap::<i64, (usize, NodeId)>::new();
+
+    for record in records {
+        let node = match record.node() {
+            Some(node) => node,
+            None => continue,
+        };
+
+        match record.parent {
+            None => {
+                let tree = Tree::new(node);
+                nodes.insert(record.id, (trees.len(), tree.root().id()));
+                trees.push((record.course_id, tree));
+            }
+            Some(parent) => {
+                let (index, parent_id) = match nodes.get(&parent) {
+                    Some(&parent) => parent,
+                    None => continue,
+                };
+
+                if let Some(mut parent) = trees[index].1.get_mut(parent_id) {
+                    let id = parent.append(node).id();
+                    nodes.insert(record.id, (index, id));
+                }
+            }
+        }
+    }
+
+    trees
+        .into_iter()
+        .map(|(course_id, tree)| (course_id, CourseRequirementTree { tree }))
+        .collect()
+}
+
+/// Every requirement tree stored in `table`, by course id
+fn requirement_trees(db: &Client, table: &str) -> Result<HashMap<i64, CourseRequirementTree>> {
+    let res = db
+        .execute(format!(
+            r#"SELECT {table}.id, {table}.course_id, logic, parent, min_grade, detail, required_count, {IDENTIFIER_SQL} AS identifier
+            FROM {table} LEFT JOIN UndergraduateCourse ON {table}.req_course_id = UndergraduateCourse.id
+            ORDER BY {table}.id"#
+        ))
+        .map_err(Error::database)?;
+    let records = res
+        .rows
+        .iter()
+        .map(de::from_row)
+        .collect::<std::result::Result<Vec<RequirementRecord>, _>>()
+        .map_err(Error::database)?;
+
+    Ok(build_trees(&records)
+        .into_iter()
+        .filter_map(|(course_id, tree)| Some((course_id?, tree)))
+        .collect())
+}
+
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::Text { value } => Some(value.clone()),
+        _ => None,
+    }
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer { value } => Some(*value as f64),
+        Value::Float { value } => Some(*value),
+        _ => None,
+    }
+}
+
+fn flag(value: &Value) -> bool {
+    matches!(value, Value::Integer { value } if *value != 0)
+}
+
+/// Every crosslist stored, by course id
+fn crosslists(db: &Client) -> Result<HashMap<i64, Vec<CourseIdentifier>>> {
+    #[derive(serde::Deserialize)]
+    struct CrosslistRecord {
+        course_id: i64,
+        identifier: String,
+    }
+
+    let res = db
+        .execute(format!(
+            r#"SELECT course_id, {IDENTIFIER_SQL} AS identifier FROM UndergraduateCourseCrossLists
+            JOIN UndergraduateCourse ON crossed_course_id = UndergraduateCourse.id"#
+        ))
+        .map_err(Error::database)?;
+
+    let mut crosslists = HashMap::<i64, Vec<CourseIdentifier>>::new();
+    for row in &res.rows {
+        let record: CrosslistRecord = de::from_row(row).map_err(Error::database)?;
+        crosslists
+            .entry(record.course_id)
+            .or_default()
+            .push(CourseIdentifier::try_from(record.identifier.as_str())?);
+    }
+
+    Ok(crosslists)
+}
+
+/// Every course still in the bulletin, with its crosslists and requirements, sorted by
+/// identifier. Requirements come back the way they're stored: normalized, with the courses
+/// that weren't scraped as text.
+pub fn load_courses(url: &str) -> Result<Vec<UndergraduateCourse>> {
+    let db = connect(url)?;
+
+    let mut crosslists = crosslists(&db)?;
+    // in `REQUIREMENT_TABLES` order
+    let mut trees = Vec::new();
+    for table in REQUIREMENT_TABLES {
+        trees.push(requirement_trees(&db, table)?);
+    }
+
+    let res = db
+        .execute(format!(
+            "SELECT id, {IDENTIFIER_SQL} AS identifier, {} FROM UndergraduateCourse WHERE retired_at IS NULL",
+            COURSE_COLUMNS.join(", ")
+        ))
+        .map_err(Error::database)?;
+
+    let attribute_names = FullAttributeList::names();
+    let mut courses = Vec::<UndergraduateCourse>::new();
+
+    for row in &res.rows {
+        let id = match row.values[0] {
+            Value::Integer { value } => value,
+            _ => return Err(Error::database("course id couldn't be read.")),
+        };
+        let identifier = text(&row.values[1])
+            .ok_or_else(|| Error::database("course identifier couldn't be read."))?;
+        // the columns, in `COURSE_COLUMNS` order
+        let (fields, values) = row.values[2..].split_at(FIELD_COLUMNS.len());
+        let (attributes, flags) = values.split_at(ATTRIBUTE_COLUMNS.len());
+        let (
+            [title, description, credits, min_credits],
+            [is_prerequisite_concurrent_separate, empty_crosslist, unknown_requirement],
+        ) = (fields, flags)
+        else {
+            return Err(Error::database("course columns couldn't be read."));
+        };
+
+        let mut attribute_list = FullAttributeList::new();
+        for (name, value) in attribute_names.iter().zip(attributes) {
+            if flag(value) {
+                attribute_list.add(name)?;
+            }
+        }
+
+        let crosslist = crosslists.remove(&id).map(|mut crosslist| {
+            crosslist.sort();
+            crosslist
+        });
+
+        courses.push(UndergraduateCourse {
+            identifier: CourseIdentifier::try_from(identifier.as_str())?,
+            title: text(title).unwrap_or_default(),
+            description: text(description),
+            credits: number(credits).unwrap_or_default() as f32,
+            min_credits: number(min_credits).map(|credits| credits as f32),
+            attribute_list,
+            crosslist,
+            requirements: UndergraduateCourseRequirements {
+                prerequisites: trees[0].remove(&id),
+                concurrent: trees[1].remove(&id),
+                corequisites: trees[2].remove(&id),
+                recommended: trees[3].remove(&id),
+            },
+            flags: UndergraduateCourseFlags {
+                is_prerequisite_concurrent_separate: flag(is_prerequisite_concurrent_separate),
+                deviant: UndergraduateCourseDeviantFlags {
+                    empty_crosslist: flag(empty_crosslist),
+                    unknown_requirement: flag(unknown_requirement),
+                },
+            },
+        });
+    }
+
+    courses.sort_by(|a, b| a.identifier.cmp(&b.identifier));
+    Ok(courses)
+}
+
+/// The degree requirement courses of the program at `program`, its link or the end of it (e.g.
+/// "math-bs"), in page order. Of alternatives ("MATH 220 or MATH 484"), only the first one is
+/// taken.
+pub fn program_courses(url: &str, program: &str) -> Result<Vec<CourseIdentifier>> {
+    #[derive(serde::Deserialize)]
+    struct ProgramRecord {
+        id: i64,
+        has_details: i64,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct ProgramRequirementRecord {
+        identifier: Option<String>,
+        is_alternative: i64,
+    }
+
+    let db = connect(url)?;
+
+    let slug = format!("%/programs/{}/", program.trim_matches('/'));
+    let res = db
+        .execute(Statement::with_args(
+            r#"SELECT id, EXISTS (SELECT 1 FROM UndergraduateProgramDetails WHERE program_id = UndergraduateProgram.id) AS has_details
+            FROM UndergraduateProgram WHERE retired_at IS NULL AND (link = ? OR link LIKE ?)"#,
+            args!(program, slug),
+        ))
+        .map_err(Error::database)?;
+    let record: ProgramRecord = match res.rows.first() {
+        Some(row) => de::from_row(row).map_err(Error::database)?,
+        None => return Err(Error::unknown("program", program)),
+    };
+    if record.has_details == 0 {
+        return Err(Error::database(format!(
+            "the details of {} aren't synced, run `programs --details` first.",
+            program
+        )));
+    }
+
+    let res = db
+        .execute(Statement::with_args(
+            "SELECT identifier, is_alternative FROM UndergraduateProgramRequirement WHERE program_id = ? ORDER BY position",
+            args!(record.id),
+        ))
+        .map_err(Error::database)?;
+
+    let mut courses = Vec::<CourseIdentifier>::new();
+    // whether the current row and its alternatives already gave a course
+    let mut picked = false;
+    for row in &res.rows {
+        let record: ProgramRequirementRecord = de::from_row(row).map_err(Error::database)?;
+        if record.is_alternative == 0 {
+            picked = false;
+        }
+
+        if let (Some(identifier), false) = (record.identifier, picked) {
+            courses.push(CourseIdentifier::try_from(identifier.as_str())?);
+            picked = true;
+        }
+    }
+
+    Ok(courses)
+}
+
+/// Courses offered at `campus` (e.g. "University Park"). The bulletin doesn't say where a course
+/// is taught, so these are the courses required by the programs offered there, as far as their
+/// details were synced: electives and general education courses none of them requires are left
+/// out.
+pub fn campus_courses(url: &str, campus: &str) -> Result<HashSet<CourseIdentifier>> {
+    #[derive(serde::Deserialize)]
+    struct IdentifierRecord {
+        identifier: String,
+    }
+
+    // only to fail on a typo
+    Campus::try_from(campus)?;
+
+    let db = connect(url)?;
+    let res = db
+        .execute(Statement::with_args(
+            r#"SELECT DISTINCT identifier FROM UndergraduateProgramRequirement
+            JOIN Campus ON Campus.program_id = UndergraduateProgramRequirement.program_id
+            JOIN UndergraduateProgram ON UndergraduateProgram.id = Campus.program_id
+            WHERE Campus.name = ? AND identifier IS NOT NULL AND retired_at IS NULL"#,
+            args!(campus),
+        ))
+        .map_err(Error::database)?;
+
+    let mut courses = HashSet::<CourseIdentifier>::new();
+    for row in &res.rows {
+        let record: IdentifierRecord = de::from_row(row).map_err(Error::database)?;
+        courses.insert(CourseIdentifier::try_from(record.identifier.as_str())?);
+    }
+
+    if courses.is_empty() {
+        return Err(Error::database(format!(
+            "no program offered at {} has its details synced, run `programs --details` first.",
+            campus
+        )));
+    }
+
+    Ok(courses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bulletin::Crosslists,
+        database::{tests::database, SyncOptions, SyncUndergraduateCourses, Synchronizable},
+        evaluation::{self, CourseFilter, Transcript},
+    };
+
+    fn identifiers(courses: &[&UndergraduateCourse]) -> Vec<String> {
+        courses
+            .iter()
+            .map(|course| course.identifier.to_string())
+            .collect()
+    }
+
+    /// MATH 484 and CMPSC 484 are crosslisted, MATH 230 was retired by the second sync
+    fn synced_catalog(name: &str) -> String {
+        let url = database(name);
+
+        let mut math_140 = UndergraduateCourse::with_prerequisites("MATH 140", None);
+        math_140.title = "Calculus With Analytic Geometry I".into();
+        math_140.description = Some("Functions, limits; analytic geometry.".into());
+        math_140.credits = 4.0;
+        math_140
+            .attribute_list
+            .add("General Education: Quantification (GQ)")
+            .unwrap();
+        let mut math_484 = UndergraduateCourse::with_prerequisites(
+            "MATH 484",
+            Some(
+                "at least 2 of the following: (MATH 220, MATH 230, MATH 231) and C or better in MATH 140",
+            ),
+        );
+        math_484.crosslist = Some(vec![CourseIdentifier::try_from("CMPSC 484").unwrap()]);
+        let mut math_497 =
+            UndergraduateCourse::with_prerequisites("MATH 497", Some("5th semester standing"));
+        math_497.credits = 3.0;
+        math_497.min_credits = Some(1.0);
+
+        let mut courses = vec![
+            math_140,
+            math_484,
+            UndergraduateCourse::with_prerequisites("CMPSC 484", None),
+            UndergraduateCourse::with_prerequisites("MATH 220", None),
+            UndergraduateCourse::with_prerequisites("MATH 230", None),
+            UndergraduateCourse::with_prerequisites("MATH 231", None),
+            UndergraduateCourse::with_prerequisites("MATH 485", Some("MATH 484")),
+            math_497,
+        ];
+        SyncUndergraduateCourses::sync(&url, &courses, &SyncOptions::default()).unwrap();
+        courses.retain(|course| course.identifier.to_string() != "MATH 230");
+        SyncUndergraduateCourses::sync(&url, &courses, &SyncOptions::default()).unwrap();
+
+        url
+    }
+
+    #[test]
+    fn courses_are_loaded_as_synced() {
+        let courses = load_courses(&synced_catalog("catalog-load")).unwrap();
+
+        // sorted, without the retired course
+        assert_eq!(
+            vec![
+                "CMPSC 484",
+                "MATH 140",
+                "MATH 220",
+                "MATH 231",
+                "MATH 484",
+                "MATH 485",
+                "MATH 497"
+            ],
+            identifiers(&courses.iter().collect::<Vec<_>>())
+        );
+
+        let math_140 = &courses[1];
+        assert_eq!("Calculus With Analytic Geometry I", math_140.title);
+        assert_eq!(
+            Some("Functions, limits; analytic geometry."),
+            math_140.description.as_deref()
+        );
+        assert_eq!(4.0, math_140.credits);
+        assert!(math_140.attribute_list.contains_code("GQ").unwrap());
+        assert!(!math_140.attribute_list.contains_code("GN").unwrap());
+
+        // a retired course is still required, it's only left out of the catalog
+        let math_484 = &courses[4];
+        assert_eq!(
+            Some(vec![CourseIdentifier::try_from("CMPSC 484").unwrap()]),
+            math_484.crosslist
+        );
+        assert_eq!(
+            Some("[C OR BETTER IN MATH 140] AND AT LEAST 2 OF (MATH 220 OR MATH 230 OR MATH 231)"),
+            math_484
+                .requirements
+                .prerequisites
+                .as_ref()
+                .map(|tree| tree.to_string())
+                .as_deref()
+        );
+
+        let math_497 = &courses[6];
+        assert_eq!((3.0, Some(1.0)), (math_497.credits, math_497.min_credits));
+        assert_eq!(
+            Some("5TH SEMESTER STANDING".to_string()),
+            math_497
+                .requirements
+                .prerequisites
+                .as_ref()
+                .map(|tree| tree.to_string())
+        );
+    }
+
+    #[test]
+    fn eligible_courses_count_crosslists() {
+        let courses = load_courses(&synced_catalog("catalog-eligible")).unwrap();
+        let transcript = Transcript::parse(
+            5,
+            &["MATH 140:B".into(), "CMPSC 484".into()],
+            Crosslists::new(&courses),
+        )
+        .unwrap();
+
+        // MATH 484 was passed as CMPSC 484, which is all MATH 485 needs
+        assert_eq!(
+            vec!["MATH 220", "MATH 231", "MATH 485", "MATH 497"],
+            identifiers(
+                &evaluation::eligible(&courses, &transcript, &CourseFilter::default()).unwrap()
+            )
+        );
+        assert_eq!(
+            vec!["MATH 497"],
+            identifiers(
+                &evaluation::eligible(
+                    &courses,
+                    &transcript,
+                    &CourseFilter {
+                        credits: Some(2.0),
+                        ..Default::default()
+                    }
+                )
+                .unwrap()
+            )
+        );
+    }
+}
@@ -0,0 +1,459 @@
+//! Change log written by every sync: one `SyncRun` row per sync, and one
+//! `CourseChange`/`ProgramChange` row per field that changed during it.
+
+use std::collections::BTreeMap;
+
+use libsql_client::{args, de, local::Client, Statement, Value};
+
+use super::{
+    catalog::{build_trees, RequirementRecord},
+    ATTRIBUTE_COLUMNS, FIELD_COLUMNS, REQUIREMENT_TABLES,
+};
+use crate::error::{Error, Result};
+
+/// Field name -> value as text, read back from the database. Taken before and after a record
+/// is synced so both sides are rendered the same way.
+pub type Snapshot = BTreeMap<&'static str, Option<String>>;
+
+/// "MATH 140H" from the columns of `UndergraduateCourse`
+pub const IDENTIFIER_SQL: &str = "UndergraduateCourse.code || ' ' || UndergraduateCourse.number || IFNULL(UndergraduateCourse.suffix, '')";
+
+/// Which change table a record belongs to
+#[derive(Clone, Copy)]
+pub enum ChangeLog {
+    Course,
+    Program,
+}
+
+impl ChangeLog {
+    fn insert_sql(&self) -> &'static str {
+        match self {
+            ChangeLog::Course => "INSERT INTO CourseChange (run_id, course_id, identifier, field, old_value, new_value) VALUES (?, ?, ?, ?, ?, ?)",
+            ChangeLog::Program => "INSERT INTO ProgramChange (run_id, program_id, link, field, old_value, new_value) VALUES (?, ?, ?, ?, ?, ?)",
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct NameRecord {
+    name: String,
+}
+
+/// Starts a new run for `target` and returns its id
+pub fn start_run(db: &Client, target: &str) -> Result<i64> {
+    let res = db
+        .execute(Statement::with_args(
+            "INSERT INTO SyncRun (target) VALUES (?)",
+            args!(target),
+        ))
+        .map_err(Error::database)?;
+
+    res.last_insert_rowid
+        .ok_or_else(|| Error::database("SyncRun id couldn't be found."))
+}
+
+pub fn finish_run(db: &Client, run_id: i64) -> Result<()> {
+    db.execute(Statement::with_args(
+        "UPDATE SyncRun SET finished_at = CURRENT_TIMESTAMP WHERE id = ?",
+        args!(run_id),
+    ))
+    .map_err(Error::database)?;
+
+    Ok(())
+}
+
+/// Writes one row per field that differs between `before` and `after` and returns how many
+/// were written. A missing `before` means the record was added, a missing `after` that it was
+/// deleted; only its status is recorded then.
+pub fn record_changes(
+    db: &Client,
+    log: ChangeLog,
+    run_id: i64,
+    record_id: i64,
+    label: &str,
+    before: Option<&Snapshot>,
+    after: Option<&Snapshot>,
+) -> Result<usize> {
+    let status = |snapshot: Option<&Snapshot>| snapshot.and_then(|s| s["status"].clone());
+
+    let changes: Vec<(&str, Option<String>, Option<String>)> = match (before, after) {
+        (Some(before), Some(after)) => before
+            .iter()
+            .filter(|(field, old_value)| after.get(*field) != Some(old_value))
+            .map(|(field, old_value)| (*field, old_value.clone(), after[field].clone()))
+            .collect(),
+        _ => vec![("status", status(before), status(after))],
+    };
+
+    for (field, old_value, new_value) in &changes {
+        db.execute(Statement::with_args(
+            log.insert_sql(),
+            args!(
+                run_id,
+                record_id,
+                label,
+                *field,
+                old_value.clone(),
+                new_value.clone()
+            ),
+        ))
+        .map_err(Error::database)?;
+    }
+
+    Ok(changes.len())
+}
+
+/// Returns the value as text, `None` for NULL
+fn value_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::Integer { value } => Some(value.to_string()),
+        Value::Float { value } => Some(value.to_string()),
+        Value::Text { value } => Some(value.clone()),
+        Value::Blob { .. } => Some("<blob>".into()),
+    }
+}
+
+/// Joins the `name` column of every row, sorted, e.g. "CMPSC 484, MATH 484"
+fn joined_names(db: &Client, statement: Statement) -> Result<Option<String>> {
+    let res = db.execute(statement).map_err(Error::database)?;
+    let mut names = res
+        .rows
+        .iter()
+        .map(de::from_row)
+        .collect::<std::result::Result<Vec<NameRecord>, _>>()
+        .map_err(Error::database)?
+        .into_iter()
+        .map(|record| record.name)
+        .collect::<Vec<_>>();
+
+    names.sort();
+
+    Ok(match names.is_empty() {
+        true => None,
+        false => Some(names.join(", ")),
+    })
+}
+
+/// Renders the requirement tree of `course_id` stored in `table`, e.g. "MATH 140 OR [MATH 141 AND CMPSC 121]"
+fn requirement_text(db: &Client, course_id: i64, table: &str) -> Result<Option<String>> {
+    let res = db
+        .execute(Statement::with_args(
+            format!(
+                r#"WITH RECURSIVE subtree (id, logic, req_course_id, parent, min_grade, detail, required_count) AS (
+                    SELECT id, logic, req_course_id, parent, min_grade, detail, required_count FROM {table} WHERE course_id = ?
+                    UNION ALL
+                    SELECT {table}.id, {table}.logic, {table}.req_course_id, {table}.parent, {table}.min_grade, {table}.detail, {table}.required_count
+                    FROM {table} JOIN subtree ON {table}.parent = subtree.id
+                )
+                SELECT subtree.id, NULL AS course_id, subtree.logic, subtree.parent, subtree.min_grade, subtree.detail, subtree.required_count, {IDENTIFIER_SQL} AS identifier
+                FROM subtree LEFT JOIN UndergraduateCourse ON subtree.req_course_id = UndergraduateCourse.id
+                ORDER BY subtree.id"#
+            ),
+            args!(course_id),
+        ))
+        .map_err(Error::database)?;
+    let records = res
+        .rows
+        .iter()
+        .map(de::from_row)
+        .collect::<std::result::Result<Vec<RequirementRecord>, _>>()
+        .map_err(Error::database)?;
+
+    let trees = build_trees(&records);
+
+    let roots: Vec<String> = trees
+        .into_iter()
+        .map(|(_, tree)| tree.to_string())
+        .collect();
+
+    Ok(match roots.is_empty() {
+        true => None,
+        false => Some(roots.join("; ")),
+    })
+}
+
+/// Reads every tracked field of a stored course
+pub fn course_snapshot(db: &Client, course_id: i64) -> Result<Snapshot> {
+    // the fields and the attributes, flags aren't tracked
+    let res = db
+        .execute(Statement::with_args(
+            format!(
+                "SELECT {}, {}, retired_at FROM UndergraduateCourse WHERE id = ?",
+                FIELD_COLUMNS.join(", "),
+                ATTRIBUTE_COLUMNS.join(", ")
+            ),
+            args!(course_id),
+        ))
+        .map_err(Error::database)?;
+    let row = match res.rows.first() {
+        Some(row) => row,
+        None => return Err(Error::database("course couldn't be found.")),
+    };
+    let (fields, values) = row.values.split_at(FIELD_COLUMNS.len());
+    let (attributes, values) = values.split_at(ATTRIBUTE_COLUMNS.len());
+
+    let mut snapshot = Snapshot::new();
+
+    for (column, value) in FIELD_COLUMNS.iter().zip(fields) {
+        snapshot.insert(column, value_text(value));
+    }
+
+    // attributes are compared as a set, e.g. "GN, GQ"
+    let attributes: Vec<&str> = ATTRIBUTE_COLUMNS
+        .iter()
+        .zip(attributes)
+        .filter(|(_, value)| matches!(value, Value::Integer { value: 1 }))
+        .map(|(column, _)| *column)
+        .collect();
+    snapshot.insert(
+        "attributes",
+        match attributes.is_empty() {
+            true => None,
+            false => Some(attributes.join(", ")),
+        },
+    );
+
+    snapshot.insert(
+        "status",
+        Some(match values.first() {
+            Some(Value::Null) => "active".into(),
+            _ => "retired".into(),
+        }),
+    );
+
+    snapshot.insert(
+        "crosslist",
+        joined_names(
+            db,
+            Statement::with_args(
+                format!(
+                    "SELECT {IDENTIFIER_SQL} AS name FROM UndergraduateCourseCrossLists JOIN UndergraduateCourse ON crossed_course_id = UndergraduateCourse.id WHERE course_id = ?"
+                ),
+                args!(course_id),
+            ),
+        )?,
+    );
+
+    for (field, table) in ["prerequisites", "concurrent", "corequisites", "recommended"]
+        .into_iter()
+        .zip(REQUIREMENT_TABLES)
+    {
+        snapshot.insert(field, requirement_text(db, course_id, table)?);
+    }
+
+    Ok(snapshot)
+}
+
+/// Reads every tracked field of a stored program
+pub fn program_snapshot(db: &Client, program_id: i64) -> Result<Snapshot> {
+    let res = db
+        .execute(Statement::with_args(
+            r#"SELECT title, image, type, name, retired_at FROM UndergraduateProgram
+            JOIN UndergraduateProgramType ON UndergraduateProgram.type_id = UndergraduateProgramType.id
+            LEFT JOIN College ON UndergraduateProgram.college_id = College.id
+            WHERE UndergraduateProgram.id = ?"#,
+            args!(program_id),
+        ))
+        .map_err(Error::database)?;
+    let row = match res.rows.first() {
+        Some(row) => row,
+        None => return Err(Error::database("program couldn't be found.")),
+    };
+
+    let mut snapshot = Snapshot::new();
+
+    for (field, value) in ["title", "image", "type", "college"]
+        .iter()
+        .zip(&row.values)
+    {
+        snapshot.insert(field, value_text(value));
+    }
+
+    snapshot.insert(
+        "status",
+        Some(match row.values[4] {
+            Value::Null => "active".into(),
+            _ => "retired".into(),
+        }),
+    );
+
+    snapshot.insert(
+        "campuses",
+        joined_names(
+            db,
+            Statement::with_args(
+                "SELECT name FROM Campus WHERE program_id = ?",
+                args!(program_id),
+            ),
+        )?,
+    );
+    snapshot.insert(
+        "keywords",
+        joined_names(
+            db,
+            Statement::with_args(
+                "SELECT keyword AS name FROM Keywords WHERE program_id = ?",
+                args!(program_id),
+            ),
+        )?,
+    );
+
+    Ok(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bulletin::{CourseRequirementTree, UndergraduateCourse, UndergraduateProgram},
+        database::{
+            connect, tests::database, StalePolicy, SyncOptions, SyncUndergraduateCourses,
+            SyncUndergraduatePrograms, Synchronizable,
+        },
+    };
+
+    #[derive(serde::Deserialize)]
+    struct ChangeRecord {
+        label: String,
+        field: String,
+        old_value: Option<String>,
+        new_value: Option<String>,
+    }
+
+    /// Changes recorded in `run_id` as (label, field, old value, new value)
+    fn changes(
+        db: &Client,
+        table: &str,
+        label: &str,
+        run_id: i64,
+    ) -> Vec<(String, String, Option<String>, Option<String>)> {
+        let res = db
+            .execute(Statement::with_args(
+                format!(
+                    "SELECT {label} AS label, field, old_value, new_value FROM {table} WHERE run_id = ? ORDER BY label, field"
+                ),
+                args!(run_id),
+            ))
+            .unwrap();
+
+        res.rows
+            .iter()
+            .map(|row| de::from_row::<ChangeRecord>(row).unwrap())
+            .map(|record| {
+                (
+                    record.label,
+                    record.field,
+                    record.old_value,
+                    record.new_value,
+                )
+            })
+            .collect()
+    }
+
+    fn change(
+        label: &str,
+        field: &str,
+        old_value: Option<&str>,
+        new_value: Option<&str>,
+    ) -> (String, String, Option<String>, Option<String>) {
+        (
+            label.into(),
+            field.into(),
+            old_value.map(String::from),
+            new_value.map(String::from),
+        )
+    }
+
+    #[test]
+    fn changed_courses_are_logged() {
+        let url = database("course-changes");
+        let options = SyncOptions::default();
+        let mut courses = vec![
+            UndergraduateCourse::with_prerequisites("MATH 140", None),
+            UndergraduateCourse::with_prerequisites("MATH 141", Some("MATH 140")),
+            UndergraduateCourse::with_prerequisites("MATH 230", None),
+        ];
+        let first = SyncUndergraduateCourses::sync(&url, &courses, &options).unwrap();
+
+        courses[1].title = "Calculus II".into();
+        courses[1].requirements.prerequisites =
+            Some(CourseRequirementTree::try_from("C or better in MATH 140").unwrap());
+        courses.pop();
+        courses.push(UndergraduateCourse::with_prerequisites("MATH 231", None));
+        let second = SyncUndergraduateCourses::sync(&url, &courses, &options).unwrap();
+
+        let db = connect(&url).unwrap();
+        assert_eq!(3, first.changes);
+        assert_eq!(
+            vec![
+                change("MATH 140", "status", None, Some("active")),
+                change("MATH 141", "status", None, Some("active")),
+                change("MATH 230", "status", None, Some("active")),
+            ],
+            changes(&db, "CourseChange", "identifier", first.run_id.unwrap())
+        );
+        assert_eq!(4, second.changes);
+        assert_eq!(
+            vec![
+                change(
+                    "MATH 141",
+                    "prerequisites",
+                    Some("MATH 140"),
+                    Some("C OR BETTER IN MATH 140")
+                ),
+                change("MATH 141", "title", Some("MATH 141"), Some("Calculus II")),
+                change("MATH 230", "status", Some("active"), Some("retired")),
+                change("MATH 231", "status", None, Some("active")),
+            ],
+            changes(&db, "CourseChange", "identifier", second.run_id.unwrap())
+        );
+    }
+
+    #[test]
+    fn changed_programs_are_logged() {
+        let url = database("program-changes");
+        let link = "/undergraduate/programs/majors/science/mathematics/";
+        let options = SyncOptions {
+            stale: StalePolicy::Delete,
+            ..Default::default()
+        };
+
+        SyncUndergraduatePrograms::sync(
+            &url,
+            &[UndergraduateProgram::baccalaureate(
+                "Mathematics",
+                link,
+                &["University Park"],
+            )],
+            &options,
+        )
+        .unwrap();
+        let second = SyncUndergraduatePrograms::sync(
+            &url,
+            &[UndergraduateProgram::baccalaureate(
+                "Mathematics",
+                link,
+                &["University Park", "Altoona"],
+            )],
+            &options,
+        )
+        .unwrap();
+        let third = SyncUndergraduatePrograms::sync(&url, &[], &options).unwrap();
+
+        let db = connect(&url).unwrap();
+        assert_eq!(
+            vec![change(
+                link,
+                "campuses",
+                Some("University Park"),
+                Some("Altoona, University Park")
+            )],
+            changes(&db, "ProgramChange", "link", second.run_id.unwrap())
+        );
+        assert_eq!(
+            vec![change(link, "status", Some("active"), None)],
+            changes(&db, "ProgramChange", "link", third.run_id.unwrap())
+        );
+    }
+}
@@ -0,0 +1,304 @@
+//! Reverse index of the requirement trees: which courses mention a course in their
+//! requirements, e.g. what taking STAT 200 opens up. Written while the trees are synced, so it
+//! always matches them.
+
+use std::fmt;
+
+use ego_tree::NodeRef;
+use libsql_client::{args, de, local::Client, Statement};
+
+use super::connect;
+use crate::{
+    bulletin::{CourseIdentifier, CourseRequirementNode},
+    database::changes::IDENTIFIER_SQL,
+    error::{Error, Result},
+};
+
+/// Which requirements of the dependent course mention the course
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RequirementKind {
+    Prerequisite,
+    Concurrent,
+    Corequisite,
+    Recommended,
+}
+
+/// Every kind, in `UndergraduateCourseRequirements` (and `REQUIREMENT_TABLES`) order
+pub const REQUIREMENT_KINDS: [RequirementKind; 4] = [
+    RequirementKind::Prerequisite,
+    RequirementKind::Concurrent,
+    RequirementKind::Corequisite,
+    RequirementKind::Recommended,
+];
+
+impl From<RequirementKind> for &str {
+    fn from(value: RequirementKind) -> Self {
+        match value {
+            RequirementKind::Prerequisite => "prerequisite",
+            RequirementKind::Concurrent => "concurrent",
+            RequirementKind::Corequisite => "corequisite",
+            RequirementKind::Recommended => "recommended",
+        }
+    }
+}
+
+impl TryFrom<&str> for RequirementKind {
+    type Error = Error;
+    fn try_from(value: &str) -> Result<Self> {
+        REQUIREMENT_KINDS
+            .into_iter()
+            .find(|kind| <&str>::from(*kind) == value)
+            .ok_or_else(|| Error::unknown("requirement kind", value))
+    }
+}
+
+impl fmt::Display for RequirementKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", <&str>::from(*self))
+    }
+}
+
+/// A course whose requirements mention the course that was looked up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependent {
+    pub course: CourseIdentifier,
+    pub kind: RequirementKind,
+    /// `false` if it's only one option among alternatives
+    pub mandatory: bool,
+}
+
+#[derive(serde::Deserialize)]
+struct DependentRecord {
+    identifier: String,
+    kind: String,
+    mandatory: i64,
+}
+
+/// Whether the requirement `node` is needed whatever option is picked, i.e. every group above
+/// it is an AND
+pub fn is_mandatory(node: NodeRef<CourseRequirementNode>) -> bool {
+    node.ancestors()
+        .all(|ancestor| matches!(ancestor.value(), CourseRequirementNode::AND))
+}
+
+/// Records that the requirements of `dependent_id` of `kind` mention `course_id`
+pub fn insert(
+    db: &Client,
+    course_id: i64,
+    dependent_id: i64,
+    kind: RequirementKind,
+    mandatory: bool,
+) -> Result<()> {
+    db.execute(Statement::with_args(
+        r#"INSERT INTO UndergraduateCourseDependents (course_id, dependent_id, kind, mandatory) VALUES (?, ?, ?, ?)
+        ON CONFLICT (course_id, dependent_id, kind) DO UPDATE SET mandatory = MAX(mandatory, excluded.mandatory)"#,
+        args!(course_id, dependent_id, <&str>::from(kind), mandatory as i64),
+    ))
+    .map_err(Error::database)?;
+
+    Ok(())
+}
+
+/// Every course still in the bulletin whose requirements mention `course` or a course
+/// crosslisted with it, sorted by identifier then kind
+pub fn dependents(url: &str, course: &CourseIdentifier) -> Result<Vec<Dependent>> {
+    let db = connect(url)?;
+
+    let course_id = match super::find_course_id(&db, course)? {
+        Some(course_id) => course_id,
+        None => return Err(Error::unknown("course", course.to_string())),
+    };
+
+    let res = db
+        .execute(Statement::with_args(
+            format!(
+                r#"SELECT {IDENTIFIER_SQL} AS identifier, kind, MAX(mandatory) AS mandatory
+                FROM UndergraduateCourseDependents
+                JOIN UndergraduateCourse ON UndergraduateCourseDependents.dependent_id = UndergraduateCourse.id
+                WHERE UndergraduateCourse.retired_at IS NULL AND UndergraduateCourseDependents.course_id IN (
+                    SELECT ?
+                    UNION SELECT crossed_course_id FROM UndergraduateCourseCrossLists WHERE course_id = ?
+                    UNION SELECT course_id FROM UndergraduateCourseCrossLists WHERE crossed_course_id = ?
+                )
+                GROUP BY UndergraduateCourse.id, kind"#
+            ),
+            args!(course_id, course_id, course_id),
+        ))
+        .map_err(Error::database)?;
+
+    let mut dependents = res
+        .rows
+        .iter()
+        .map(|row| {
+            let record: DependentRecord = de::from_row(row).map_err(Error::database)?;
+
+            Ok(Dependent {
+                course: CourseIdentifier::try_from(record.identifier.as_str())?,
+                kind: RequirementKind::try_from(record.kind.as_str())?,
+                mandatory: record.mandatory != 0,
+            })
+        })
+        .collect::<Result<Vec<Dependent>>>()?;
+
+    dependents.sort_by(|a, b| (&a.course, a.kind).cmp(&(&b.course, b.kind)));
+    Ok(dependents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bulletin::{CourseRequirementTree, UndergraduateCourse},
+        database::{
+            migrations::{self, tests::migrate_to},
+            tests::database,
+            SyncOptions, SyncUndergraduateCourses, Synchronizable,
+        },
+    };
+
+    fn course(identifier: &str) -> CourseIdentifier {
+        CourseIdentifier::try_from(identifier).unwrap()
+    }
+
+    fn dependent(identifier: &str, mandatory: bool) -> Dependent {
+        Dependent {
+            course: course(identifier),
+            kind: RequirementKind::Prerequisite,
+            mandatory,
+        }
+    }
+
+    #[test]
+    fn dependents_are_found_through_crosslists() {
+        let url = database("dependents");
+
+        let mut math_484 = UndergraduateCourse::with_prerequisites("MATH 484", None);
+        math_484.crosslist = Some(vec![course("CMPSC 484")]);
+        let mut courses = vec![
+            UndergraduateCourse::with_prerequisites("MATH 140", None),
+            UndergraduateCourse::with_prerequisites("STAT 200", None),
+            math_484,
+            UndergraduateCourse::with_prerequisites("CMPSC 484", None),
+            UndergraduateCourse::with_prerequisites("MATH 485", Some("CMPSC 484")),
+            UndergraduateCourse::with_prerequisites(
+                "CMPSC 465",
+                Some("MATH 140 and (MATH 484 or STAT 200)"),
+            ),
+            UndergraduateCourse::with_prerequisites("MATH 497", Some("MATH 484")),
+        ];
+        SyncUndergraduateCourses::sync(&url, &courses, &SyncOptions::default()).unwrap();
+
+        // retired courses open nothing up anymore
+        courses.pop();
+        SyncUndergraduateCourses::sync(&url, &courses, &SyncOptions::default()).unwrap();
+
+        assert_eq!(
+            vec![dependent("CMPSC 465", false), dependent("MATH 485", true)],
+            dependents(&url, &course("MATH 484")).unwrap()
+        );
+        assert_eq!(
+            vec![dependent("CMPSC 465", true)],
+            dependents(&url, &course("MATH 140")).unwrap()
+        );
+        assert!(dependents(&url, &course("CMPSC 465")).unwrap().is_empty());
+        assert!(dependents(&url, &course("MATH 999")).is_err());
+    }
+
+    #[test]
+    fn only_courses_under_ands_are_mandatory() {
+        let tree = CourseRequirementTree::try_from(
+            "MATH 140 and (MATH 141 or STAT 200) and (CMPSC 121 and CMPSC 131) and at least 1 of the following: (MATH 220, MATH 230)",
+        )
+        .unwrap();
+
+        let mandatory = tree
+            .tree
+            .root()
+            .descendants()
+            .filter(|node| matches!(node.value(), CourseRequirementNode::COURSE(..)))
+            .map(|node| (node.value().to_string(), is_mandatory(node)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                ("MATH 140".to_string(), true),
+                ("MATH 141".to_string(), false),
+                ("STAT 200".to_string(), false),
+                ("CMPSC 121".to_string(), true),
+                ("CMPSC 131".to_string(), true),
+                ("MATH 220".to_string(), false),
+                ("MATH 230".to_string(), false),
+            ],
+            mandatory
+        );
+
+        // a lone course sits in the AND every tree has at its root
+        let tree = CourseRequirementTree::try_from("MATH 140").unwrap();
+        assert!(is_mandatory(tree.tree.root().first_child().unwrap()));
+    }
+
+    #[test]
+    fn existing_trees_are_backfilled() {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct DependentRow {
+            course_id: i64,
+            dependent_id: i64,
+            kind: String,
+            mandatory: i64,
+        }
+
+        let db = Client::new(database("dependents-backfill")).unwrap();
+        migrate_to(&db, 8);
+        db.execute(
+            r#"INSERT INTO UndergraduateCourse (id, code, number) VALUES (1, 'MATH', 140), (2, 'MATH', 141), (3, 'CMPSC', 465)"#,
+        )
+        .unwrap();
+        // MATH 141: MATH 140
+        // CMPSC 465: MATH 140 AND (MATH 141 OR MATH 140 OR ENGL 15)
+        db.execute(
+            r#"INSERT INTO UndergraduateCoursePrerequisites (id, logic, course_id, req_course_id, parent) VALUES
+            (1, 'C', 2, 1, NULL),
+            (2, '&', 3, NULL, NULL),
+            (3, 'C', NULL, 1, 2),
+            (4, '|', NULL, NULL, 2),
+            (5, 'C', NULL, 2, 4),
+            (6, 'C', NULL, 1, 4),
+            (7, 'T', NULL, NULL, 4)"#,
+        )
+        .unwrap();
+        db.execute(
+            r#"INSERT INTO UndergraduateCourseCorequisites (id, logic, course_id, req_course_id, parent) VALUES (1, 'C', 2, 3, NULL)"#,
+        )
+        .unwrap();
+
+        migrations::migrate(&db).unwrap();
+
+        let res = db
+            .execute(
+                "SELECT course_id, dependent_id, kind, mandatory FROM UndergraduateCourseDependents ORDER BY course_id, dependent_id, kind",
+            )
+            .unwrap();
+        let rows = res
+            .rows
+            .iter()
+            .map(|row| de::from_row::<DependentRow>(row).unwrap())
+            .collect::<Vec<_>>();
+
+        let row = |course_id, dependent_id, kind: &str, mandatory| DependentRow {
+            course_id,
+            dependent_id,
+            kind: kind.to_string(),
+            mandatory,
+        };
+        assert_eq!(
+            vec![
+                row(1, 2, "prerequisite", 1),
+                // mandatory wins over being one of the options
+                row(1, 3, "prerequisite", 1),
+                row(2, 3, "prerequisite", 0),
+                row(3, 2, "corequisite", 1),
+            ],
+            rows
+        );
+    }
+}
@@ -0,0 +1,365 @@
+//! Ordered schema migrations. The version of a database is the highest version in its
+//! `schema_version` table; every migration above it is applied, in order, when connecting.
+//!
+//! To change the schema, append a migration; never edit one that has already been released.
+//!
+//! SQL steps live in `migrations/`, named after the version they belong to. Versions 3, 6, 7 and
+//! 8 have no file: they only add columns, which are `Step::AddColumn`s since SQLite can't add a
+//! column only if it's missing.
+
+use libsql_client::{args, de, local::Client, Statement};
+
+use super::{execute_all, Transaction};
+use crate::error::{Context, Error, Result};
+
+enum Step {
+    /// One or more `;` separated statements (comments must not contain `;`)
+    Sql(&'static str),
+    /// `ALTER TABLE table ADD COLUMN column definition`, skipped if the column is already there
+    /// (databases from before migrations may have it)
+    AddColumn {
+        table: &'static str,
+        column: &'static str,
+        definition: &'static str,
+    },
+}
+
+struct Migration {
+    version: i64,
+    description: &'static str,
+    steps: &'static [Step],
+}
+
+// tables are created with IF NOT EXISTS, databases from before migrations already have them
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create program tables",
+        steps: &[Step::Sql(include_str!(
+            "../../migrations/0001_create_programs.sql"
+        ))],
+    },
+    Migration {
+        version: 2,
+        description: "create course tables",
+        steps: &[Step::Sql(include_str!(
+            "../../migrations/0002_create_courses.sql"
+        ))],
+    },
+    Migration {
+        version: 3,
+        description: "add retired_at to courses and programs",
+        steps: &[
+            Step::AddColumn {
+                table: "UndergraduateProgram",
+                column: "retired_at",
+                definition: "DATETIME",
+            },
+            Step::AddColumn {
+                table: "UndergraduateCourse",
+                column: "retired_at",
+                definition: "DATETIME",
+            },
+        ],
+    },
+    Migration {
+        version: 4,
+        description: "create change log tables",
+        steps: &[Step::Sql(include_str!(
+            "../../migrations/0004_create_change_log.sql"
+        ))],
+    },
+    Migration {
+        version: 5,
+        description: "index requirement trees",
+        steps: &[Step::Sql(include_str!(
+            "../../migrations/0005_index_requirement_trees.sql"
+        ))],
+    },
+    Migration {
+        version: 6,
+        description: "add detail and min_grade to requirement trees",
+        steps: &[
+            Step::AddColumn {
+                table: "UndergraduateCoursePrerequisites",
+                column: "detail",
+                definition: "VARCHAR(255)",
+            },
+            Step::AddColumn {
+                table: "UndergraduateCourseConcurrent",
+                column: "detail",
+                definition: "VARCHAR(255)",
+            },
+            Step::AddColumn {
+                table: "UndergraduateCourseCorequisites",
+                column: "detail",
+                definition: "VARCHAR(255)",
+            },
+            Step::AddColumn {
+                table: "UndergraduateCourseRecommended",
+                column: "detail",
+                definition: "VARCHAR(255)",
+            },
+            Step::AddColumn {
+                table: "UndergraduateCoursePrerequisites",
+                column: "min_grade",
+                definition: "VARCHAR(2)",
+            },
+            Step::AddColumn {
+                table: "UndergraduateCourseConcurrent",
+                column: "min_grade",
+                definition: "VARCHAR(2)",
+            },
+            Step::AddColumn {
+                table: "UndergraduateCourseCorequisites",
+                column: "min_grade",
+                definition: "VARCHAR(2)",
+            },
+            Step::AddColumn {
+                table: "UndergraduateCourseRecommended",
+                column: "min_grade",
+                definition: "VARCHAR(2)",
+            },
+        ],
+    },
+    Migration {
+        version: 7,
+        description: "add required_count to requirement trees",
+        steps: &[
+            Step::AddColumn {
+                table: "UndergraduateCoursePrerequisites",
+                column: "required_count",
+                definition: "INTEGER",
+            },
+            Step::AddColumn {
+                table: "UndergraduateCourseConcurrent",
+                column: "required_count",
+                definition: "INTEGER",
+            },
+            Step::AddColumn {
+                table: "UndergraduateCourseCorequisites",
+                column: "required_count",
+                definition: "INTEGER",
+            },
+            Step::AddColumn {
+                table: "UndergraduateCourseRecommended",
+                column: "required_count",
+                definition: "INTEGER",
+            },
+        ],
+    },
+    Migration {
+        version: 8,
+        description: "add prerequisite depth to courses",
+        steps: &[Step::AddColumn {
+            table: "UndergraduateCourse",
+            column: "depth",
+            definition: "INTEGER",
+        }],
+    },
+    Migration {
+        version: 9,
+        description: "create reverse requirement index",
+        steps: &[Step::Sql(include_str!(
+            "../../migrations/0009_create_course_dependents.sql"
+        ))],
+    },
+    Migration {
+        version: 10,
+        description: "create program detail tables",
+        steps: &[Step::Sql(include_str!(
+            "../../migrations/0010_create_program_details.sql"
+        ))],
+    },
+];
+
+#[derive(serde::Deserialize)]
+struct VersionRecord {
+    version: Option<i64>,
+}
+
+#[derive(serde::Deserialize)]
+struct ColumnRecord {
+    name: String,
+}
+
+/// Returns the version of the database, 0 if no migration was ever applied
+fn current_version(db: &Client) -> Result<i64> {
+    let res = db
+        .execute("SELECT MAX(version) AS version FROM schema_version")
+        .map_err(Error::database)?;
+
+    Ok(res
+        .rows
+        .iter()
+        .map(de::from_row)
+        .collect::<std::result::Result<Vec<VersionRecord>, _>>()
+        .map_err(Error::database)?
+        .first()
+        .and_then(|record| record.version)
+        .unwrap_or(0))
+}
+
+fn has_column(db: &Client, table: &str, column: &str) -> Result<bool> {
+    let res = db
+        .execute(format!("PRAGMA table_info({})", table))
+        .map_err(Error::database)?;
+
+    Ok(res
+        .rows
+        .iter()
+        .map(de::from_row)
+        .collect::<std::result::Result<Vec<ColumnRecord>, _>>()
+        .map_err(Error::database)?
+        .iter()
+        .any(|record| record.name == column))
+}
+
+fn apply(db: &Client, step: &Step) -> Result<()> {
+    match step {
+        Step::Sql(sql) => {
+            let statements = sql
+                .split(';')
+                // drop what's left after the last statement (whitespace and comments)
+                .filter(|statement| {
+                    statement
+                        .lines()
+                        .any(|line| !line.trim().is_empty() && !line.trim().starts_with("--"))
+                })
+                .map(Statement::new);
+
+            execute_all(db, statements)?;
+        }
+        Step::AddColumn {
+            table,
+            column,
+            definition,
+        } => {
+            if !has_column(db, table, column)? {
+                db.execute(format!(
+                    "ALTER TABLE {} ADD COLUMN {} {}",
+                    table, column, definition
+                ))
+                .map_err(Error::database)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Brings the database up to the latest version. Each migration is applied in its own
+/// transaction, so a failed one leaves the database at the previous version. Returns the
+/// migrations that were applied, e.g. "1 (create program tables)".
+pub fn migrate(db: &Client) -> Result<Vec<String>> {
+    db.execute(
+        r#"
+            CREATE TABLE IF NOT EXISTS schema_version (
+                version INTEGER PRIMARY KEY,
+                description VARCHAR(255) NOT NULL,
+                applied_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );
+        "#,
+    )
+    .map_err(Error::database)?;
+
+    let version = current_version(db)?;
+    let mut applied = Vec::new();
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > version) {
+        let transaction = Transaction::begin(db)?;
+
+        for step in migration.steps {
+            apply(db, step).with_context(|| {
+                format!(
+                    "Migration {} ({})",
+                    migration.version, migration.description
+                )
+            })?;
+        }
+
+        db.execute(Statement::with_args(
+            "INSERT INTO schema_version (version, description) VALUES (?, ?)",
+            args!(migration.version, migration.description),
+        ))
+        .map_err(Error::database)?;
+
+        transaction.commit()?;
+        applied.push(format!("{} ({})", migration.version, migration.description));
+    }
+
+    Ok(applied)
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use crate::database::tests::{count, database};
+
+    /// Applies the migrations up to `version`, like an older release of the scraper would have
+    pub(crate) fn migrate_to(db: &Client, version: i64) {
+        db.execute(
+            "CREATE TABLE schema_version (version INTEGER PRIMARY KEY, description VARCHAR(255) NOT NULL, applied_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP)",
+        )
+        .unwrap();
+
+        for migration in MIGRATIONS.iter().filter(|m| m.version <= version) {
+            for step in migration.steps {
+                apply(db, step).unwrap();
+            }
+            db.execute(Statement::with_args(
+                "INSERT INTO schema_version (version, description) VALUES (?, ?)",
+                args!(migration.version, migration.description),
+            ))
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn old_databases_are_brought_up_to_date() {
+        let db = Client::new(database("migrate-old")).unwrap();
+        migrate_to(&db, 5);
+        db.execute(
+            "INSERT INTO UndergraduateCourse (id, code, number, title) VALUES (1, 'MATH', 140, 'Calculus')",
+        )
+        .unwrap();
+
+        let applied = migrate(&db).unwrap();
+
+        assert_eq!(
+            MIGRATIONS
+                .iter()
+                .filter(|m| m.version > 5)
+                .map(|m| format!("{} ({})", m.version, m.description))
+                .collect::<Vec<_>>(),
+            applied
+        );
+        assert_eq!(
+            MIGRATIONS.last().unwrap().version,
+            current_version(&db).unwrap()
+        );
+        assert!(has_column(&db, "UndergraduateCoursePrerequisites", "required_count").unwrap());
+        assert!(has_column(&db, "UndergraduateCourse", "depth").unwrap());
+        assert_eq!(
+            1,
+            count(
+                &db,
+                "SELECT COUNT(*) AS count FROM UndergraduateCourse WHERE title = 'Calculus'"
+            )
+        );
+
+        // nothing left to apply
+        assert!(migrate(&db).unwrap().is_empty());
+    }
+
+    #[test]
+    fn databases_from_before_migrations_keep_their_columns() {
+        let db = Client::new(database("migrate-unversioned")).unwrap();
+        for step in MIGRATIONS[..3].iter().flat_map(|m| m.steps) {
+            apply(&db, step).unwrap();
+        }
+
+        assert_eq!(MIGRATIONS.len(), migrate(&db).unwrap().len());
+        assert!(has_column(&db, "UndergraduateCourse", "retired_at").unwrap());
+    }
+}
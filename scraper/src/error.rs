@@ -0,0 +1,114 @@
+use std::{fmt, path::PathBuf};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// A page couldn't be retrieved
+    Fetch { url: String, message: String },
+    /// A local file (cache, fixture, report, ...) couldn't be read or written
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// An element, attribute or text node expected in the HTML wasn't there
+    MissingElement { selector: &'static str },
+    /// A value that doesn't map onto any known variant (College, Campus, Attribute, ...)
+    Unknown { kind: &'static str, value: String },
+    /// A value that was found but couldn't be parsed
+    Malformed {
+        kind: &'static str,
+        value: String,
+        reason: &'static str,
+    },
+    /// Any error coming from libSQL
+    Database { message: String },
+    /// Wraps another error with where it happened (URL, course identifier, ...)
+    Context { context: String, source: Box<Error> },
+}
+
+impl Error {
+    pub fn unknown(kind: &'static str, value: impl Into<String>) -> Self {
+        Self::Unknown {
+            kind,
+            value: value.into(),
+        }
+    }
+
+    pub fn malformed(kind: &'static str, value: impl Into<String>, reason: &'static str) -> Self {
+        Self::Malformed {
+            kind,
+            value: value.into(),
+            reason,
+        }
+    }
+
+    pub fn database(error: impl fmt::Display) -> Self {
+        Self::Database {
+            message: error.to_string(),
+        }
+    }
+
+    /// Returns the innermost error, skipping any context
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Context { source, .. } => source.root(),
+            _ => self,
+        }
+    }
+
+    /// Short description of the step that failed, e.g. "selector `.course_credits`"
+    pub fn failed_step(&self) -> String {
+        match self.root() {
+            Error::Fetch { .. } => "fetch".into(),
+            Error::Io { .. } => "file access".into(),
+            Error::MissingElement { selector } => format!("selector `{}`", selector),
+            Error::Unknown { kind, .. } => format!("{} conversion", kind),
+            Error::Malformed { kind, .. } => format!("{} parsing", kind),
+            Error::Database { .. } => "database".into(),
+            Error::Context { .. } => unreachable!("root is never a context"),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Fetch { url, message } => write!(f, "Couldn't fetch {}: {}", url, message),
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::MissingElement { selector } => write!(f, "Couldn't find `{}`", selector),
+            Error::Unknown { kind, value } => write!(f, "Unknown {}: {:?}", kind, value),
+            Error::Malformed {
+                kind,
+                value,
+                reason,
+            } => write!(f, "Malformed {} {:?}: {}", kind, value, reason),
+            Error::Database { message } => write!(f, "Database error: {}", message),
+            Error::Context { context, source } => write!(f, "{}: {}", context, source),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+pub trait Context<T> {
+    /// Wraps the error (if any) with `context`, e.g. the URL or course being processed.
+    fn with_context<C: fmt::Display>(self, context: impl FnOnce() -> C) -> Result<T>;
+}
+
+impl<T> Context<T> for Result<T> {
+    fn with_context<C: fmt::Display>(self, context: impl FnOnce() -> C) -> Result<T> {
+        self.map_err(|source| Error::Context {
+            context: context().to_string(),
+            source: Box::new(source),
+        })
+    }
+}
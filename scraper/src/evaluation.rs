@@ -0,0 +1,488 @@
+//! Checks requirement trees against a student's coursework, e.g. whether someone with MATH 140
+//! (B) can take MATH 141, and if not, which courses they're missing.
+//!
+//! A tree evaluates to every minimal set of requirements that would still satisfy it: it's
+//! satisfied if one of them is empty. Clauses that can't be read off a transcript (permission,
+//! enrollment in a major, free text) are always reported as missing.
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt,
+};
+
+use ego_tree::NodeRef;
+
+use crate::{
+    bulletin::{
+        CourseIdentifier, CourseRequirementNode, CourseRequirementTree, Crosslists, Grade,
+        UndergraduateCourse,
+    },
+    error::Result,
+    utility::{minimal_sets, set_product},
+};
+
+/// More options than this are cut to the smallest ones, AT LEAST N OF a long list grows fast
+const MAX_OPTIONS: usize = 64;
+
+/// What a student has done so far
+#[derive(Debug, Clone, Default)]
+pub struct Transcript {
+    /// Completed courses, with their grade if it's known. A course without a grade counts as
+    /// passed with any grade.
+    pub completed: HashMap<CourseIdentifier, Option<Grade>>,
+    /// Courses taken this semester, they satisfy concurrent requirements and corequisites
+    pub concurrent: HashSet<CourseIdentifier>,
+    /// Semester standing, e.g. 5 for a student in their fifth semester
+    pub standing: u8,
+    /// Crosslisted courses count for each other
+    pub crosslists: Crosslists,
+}
+
+impl Transcript {
+    pub fn new(standing: u8) -> Self {
+        Self {
+            standing,
+            ..Default::default()
+        }
+    }
+
+    /// A transcript from entries like "MATH 140" or "MATH 140:B", the grade is optional
+    pub fn parse(standing: u8, completed: &[String], crosslists: Crosslists) -> Result<Self> {
+        let mut transcript = Self {
+            crosslists,
+            ..Self::new(standing)
+        };
+
+        for entry in completed {
+            let (course, grade) = match entry.rsplit_once(':') {
+                Some((course, grade)) => (course, Some(Grade::try_from(grade)?)),
+                None => (entry.as_str(), None),
+            };
+            transcript.complete(CourseIdentifier::try_from(course.trim())?, grade);
+        }
+
+        Ok(transcript)
+    }
+
+    pub fn complete(&mut self, course: CourseIdentifier, grade: Option<Grade>) {
+        self.completed.insert(course, grade);
+    }
+
+    pub fn enroll(&mut self, course: CourseIdentifier) {
+        self.concurrent.insert(course);
+    }
+
+    /// Whether `course` (or an equivalent) was passed with at least `min_grade`
+    pub fn has_passed(&self, course: &CourseIdentifier, min_grade: Option<Grade>) -> bool {
+        let min_grade = min_grade.unwrap_or(Grade::D);
+
+        self.crosslists.equivalents(course).any(|course| {
+            matches!(self.completed.get(course), Some(None))
+                || matches!(self.completed.get(course), Some(Some(grade)) if *grade >= min_grade)
+        })
+    }
+
+    pub fn is_taking(&self, course: &CourseIdentifier) -> bool {
+        self.crosslists
+            .equivalents(course)
+            .any(|course| self.concurrent.contains(course))
+    }
+}
+
+/// A single requirement a student doesn't meet yet
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Missing {
+    Course(CourseIdentifier, Option<Grade>),
+    Standing(u8),
+    Enrollment(String),
+    Permission(String),
+    Text(String),
+}
+
+impl fmt::Display for Missing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Missing::Course(course, Some(grade)) => write!(f, "{} OR BETTER IN {}", grade, course),
+            Missing::Course(course, None) => write!(f, "{}", course),
+            // same wording as the tree, e.g. "5TH SEMESTER STANDING"
+            Missing::Standing(semester) => {
+                write!(f, "{}", CourseRequirementNode::STANDING(*semester))
+            }
+            Missing::Enrollment(program) => write!(f, "ENROLLMENT IN {}", program),
+            Missing::Permission(whom) => write!(f, "PERMISSION OF {}", whom),
+            Missing::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Evaluation {
+    pub satisfied: bool,
+    /// Every minimal set of requirements that would satisfy the tree, smallest first. Empty if
+    /// it's already satisfied.
+    pub missing: Vec<BTreeSet<Missing>>,
+}
+
+impl Evaluation {
+    fn new(mut options: Vec<BTreeSet<Missing>>) -> Self {
+        let satisfied = options.iter().any(|option| option.is_empty());
+        if satisfied {
+            options.clear();
+        }
+
+        Self {
+            satisfied,
+            missing: options,
+        }
+    }
+
+    /// Nothing is missing
+    fn satisfied() -> Self {
+        Self::new(vec![BTreeSet::new()])
+    }
+
+    /// The options of the evaluation, a satisfied one has a single empty option
+    fn into_options(self) -> Vec<BTreeSet<Missing>> {
+        match self.satisfied {
+            true => vec![BTreeSet::new()],
+            false => self.missing,
+        }
+    }
+
+    /// Both have to be satisfied
+    pub fn and(self, other: Evaluation) -> Evaluation {
+        Evaluation::new(product(&self.into_options(), &other.into_options()))
+    }
+
+    /// Either has to be satisfied
+    pub fn or(self, other: Evaluation) -> Evaluation {
+        let mut options = self.into_options();
+        options.extend(other.into_options());
+        Evaluation::new(minimize(options))
+    }
+}
+
+/// Drops options that contain another option, then keeps the smallest ones
+fn minimize(options: Vec<BTreeSet<Missing>>) -> Vec<BTreeSet<Missing>> {
+    let mut options = minimal_sets(options);
+    options.truncate(MAX_OPTIONS);
+    options
+}
+
+/// Every way to satisfy both `a` and `b`
+fn product(a: &[BTreeSet<Missing>], b: &[BTreeSet<Missing>]) -> Vec<BTreeSet<Missing>> {
+    minimize(set_product(a, b))
+}
+
+/// Every way to satisfy `count` of `children`
+fn at_least(count: usize, children: &[Vec<BTreeSet<Missing>>]) -> Vec<BTreeSet<Missing>> {
+    if count == 0 {
+        return vec![BTreeSet::new()];
+    }
+
+    // more than there is can't be picked
+    if count > children.len() {
+        return vec![];
+    }
+
+    if count == children.len() {
+        return children
+            .iter()
+            .fold(vec![BTreeSet::new()], |options, child| {
+                product(&options, child)
+            });
+    }
+
+    // either the first child is one of them, or it isn't
+    let (first, rest) = children.split_first().unwrap();
+    let mut options = product(first, &at_least(count - 1, rest));
+    options.extend(at_least(count, rest));
+
+    minimize(options)
+}
+
+impl CourseRequirementTree {
+    /// Evaluates the tree against `transcript`. With `concurrent`, courses being taken this
+    /// semester count too (concurrent requirements and corequisites).
+    pub fn evaluate(&self, transcript: &Transcript, concurrent: bool) -> Evaluation {
+        fn options(
+            node: NodeRef<CourseRequirementNode>,
+            transcript: &Transcript,
+            concurrent: bool,
+        ) -> Vec<BTreeSet<Missing>> {
+            let missing = |missing: Missing| vec![BTreeSet::from([missing])];
+
+            let children = || -> Vec<Vec<BTreeSet<Missing>>> {
+                node.children()
+                    .map(|child| options(child, transcript, concurrent))
+                    .collect()
+            };
+
+            match node.value() {
+                CourseRequirementNode::COURSE(course, min_grade) => {
+                    match transcript.has_passed(course, *min_grade)
+                        || (concurrent && transcript.is_taking(course))
+                    {
+                        true => vec![BTreeSet::new()],
+                        false => missing(Missing::Course(course.clone(), *min_grade)),
+                    }
+                }
+                CourseRequirementNode::STANDING(semester) => match transcript.standing >= *semester
+                {
+                    true => vec![BTreeSet::new()],
+                    false => missing(Missing::Standing(*semester)),
+                },
+                CourseRequirementNode::ENROLLMENT(program) => {
+                    missing(Missing::Enrollment(program.clone()))
+                }
+                CourseRequirementNode::PERMISSION(whom) => {
+                    missing(Missing::Permission(whom.clone()))
+                }
+                CourseRequirementNode::TEXT(text) => missing(Missing::Text(text.clone())),
+                CourseRequirementNode::AND => {
+                    let children = children();
+                    at_least(children.len(), &children)
+                }
+                // an empty OR asks for nothing, like an empty AND
+                CourseRequirementNode::OR => at_least(1, &children()),
+                CourseRequirementNode::AT_LEAST(count) => {
+                    let children = children();
+                    match *count as usize {
+                        // an empty group asks for nothing, like an empty AND
+                        _ if children.is_empty() => vec![BTreeSet::new()],
+                        // can't be met, which is still worth saying why
+                        count if count > children.len() => missing(Missing::Text(format!(
+                            "{} {} OPTIONS",
+                            node.value(),
+                            children.len()
+                        ))),
+                        count => at_least(count, &children),
+                    }
+                }
+            }
+        }
+
+        Evaluation::new(options(self.tree.root(), transcript, concurrent))
+    }
+}
+
+impl UndergraduateCourse {
+    /// Whether a student with `transcript` has what this course needs beforehand. Concurrent
+    /// requirements and corequisites can be taken alongside it, so they're only checked here
+    /// when they replace the prerequisites.
+    pub fn evaluate_prerequisites(&self, transcript: &Transcript) -> Evaluation {
+        let requirements = &self.requirements;
+        let prerequisites = match requirements.prerequisites {
+            Some(ref tree) => tree.evaluate(transcript, false),
+            None => Evaluation::satisfied(),
+        };
+
+        // "Prerequisite: MATH 140 or Concurrent: MATH 140" takes either
+        match (
+            self.flags.is_prerequisite_concurrent_separate,
+            &requirements.concurrent,
+        ) {
+            (true, Some(concurrent)) => prerequisites.or(concurrent.evaluate(transcript, true)),
+            _ => prerequisites,
+        }
+    }
+
+    /// Whether a student with `transcript` can take this course, the recommended preparation
+    /// isn't required
+    pub fn evaluate(&self, transcript: &Transcript) -> Evaluation {
+        let evaluate = |tree: &Option<CourseRequirementTree>| match tree {
+            Some(tree) => tree.evaluate(transcript, true),
+            None => Evaluation::satisfied(),
+        };
+
+        let requirements = &self.requirements;
+        let prerequisites = self.evaluate_prerequisites(transcript);
+        let prerequisites = match self.flags.is_prerequisite_concurrent_separate {
+            true => prerequisites,
+            false => prerequisites.and(evaluate(&requirements.concurrent)),
+        };
+
+        prerequisites.and(evaluate(&requirements.corequisites))
+    }
+}
+
+/// Narrows down the courses `eligible` returns, a course has to match every field that's set
+#[derive(Debug, Default)]
+pub struct CourseFilter {
+    /// Subject codes, e.g. "MATH", the course has to be in one of them
+    pub subjects: Vec<String>,
+    /// Attribute codes, e.g. "GN" or "GWS", the course has to have one of them
+    pub attributes: Vec<String>,
+    /// Credits the course can be taken for
+    pub credits: Option<f32>,
+}
+
+impl CourseFilter {
+    fn matches(&self, course: &UndergraduateCourse) -> Result<bool> {
+        let subject = self.subjects.is_empty()
+            || self
+                .subjects
+                .iter()
+                .any(|subject| subject.eq_ignore_ascii_case(&course.identifier.code));
+
+        let mut attribute = self.attributes.is_empty();
+        for code in &self.attributes {
+            attribute |= course.attribute_list.contains_code(code)?;
+        }
+
+        let credits = match (self.credits, course.min_credits) {
+            (None, _) => true,
+            (Some(credits), Some(min_credits)) => (min_credits..=course.credits).contains(&credits),
+            (Some(credits), None) => course.credits == credits,
+        };
+
+        Ok(subject && attribute && credits)
+    }
+}
+
+/// Every course in `courses` matching `filter` whose prerequisites `transcript` meets, sorted by
+/// identifier. Courses that were already passed, or a course crosslisted with them, are left out.
+pub fn eligible<'a>(
+    courses: &'a [UndergraduateCourse],
+    transcript: &Transcript,
+    filter: &CourseFilter,
+) -> Result<Vec<&'a UndergraduateCourse>> {
+    let mut eligible = Vec::new();
+
+    for course in courses {
+        if transcript.has_passed(&course.identifier, None) || !filter.matches(course)? {
+            continue;
+        }
+
+        if course.evaluate_prerequisites(transcript).satisfied {
+            eligible.push(course);
+        }
+    }
+
+    eligible.sort_by(|a, b| a.identifier.cmp(&b.identifier));
+    Ok(eligible)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn course(identifier: &str) -> CourseIdentifier {
+        CourseIdentifier::try_from(identifier).unwrap()
+    }
+
+    fn tree(requirement: &str) -> CourseRequirementTree {
+        CourseRequirementTree::try_from(requirement).unwrap()
+    }
+
+    /// The options of an evaluation as text
+    fn missing(evaluation: &Evaluation) -> Vec<Vec<String>> {
+        evaluation
+            .missing
+            .iter()
+            .map(|option| option.iter().map(|item| item.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn grades_are_compared() {
+        for (grade, min_grade, passed) in [
+            (Some(Grade::A), Some(Grade::C), true),
+            (Some(Grade::C), Some(Grade::C), true),
+            (Some(Grade::CPlus), Some(Grade::BMinus), false),
+            (Some(Grade::D), Some(Grade::C), false),
+            // any passing grade without a minimum
+            (Some(Grade::D), None, true),
+            (Some(Grade::F), None, false),
+            // without a grade, the course counts as passed
+            (None, Some(Grade::A), true),
+        ] {
+            let mut transcript = Transcript::new(1);
+            transcript.complete(course("MATH 140"), grade);
+
+            assert_eq!(
+                passed,
+                transcript.has_passed(&course("MATH 140"), min_grade),
+                "{grade:?} for {min_grade:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn missing_requirements_are_listed() {
+        let mut transcript = Transcript::new(3);
+        transcript.complete(course("PHYS 211"), Some(Grade::C));
+
+        for (requirement, expected) in [
+            ("PHYS 211", vec![]),
+            (
+                "MATH 140 or MATH 141 and PHYS 211",
+                vec![vec!["MATH 140"], vec!["MATH 141"]],
+            ),
+            (
+                "C or better in MATH 141 and PHYS 211",
+                vec![vec!["C OR BETTER IN MATH 141"]],
+            ),
+            (
+                "B or better in PHYS 211 and (MATH 140 or MATH 141) or 5th semester standing",
+                vec![
+                    vec!["5TH SEMESTER STANDING"],
+                    vec!["B OR BETTER IN MATH 140", "B OR BETTER IN PHYS 211"],
+                    vec!["B OR BETTER IN MATH 141", "B OR BETTER IN PHYS 211"],
+                ],
+            ),
+            (
+                "at least 2 of the following: MATH 140, MATH 141, PHYS 211, PHYS 212",
+                vec![vec!["MATH 140"], vec!["MATH 141"], vec!["PHYS 212"]],
+            ),
+            // having all of them isn't enough for more than there are
+            (
+                "at least 3 of the following: PHYS 211, MATH 141",
+                vec![vec!["AT LEAST 3 OF 2 OPTIONS"]],
+            ),
+            (
+                "permission of the instructor or 3rd semester standing",
+                vec![],
+            ),
+        ] {
+            let evaluation = tree(requirement).evaluate(&transcript, false);
+
+            assert_eq!(expected.is_empty(), evaluation.satisfied, "{requirement}");
+            assert_eq!(expected, missing(&evaluation), "{requirement}");
+        }
+    }
+
+    #[test]
+    fn courses_taken_alongside_only_count_when_concurrent() {
+        let mut transcript = Transcript::new(1);
+        transcript.enroll(course("MATH 230"));
+        let requirement = tree("MATH 230");
+
+        assert!(requirement.evaluate(&transcript, true).satisfied);
+        assert!(!requirement.evaluate(&transcript, false).satisfied);
+    }
+
+    #[test]
+    fn options_are_cut_to_the_smallest() {
+        // 5 of 12 is 792 ways
+        let requirement = tree(
+            "at least 5 of the following: MATH 401, MATH 402, MATH 403, MATH 404, MATH 405, MATH 406, \
+            MATH 407, MATH 408, MATH 409, MATH 410, MATH 411, MATH 412",
+        );
+        let evaluation = requirement.evaluate(&Transcript::new(1), false);
+
+        assert_eq!(MAX_OPTIONS, evaluation.missing.len());
+        assert!(evaluation.missing.iter().all(|option| option.len() == 5));
+    }
+
+    #[test]
+    fn standing_reads_like_the_tree() {
+        for semester in [1, 2, 3, 5, 11] {
+            assert_eq!(
+                CourseRequirementNode::STANDING(semester).to_string(),
+                Missing::Standing(semester).to_string()
+            );
+        }
+        assert_eq!("5TH SEMESTER STANDING", Missing::Standing(5).to_string());
+    }
+}
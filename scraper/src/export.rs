@@ -0,0 +1,412 @@
+//! Writes scraped programs and courses to JSON or CSV files next to the database, so the
+//! catalog can be served as static files, opened in a spreadsheet or read without SQLite.
+//!
+//! Items are sorted (courses by identifier, programs by title and link) so the same bulletin
+//! always gives the same file.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use serde::Serialize;
+
+pub mod dot;
+
+use crate::{
+    bulletin::{
+        CourseRequirementTree, FullAttributeList, UndergraduateCourse, UndergraduateProgram,
+        UndergraduateProgramType, ATTRIBUTE_COUNT,
+    },
+    database::{SyncOptions, SyncSummary, Synchronizable},
+    error::{Error, Result},
+};
+
+pub struct ExportUndergraduatePrograms;
+pub struct ExportUndergraduateCourses;
+
+/// Output format of an export
+#[derive(Clone, Copy, Debug, Default, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// A single JSON array
+    #[default]
+    Json,
+    /// One JSON object per line
+    Ndjson,
+    /// Flattened columns, for spreadsheets
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+/// An item flattened into a CSV row
+trait CsvRecord {
+    fn header() -> Vec<String>;
+    fn record(&self) -> Vec<String>;
+}
+
+impl CsvRecord for UndergraduateProgram {
+    fn header() -> Vec<String> {
+        ["title", "type", "college", "campuses", "link"]
+            .map(String::from)
+            .into()
+    }
+
+    fn record(&self) -> Vec<String> {
+        let (raw_type, campus_list) = match &self.program_type {
+            UndergraduateProgramType::BaccalaureateDegree(raw_type, campus_list)
+            | UndergraduateProgramType::AssociateDegree(raw_type, campus_list) => {
+                (raw_type, campus_list.to_string())
+            }
+            UndergraduateProgramType::Minor(raw_type)
+            | UndergraduateProgramType::Certificate(raw_type)
+            | UndergraduateProgramType::ROTC(raw_type) => (raw_type, String::new()),
+        };
+
+        vec![
+            self.title.clone(),
+            raw_type.clone(),
+            self.college
+                .as_ref()
+                .map_or(String::new(), |college| college.to_string()),
+            campus_list,
+            self.link.clone(),
+        ]
+    }
+}
+
+impl CsvRecord for UndergraduateCourse {
+    // identifier, title, credits, min_credits, one column per attribute, crosslists and the
+    // four requirement trees
+    fn header() -> Vec<String> {
+        let mut header: Vec<String> = ["identifier", "title", "credits", "min_credits"]
+            .map(String::from)
+            .into();
+        header.extend(FullAttributeList::names().into_iter().map(String::from));
+        header.extend(
+            [
+                "crosslists",
+                "prerequisites",
+                "concurrent",
+                "corequisites",
+                "recommended",
+            ]
+            .map(String::from),
+        );
+
+        header
+    }
+
+    fn record(&self) -> Vec<String> {
+        let tree = |tree: &Option<CourseRequirementTree>| {
+            tree.as_ref().map_or(String::new(), |tree| tree.to_string())
+        };
+
+        let mut record = vec![
+            self.identifier.to_string(),
+            self.title.clone(),
+            self.credits.to_string(),
+            self.min_credits
+                .map_or(String::new(), |min_credits| min_credits.to_string()),
+        ];
+        record.extend(
+            (0..ATTRIBUTE_COUNT).map(|idx| match self.attribute_list.get(idx) {
+                true => "X".to_string(),
+                false => String::new(),
+            }),
+        );
+        record.push(self.crosslist.as_ref().map_or(String::new(), |crosslist| {
+            crosslist
+                .iter()
+                .map(|course| course.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        }));
+
+        let requirements = &self.requirements;
+        record.extend([
+            tree(&requirements.prerequisites),
+            tree(&requirements.concurrent),
+            tree(&requirements.corequisites),
+            tree(&requirements.recommended),
+        ]);
+
+        record
+    }
+}
+
+/// Writes `items` to `path` in `format`
+fn write<T: Serialize + CsvRecord>(path: &Path, items: &[&T], format: ExportFormat) -> Result<()> {
+    let io_error = |source: std::io::Error| Error::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    let json_error = |e: serde_json::Error| io_error(e.into());
+    let csv_error = |e: csv::Error| io_error(e.into());
+
+    let mut writer = BufWriter::new(File::create(path).map_err(io_error)?);
+
+    match format {
+        ExportFormat::Csv => {
+            let mut csv = csv::Writer::from_writer(&mut writer);
+
+            csv.write_record(T::header()).map_err(csv_error)?;
+            for item in items {
+                csv.write_record(item.record()).map_err(csv_error)?;
+            }
+
+            csv.flush().map_err(io_error)?;
+        }
+        ExportFormat::Ndjson => {
+            for item in items {
+                serde_json::to_writer(&mut writer, item).map_err(json_error)?;
+                writeln!(writer).map_err(io_error)?;
+            }
+        }
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, items).map_err(json_error)?;
+            writeln!(writer).map_err(io_error)?;
+        }
+    }
+
+    writer.flush().map_err(io_error)
+}
+
+// the export is a snapshot of what was scraped, stale rows and subjects only matter to the
+// database, so only the format is used
+impl Synchronizable<UndergraduateProgram> for ExportUndergraduatePrograms {
+    fn sync(
+        path: &str,
+        items: &[UndergraduateProgram],
+        options: &SyncOptions,
+    ) -> Result<SyncSummary> {
+        let mut programs: Vec<&UndergraduateProgram> = items.iter().collect();
+        programs.sort_by(|a, b| (&a.title, &a.link).cmp(&(&b.title, &b.link)));
+
+        write(Path::new(path), &programs, options.export_format)?;
+        Ok(SyncSummary {
+            saved: programs.len(),
+            ..Default::default()
+        })
+    }
+}
+
+impl Synchronizable<UndergraduateCourse> for ExportUndergraduateCourses {
+    fn sync(
+        path: &str,
+        items: &[UndergraduateCourse],
+        options: &SyncOptions,
+    ) -> Result<SyncSummary> {
+        let mut courses: Vec<&UndergraduateCourse> = items.iter().collect();
+        courses.sort_by(|a, b| a.identifier.cmp(&b.identifier));
+
+        write(Path::new(path), &courses, options.export_format)?;
+        Ok(SyncSummary {
+            saved: courses.len(),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::bulletin::CourseIdentifier;
+
+    /// A file of its own for every test, in a temporary directory
+    fn export_file(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("scrapper-export-{name}"));
+        let _ = std::fs::remove_file(&path);
+
+        path.to_string_lossy().into_owned()
+    }
+
+    fn options(export_format: ExportFormat) -> SyncOptions<'static> {
+        SyncOptions {
+            export_format,
+            ..Default::default()
+        }
+    }
+
+    fn courses() -> Vec<UndergraduateCourse> {
+        ["MATH 141", "CMPSC 121", "MATH 140H", "MATH 140"]
+            .into_iter()
+            .map(|identifier| UndergraduateCourse::with_prerequisites(identifier, None))
+            .collect()
+    }
+
+    fn programs() -> Vec<UndergraduateProgram> {
+        vec![
+            UndergraduateProgram::baccalaureate("Statistics", "/statistics-bs/", &[]),
+            UndergraduateProgram::baccalaureate("Mathematics", "/mathematics-bs/", &[]),
+            UndergraduateProgram::baccalaureate("Mathematics", "/mathematics-ba/", &[]),
+        ]
+    }
+
+    #[test]
+    fn json_exports_are_sorted_arrays() {
+        let path = export_file("courses.json");
+        let summary =
+            ExportUndergraduateCourses::sync(&path, &courses(), &options(ExportFormat::Json))
+                .unwrap();
+        assert_eq!(4, summary.saved);
+
+        let exported: Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let identifiers: Vec<&str> = exported
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|course| course["identifier"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            vec!["CMPSC 121", "MATH 140", "MATH 140H", "MATH 141"],
+            identifiers
+        );
+
+        let path = export_file("programs.json");
+        ExportUndergraduatePrograms::sync(&path, &programs(), &options(ExportFormat::Json))
+            .unwrap();
+
+        let exported: Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let links: Vec<&str> = exported
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|program| program["link"].as_str().unwrap())
+            .collect();
+        // by title, then link
+        assert_eq!(
+            vec!["/mathematics-ba/", "/mathematics-bs/", "/statistics-bs/"],
+            links
+        );
+    }
+
+    #[test]
+    fn ndjson_exports_have_a_course_per_line() {
+        // the format is what's asked for, whatever the file is called
+        let path = export_file("courses.txt");
+        ExportUndergraduateCourses::sync(&path, &courses(), &options(ExportFormat::Ndjson))
+            .unwrap();
+
+        let exported = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = exported.lines().collect();
+        assert_eq!(4, lines.len());
+        assert!(exported.ends_with('\n'));
+
+        let first: Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!("CMPSC 121", first["identifier"]);
+        assert_eq!(3.0, first["credits"]);
+        assert!(first["requirements"]["prerequisites"].is_null());
+    }
+
+    /// Header and rows of the CSV file at `path`
+    fn read_csv(path: &str) -> (Vec<String>, Vec<Vec<String>>) {
+        let mut reader = csv::Reader::from_path(path).unwrap();
+        let header = reader.headers().unwrap().iter().map(String::from).collect();
+        let rows = reader
+            .records()
+            .map(|record| record.unwrap().iter().map(String::from).collect())
+            .collect();
+
+        (header, rows)
+    }
+
+    #[test]
+    fn csv_exports_have_a_column_per_field() {
+        let mut courses = courses();
+        let math_141 = &mut courses[0];
+        math_141.title = "Calculus With Analytic Geometry II".into();
+        math_141.credits = 4.0;
+        math_141
+            .attribute_list
+            .add("General Education: Quantification (GQ)")
+            .unwrap();
+        math_141.crosslist = Some(vec![
+            CourseIdentifier::try_from("MATH 141E").unwrap(),
+            CourseIdentifier::try_from("MATH 141G").unwrap(),
+        ]);
+        math_141.requirements.prerequisites =
+            Some(CourseRequirementTree::try_from("MATH 140 or MATH 140H").unwrap());
+
+        let path = export_file("courses.csv");
+        ExportUndergraduateCourses::sync(&path, &courses, &options(ExportFormat::Csv)).unwrap();
+
+        let (header, rows) = read_csv(&path);
+        assert_eq!(
+            ["identifier", "title", "credits", "min_credits"],
+            header[..4]
+        );
+        assert_eq!(4 + ATTRIBUTE_COUNT + 5, header.len());
+        assert_eq!(4, rows.len());
+
+        let column = |name: &str| header.iter().position(|column| column == name).unwrap();
+        let math_141 = &rows[3];
+        assert_eq!("MATH 141", math_141[column("identifier")]);
+        assert_eq!(
+            "Calculus With Analytic Geometry II",
+            math_141[column("title")]
+        );
+        assert_eq!("4", math_141[column("credits")]);
+        assert_eq!("", math_141[column("min_credits")]);
+        assert_eq!(
+            "X",
+            math_141[column("General Education: Quantification (GQ)")]
+        );
+        assert_eq!("", math_141[column("Honors")]);
+        assert_eq!("MATH 141E, MATH 141G", math_141[column("crosslists")]);
+        assert_eq!("MATH 140 OR MATH 140H", math_141[column("prerequisites")]);
+        assert_eq!("", math_141[column("recommended")]);
+
+        let path = export_file("programs.csv");
+        let mut programs = programs();
+        programs[0].program_type = UndergraduateProgramType::Minor("Minor".into());
+        programs[1] = UndergraduateProgram::baccalaureate(
+            "Mathematics",
+            "/mathematics-bs/",
+            &["University Park", "Abington"],
+        );
+        ExportUndergraduatePrograms::sync(&path, &programs, &options(ExportFormat::Csv)).unwrap();
+
+        let (header, rows) = read_csv(&path);
+        assert_eq!(vec!["title", "type", "college", "campuses", "link"], header);
+        assert_eq!(
+            vec![
+                vec![
+                    "Mathematics",
+                    "B.S.",
+                    "Eberly College of Science",
+                    "",
+                    "/mathematics-ba/"
+                ],
+                vec![
+                    "Mathematics",
+                    "B.S.",
+                    "Eberly College of Science",
+                    "Abington, University Park",
+                    "/mathematics-bs/"
+                ],
+                vec![
+                    "Statistics",
+                    "Minor",
+                    "Eberly College of Science",
+                    "",
+                    "/statistics-bs/"
+                ],
+            ],
+            rows
+        );
+    }
+}
@@ -0,0 +1,335 @@
+//! Graphviz DOT rendering of the prerequisite graph, e.g.
+//! `scrapper graph --root "CMPSC 465" | dot -Tsvg > cmpsc465.svg`.
+//!
+//! Courses point to the courses that need them. An AND of several requirements is drawn as
+//! edges straight into the course at the top of a tree, and as a small junction node further
+//! down; OR and AT LEAST groups are always junctions. Courses outside the filter, or that
+//! weren't scraped, are dashed.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use ego_tree::NodeRef;
+
+use crate::{
+    bulletin::{
+        CourseIdentifier, CourseRequirementNode, CourseRequirementTree, Crosslists,
+        UndergraduateCourse,
+    },
+    error::{Error, Result},
+};
+
+/// Which courses get their prerequisites drawn
+pub enum GraphFilter {
+    /// Every course
+    All,
+    /// Courses of these subject codes, e.g. "MATH"
+    Subjects(Vec<String>),
+    /// A course and what it needs, `depth` courses deep (all the way down if `None`)
+    Prerequisites {
+        root: CourseIdentifier,
+        depth: Option<usize>,
+    },
+}
+
+/// Escapes `text` for a quoted DOT string
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Every course required somewhere in `tree`
+fn required_courses(tree: &CourseRequirementTree) -> impl Iterator<Item = &CourseIdentifier> {
+    tree.tree.nodes().filter_map(|node| match node.value() {
+        CourseRequirementNode::COURSE(course, _) => Some(course),
+        _ => None,
+    })
+}
+
+#[derive(Default)]
+struct Graph {
+    /// Courses drawn so far, and whether their prerequisites are drawn too
+    courses: BTreeMap<CourseIdentifier, bool>,
+    /// Junction and clause nodes, by name
+    nodes: Vec<(String, String)>,
+    edges: Vec<String>,
+}
+
+impl Graph {
+    /// Adds a junction or clause node, returning its name
+    fn node(&mut self, attributes: String) -> String {
+        let name = format!("n{}", self.nodes.len());
+        self.nodes.push((name.clone(), attributes));
+        name
+    }
+
+    fn edge(&mut self, from: &str, to: &str, label: Option<String>) {
+        self.edges.push(match label {
+            Some(label) => format!("{} -> {} [label={}]", from, to, quote(&label)),
+            None => format!("{} -> {}", from, to),
+        });
+    }
+
+    /// Draws `node` (and what's below it) pointing to `target`
+    fn add(&mut self, node: NodeRef<CourseRequirementNode>, target: &str) {
+        let label = match node.value() {
+            CourseRequirementNode::COURSE(course, min_grade) => {
+                self.courses.entry(course.clone()).or_insert(false);
+                let label = min_grade.map(|grade| format!("{} or better", grade));
+                return self.edge(&quote(&course.to_string()), target, label);
+            }
+            CourseRequirementNode::AT_LEAST(count) => format!("{} OF", count),
+            group @ (CourseRequirementNode::AND | CourseRequirementNode::OR) => group.to_string(),
+            // standing, enrollment, permission and text
+            clause => {
+                let name = self.node(format!("shape=note, label={}", quote(&clause.to_string())));
+                return self.edge(&name, target, None);
+            }
+        };
+
+        // a group of one is just that one
+        if node.children().count() == 1 {
+            return self.add(node.first_child().unwrap(), target);
+        }
+
+        let junction = self.node(format!(
+            "shape=circle, width=0.3, fontsize=8, label={}",
+            quote(&label)
+        ));
+        self.edge(&junction, target, None);
+        for child in node.children() {
+            self.add(child, &junction);
+        }
+    }
+
+    /// Draws the prerequisites of `course`
+    fn add_course(&mut self, course: &UndergraduateCourse, crosslists: &Crosslists) {
+        self.courses.insert(course.identifier.clone(), true);
+
+        let mut tree = match course.requirements.prerequisites {
+            Some(ref tree) => tree.clone(),
+            None => return,
+        };
+        tree.normalize(crosslists);
+
+        let target = quote(&course.identifier.to_string());
+        let root = tree.tree.root();
+        match root.value() {
+            // everything at the top is needed, no need for a junction
+            CourseRequirementNode::AND => {
+                for child in root.children() {
+                    self.add(child, &target);
+                }
+            }
+            _ => self.add(root, &target),
+        }
+    }
+
+    fn into_dot(self, scraped: &HashMap<&CourseIdentifier, &UndergraduateCourse>) -> String {
+        let mut lines = vec![
+            "digraph prerequisites {".to_string(),
+            "    rankdir=LR;".to_string(),
+            "    node [shape=box, fontname=\"Helvetica\"];".to_string(),
+            "    edge [fontsize=8];".to_string(),
+        ];
+
+        for (identifier, is_expanded) in &self.courses {
+            let mut attributes = Vec::<String>::new();
+            if let Some(course) = scraped.get(identifier) {
+                attributes.push(format!("tooltip={}", quote(&course.title)));
+            }
+            if !is_expanded {
+                attributes.push("style=dashed".to_string());
+            }
+
+            lines.push(match attributes.is_empty() {
+                true => format!("    {};", quote(&identifier.to_string())),
+                false => format!(
+                    "    {} [{}];",
+                    quote(&identifier.to_string()),
+                    attributes.join(", ")
+                ),
+            });
+        }
+
+        for (name, attributes) in &self.nodes {
+            lines.push(format!("    {} [{}];", name, attributes));
+        }
+        for edge in &self.edges {
+            lines.push(format!("    {};", edge));
+        }
+
+        lines.push("}".to_string());
+        lines.join("\n") + "\n"
+    }
+}
+
+/// Renders the prerequisites of the courses selected by `filter` as a DOT graph
+pub fn prerequisite_graph(courses: &[UndergraduateCourse], filter: &GraphFilter) -> Result<String> {
+    let scraped: HashMap<&CourseIdentifier, &UndergraduateCourse> = courses
+        .iter()
+        .map(|course| (&course.identifier, course))
+        .collect();
+    let crosslists = Crosslists::new(courses);
+
+    let mut selected: Vec<&UndergraduateCourse> = match filter {
+        GraphFilter::All => courses.iter().collect(),
+        GraphFilter::Subjects(subjects) => courses
+            .iter()
+            .filter(|course| {
+                subjects
+                    .iter()
+                    .any(|subject| subject.eq_ignore_ascii_case(&course.identifier.code))
+            })
+            .collect(),
+        GraphFilter::Prerequisites { root, depth } => {
+            if !scraped.contains_key(root) {
+                return Err(Error::unknown("course", root.to_string()));
+            }
+
+            // breadth first, so every course is reached by its shortest path
+            let mut selected = Vec::new();
+            let mut seen = BTreeSet::from([root]);
+            let mut queue = VecDeque::from([(root, 0)]);
+
+            while let Some((identifier, level)) = queue.pop_front() {
+                let course = match scraped.get(identifier) {
+                    Some(course) => course,
+                    None => continue,
+                };
+                if depth.is_some_and(|depth| level >= depth) {
+                    continue;
+                }
+
+                selected.push(*course);
+                for required in course
+                    .requirements
+                    .prerequisites
+                    .iter()
+                    .flat_map(required_courses)
+                {
+                    if seen.insert(required) {
+                        queue.push_back((required, level + 1));
+                    }
+                }
+            }
+
+            selected
+        }
+    };
+    selected.sort_by(|a, b| a.identifier.cmp(&b.identifier));
+
+    let mut graph = Graph::default();
+    for course in selected {
+        graph.add_course(course, &crosslists);
+    }
+
+    // the root is drawn even if it doesn't need anything, or the depth is 0
+    if let GraphFilter::Prerequisites { root, .. } = filter {
+        graph.courses.entry(root.clone()).or_insert(false);
+    }
+
+    Ok(graph.into_dot(&scraped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn courses() -> Vec<UndergraduateCourse> {
+        [
+            ("CMPSC 131", None),
+            ("CMPSC 360", Some("CMPSC 131")),
+            (
+                "CMPSC 465",
+                Some(
+                    "CMPSC 360 and (C or better in MATH 140 or MATH 141) and 5th semester standing",
+                ),
+            ),
+            ("MATH 140", None),
+        ]
+        .into_iter()
+        .map(|(identifier, prerequisites)| {
+            UndergraduateCourse::with_prerequisites(identifier, prerequisites)
+        })
+        .collect()
+    }
+
+    fn root(depth: Option<usize>) -> GraphFilter {
+        GraphFilter::Prerequisites {
+            root: CourseIdentifier::try_from("CMPSC 465").unwrap(),
+            depth,
+        }
+    }
+
+    /// The lines of the graph between the header and the closing brace
+    fn body(dot: &str) -> Vec<&str> {
+        let lines: Vec<&str> = dot.lines().map(str::trim).collect();
+        lines[4..lines.len() - 1].to_vec()
+    }
+
+    #[test]
+    fn groups_below_the_top_are_junctions() {
+        // MATH 141 wasn't scraped, so it's dashed even though the filter reaches it
+        let dot = prerequisite_graph(&courses(), &root(None)).unwrap();
+
+        assert!(dot.starts_with("digraph prerequisites {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert_eq!(
+            vec![
+                r#""CMPSC 131" [tooltip="CMPSC 131"];"#,
+                r#""CMPSC 360" [tooltip="CMPSC 360"];"#,
+                r#""CMPSC 465" [tooltip="CMPSC 465"];"#,
+                r#""MATH 140" [tooltip="MATH 140"];"#,
+                r#""MATH 141" [style=dashed];"#,
+                r#"n0 [shape=note, label="5TH SEMESTER STANDING"];"#,
+                r#"n1 [shape=circle, width=0.3, fontsize=8, label="OR"];"#,
+                r#""CMPSC 131" -> "CMPSC 360";"#,
+                r#""CMPSC 360" -> "CMPSC 465";"#,
+                r#"n0 -> "CMPSC 465";"#,
+                r#"n1 -> "CMPSC 465";"#,
+                // the grade is for the whole group
+                r#""MATH 140" -> n1 [label="C or better"];"#,
+                r#""MATH 141" -> n1 [label="C or better"];"#,
+            ],
+            body(&dot)
+        );
+    }
+
+    #[test]
+    fn courses_outside_the_subjects_are_dashed() {
+        let dot =
+            prerequisite_graph(&courses(), &GraphFilter::Subjects(vec!["math".into()])).unwrap();
+
+        // MATH 140 needs nothing, so nothing is drawn
+        assert_eq!(vec![r#""MATH 140" [tooltip="MATH 140"];"#], body(&dot));
+
+        let dot =
+            prerequisite_graph(&courses(), &GraphFilter::Subjects(vec!["CMPSC".into()])).unwrap();
+        let body = body(&dot);
+        assert!(body.contains(&r#""CMPSC 360" [tooltip="CMPSC 360"];"#));
+        assert!(body.contains(&r#""MATH 140" [tooltip="MATH 140", style=dashed];"#));
+    }
+
+    #[test]
+    fn roots_are_followed_as_deep_as_asked() {
+        let dot = prerequisite_graph(&courses(), &root(Some(1))).unwrap();
+        let lines = body(&dot);
+
+        // CMPSC 360 is drawn, but not what it needs
+        assert!(lines.contains(&r#""CMPSC 360" [tooltip="CMPSC 360", style=dashed];"#));
+        assert!(lines.contains(&r#""CMPSC 360" -> "CMPSC 465";"#));
+        assert!(!lines.iter().any(|line| line.contains("CMPSC 131")));
+
+        // the root is still drawn when nothing else is
+        let dot = prerequisite_graph(&courses(), &root(Some(0))).unwrap();
+        assert_eq!(
+            vec![r#""CMPSC 465" [tooltip="CMPSC 465", style=dashed];"#],
+            body(&dot)
+        );
+
+        let unknown = GraphFilter::Prerequisites {
+            root: CourseIdentifier::try_from("CMPSC 999").unwrap(),
+            depth: None,
+        };
+        assert!(prerequisite_graph(&courses(), &unknown).is_err());
+    }
+}
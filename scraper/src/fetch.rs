@@ -0,0 +1,226 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::error::{Error, Result};
+
+/// Cached pages are kept for 3 days by default
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60 * 60 * 24 * 3);
+
+/// Source of raw bulletin HTML, keyed by URL.
+pub trait Fetcher {
+    fn fetch(&self, url: &str) -> Result<String>;
+}
+
+fn io_error(path: &Path) -> impl FnOnce(std::io::Error) -> Error + '_ {
+    move |source| Error::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// Returns the location of a page inside a cache (or fixture) directory.
+///
+/// # Examples
+///
+/// ```
+/// let path = cache_path(Path::new("./cache"), "https://bulletins.psu.edu/programs");
+///
+/// assert_eq!(Path::new("./cache/bulletins.psu.edu/programs.html"), path);
+/// ```
+pub fn cache_path(directory: &Path, url: &str) -> PathBuf {
+    directory
+        .join(url.replace("https://", ""))
+        .with_extension("html")
+}
+
+/// Always makes a request to the live bulletin site.
+pub struct HttpFetcher;
+
+impl Fetcher for HttpFetcher {
+    fn fetch(&self, url: &str) -> Result<String> {
+        let fetch_error = |message: String| Error::Fetch {
+            url: url.into(),
+            message,
+        };
+
+        ureq::get(url)
+            .call()
+            .map_err(|e| fetch_error(e.to_string()))?
+            .into_string()
+            .map_err(|e| fetch_error(e.to_string()))
+    }
+}
+
+/// Wraps another fetcher and saves its pages to disk. Pages older than `ttl` are refetched.
+pub struct CacheFetcher<F: Fetcher> {
+    directory: PathBuf,
+    ttl: Duration,
+    inner: F,
+}
+
+impl<F: Fetcher> CacheFetcher<F> {
+    pub fn new(directory: impl Into<PathBuf>, ttl: Duration, inner: F) -> Self {
+        Self {
+            directory: directory.into(),
+            ttl,
+            inner,
+        }
+    }
+}
+
+impl<F: Fetcher> Fetcher for CacheFetcher<F> {
+    fn fetch(&self, url: &str) -> Result<String> {
+        let cache_file = cache_path(&self.directory, url);
+
+        // pages saved less than `ttl` ago are read back, older ones are only replaced once
+        // the refetch succeeded
+        if let Ok(metadata) = std::fs::metadata(&cache_file) {
+            let modified_at = metadata.modified().map_err(io_error(&cache_file))?;
+            let expired = modified_at
+                .elapsed()
+                .map(|age| age > self.ttl)
+                .unwrap_or(false);
+
+            if !expired {
+                return std::fs::read_to_string(&cache_file).map_err(io_error(&cache_file));
+            }
+        }
+
+        let body = self.inner.fetch(url)?;
+
+        // save to file
+        let mut cache_directory = cache_file.to_path_buf();
+        cache_directory.pop();
+
+        std::fs::create_dir_all(&cache_directory).map_err(io_error(&cache_directory))?;
+        std::fs::write(&cache_file, &body).map_err(io_error(&cache_file))?;
+        Ok(body)
+    }
+}
+
+/// Reads previously saved pages from a directory (same layout as `CacheFetcher`),
+/// never touching the network.
+pub struct DirectoryFetcher {
+    directory: PathBuf,
+}
+
+impl DirectoryFetcher {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+}
+
+impl Fetcher for DirectoryFetcher {
+    fn fetch(&self, url: &str) -> Result<String> {
+        let file = cache_path(&self.directory, url);
+
+        std::fs::read_to_string(&file).map_err(io_error(&file))
+    }
+}
+
+/// Serves pages straight from memory. Useful for fixtures, or for pages that were read some
+/// other way.
+#[allow(dead_code)]
+#[derive(Default)]
+pub struct MemoryFetcher {
+    pages: HashMap<String, String>,
+}
+
+#[allow(dead_code)]
+impl MemoryFetcher {
+    pub fn new() -> Self {
+        Self {
+            pages: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, url: impl Into<String>, html: impl Into<String>) {
+        self.pages.insert(url.into(), html.into());
+    }
+}
+
+impl Fetcher for MemoryFetcher {
+    fn fetch(&self, url: &str) -> Result<String> {
+        match self.pages.get(url) {
+            Some(body) => Ok(body.clone()),
+            None => Err(Error::Fetch {
+                url: url.into(),
+                message: "No page registered".into(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://bulletins.psu.edu/programs";
+
+    /// A cache directory of its own for every test, holding `html` for `URL` if set
+    fn cache_directory(name: &str, html: Option<&str>) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("scrapper-fetch-{name}"));
+        let _ = std::fs::remove_dir_all(&directory);
+
+        if let Some(html) = html {
+            let file = cache_path(&directory, URL);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(&file, html).unwrap();
+        }
+
+        directory
+    }
+
+    #[test]
+    fn fresh_pages_come_from_the_cache() {
+        let directory = cache_directory("fresh", Some("<p>cached</p>"));
+        let fetcher = CacheFetcher::new(&directory, DEFAULT_CACHE_TTL, MemoryFetcher::new());
+
+        assert_eq!("<p>cached</p>", fetcher.fetch(URL).unwrap());
+    }
+
+    #[test]
+    fn missing_pages_are_fetched_and_saved() {
+        let directory = cache_directory("missing", None);
+        let mut inner = MemoryFetcher::new();
+        inner.insert(URL, "<p>live</p>");
+        let fetcher = CacheFetcher::new(&directory, DEFAULT_CACHE_TTL, inner);
+
+        assert_eq!("<p>live</p>", fetcher.fetch(URL).unwrap());
+        assert_eq!(
+            "<p>live</p>",
+            DirectoryFetcher::new(&directory).fetch(URL).unwrap()
+        );
+    }
+
+    #[test]
+    fn expired_pages_are_replaced() {
+        let directory = cache_directory("expired", Some("<p>cached</p>"));
+        let mut inner = MemoryFetcher::new();
+        inner.insert(URL, "<p>live</p>");
+        let fetcher = CacheFetcher::new(&directory, Duration::ZERO, inner);
+
+        assert_eq!("<p>live</p>", fetcher.fetch(URL).unwrap());
+        assert_eq!(
+            "<p>live</p>",
+            DirectoryFetcher::new(&directory).fetch(URL).unwrap()
+        );
+    }
+
+    #[test]
+    fn expired_pages_are_kept_when_the_refetch_fails() {
+        let directory = cache_directory("failed", Some("<p>cached</p>"));
+        let fetcher = CacheFetcher::new(&directory, Duration::ZERO, MemoryFetcher::new());
+
+        assert!(fetcher.fetch(URL).is_err());
+        assert_eq!(
+            "<p>cached</p>",
+            DirectoryFetcher::new(&directory).fetch(URL).unwrap()
+        );
+    }
+}
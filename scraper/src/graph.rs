@@ -0,0 +1,496 @@
+//! The requirement graph of every course: an edge goes from a course to each course its
+//! prerequisites, concurrent requirements or corequisites mention, alternatives included.
+//!
+//! Crosslisted courses are a single node, named after the first of them (see
+//! `Crosslists::canonical`), so every identifier coming out of the graph is that one.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+};
+
+use ego_tree::NodeRef;
+
+use crate::bulletin::{
+    CourseIdentifier, CourseRequirementNode, CourseRequirementTree, Crosslists, UndergraduateCourse,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EdgeKind {
+    /// Has to be passed before
+    Prerequisite,
+    /// Can be passed before or taken the same semester
+    Concurrent,
+    /// Has to be taken the same semester
+    Corequisite,
+}
+
+impl fmt::Display for EdgeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EdgeKind::Prerequisite => write!(f, "prerequisite"),
+            EdgeKind::Concurrent => write!(f, "concurrent"),
+            EdgeKind::Corequisite => write!(f, "corequisite"),
+        }
+    }
+}
+
+/// `course` mentions `required` in its requirements of `kind`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Edge {
+    pub course: CourseIdentifier,
+    pub required: CourseIdentifier,
+    pub kind: EdgeKind,
+}
+
+/// Courses that require each other, directly or not
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cycle {
+    pub courses: BTreeSet<CourseIdentifier>,
+    /// Kinds of the edges between the courses of the cycle
+    pub kinds: BTreeSet<EdgeKind>,
+}
+
+impl Cycle {
+    /// A cycle of corequisites or concurrent requirements only, e.g. a lecture and its lab
+    /// that have to be taken together. Any other cycle can't be satisfied.
+    pub fn is_expected(&self) -> bool {
+        !self.kinds.contains(&EdgeKind::Prerequisite)
+    }
+}
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let courses: Vec<String> = self.courses.iter().map(|c| c.to_string()).collect();
+        let kinds: Vec<String> = self.kinds.iter().map(|k| k.to_string()).collect();
+
+        write!(f, "{} ({})", courses.join(", "), kinds.join(", "))
+    }
+}
+
+/// How long it takes to get to a course
+#[derive(Debug, Clone, Copy)]
+struct Depth {
+    /// Semesters of prerequisites needed before the course, one per course in the chain
+    semesters: usize,
+    /// Index of the prerequisite at the end of the longest chain
+    next: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct CourseGraph {
+    crosslists: Crosslists,
+    /// Every course, scraped or only required by another, sorted
+    courses: Vec<CourseIdentifier>,
+    index: HashMap<CourseIdentifier, usize>,
+    /// Courses required by each course
+    requires: Vec<BTreeSet<(usize, EdgeKind)>>,
+    /// `None` for courses that can't be reached without going round a prerequisite cycle, i.e.
+    /// every way to satisfy their prerequisites requires a course of the cycle
+    depths: Vec<Option<Depth>>,
+}
+
+/// Every course mentioned in `tree`
+fn tree_courses(tree: &CourseRequirementTree) -> impl Iterator<Item = &CourseIdentifier> {
+    tree.tree.nodes().filter_map(|node| match node.value() {
+        CourseRequirementNode::COURSE(course, _) => Some(course),
+        _ => None,
+    })
+}
+
+impl CourseGraph {
+    pub fn new(courses: &[UndergraduateCourse]) -> Self {
+        let crosslists = Crosslists::new(courses);
+        let canonical = |course: &CourseIdentifier| crosslists.canonical(course).clone();
+
+        let mut edges = BTreeSet::<Edge>::new();
+        let mut nodes = BTreeSet::<CourseIdentifier>::new();
+
+        for course in courses {
+            nodes.insert(canonical(&course.identifier));
+
+            let requirements = &course.requirements;
+            for (kind, tree) in [
+                (EdgeKind::Prerequisite, &requirements.prerequisites),
+                (EdgeKind::Concurrent, &requirements.concurrent),
+                (EdgeKind::Corequisite, &requirements.corequisites),
+            ] {
+                for required in tree.iter().flat_map(tree_courses) {
+                    nodes.insert(canonical(required));
+                    edges.insert(Edge {
+                        course: canonical(&course.identifier),
+                        required: canonical(required),
+                        kind,
+                    });
+                }
+            }
+        }
+
+        let identifiers: Vec<CourseIdentifier> = nodes.into_iter().collect();
+        let index: HashMap<CourseIdentifier, usize> = identifiers
+            .iter()
+            .enumerate()
+            .map(|(idx, course)| (course.clone(), idx))
+            .collect();
+
+        let mut requires = vec![BTreeSet::new(); identifiers.len()];
+        for edge in &edges {
+            requires[index[&edge.course]].insert((index[&edge.required], edge.kind));
+        }
+
+        let mut graph = Self {
+            crosslists,
+            courses: identifiers,
+            index,
+            requires,
+            depths: Vec::new(),
+        };
+
+        let mut trees = vec![Vec::new(); graph.courses.len()];
+        for course in courses {
+            if let (Some(node), Some(tree)) = (
+                graph.find(&course.identifier),
+                &course.requirements.prerequisites,
+            ) {
+                trees[node].push(tree);
+            }
+        }
+        graph.depths = graph.compute_depths(trees);
+
+        graph
+    }
+
+    fn find(&self, course: &CourseIdentifier) -> Option<usize> {
+        self.index.get(self.crosslists.canonical(course)).copied()
+    }
+
+    /// Every edge of the graph, sorted
+    pub fn edges(&self) -> impl Iterator<Item = Edge> + '_ {
+        self.requires
+            .iter()
+            .enumerate()
+            .flat_map(move |(idx, requires)| {
+                requires.iter().map(move |(required, kind)| Edge {
+                    course: self.courses[idx].clone(),
+                    required: self.courses[*required].clone(),
+                    kind: *kind,
+                })
+            })
+    }
+
+    /// Strongly connected components of the edges of `kinds`, in no particular order
+    fn components(&self, kinds: &[EdgeKind]) -> Vec<Vec<usize>> {
+        // Tarjan's algorithm
+        struct State {
+            counter: usize,
+            order: Vec<Option<usize>>,
+            low: Vec<usize>,
+            stack: Vec<usize>,
+            on_stack: Vec<bool>,
+            components: Vec<Vec<usize>>,
+        }
+
+        fn visit(graph: &CourseGraph, kinds: &[EdgeKind], state: &mut State, node: usize) {
+            state.order[node] = Some(state.counter);
+            state.low[node] = state.counter;
+            state.counter += 1;
+            state.stack.push(node);
+            state.on_stack[node] = true;
+
+            for &(next, kind) in &graph.requires[node] {
+                if !kinds.contains(&kind) {
+                    continue;
+                }
+
+                match state.order[next] {
+                    None => {
+                        visit(graph, kinds, state, next);
+                        state.low[node] = state.low[node].min(state.low[next]);
+                    }
+                    Some(order) if state.on_stack[next] => {
+                        state.low[node] = state.low[node].min(order)
+                    }
+                    Some(_) => (),
+                }
+            }
+
+            if Some(state.low[node]) == state.order[node] {
+                let mut component = Vec::new();
+                while let Some(member) = state.stack.pop() {
+                    state.on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                state.components.push(component);
+            }
+        }
+
+        let size = self.courses.len();
+        let mut state = State {
+            counter: 0,
+            order: vec![None; size],
+            low: vec![0; size],
+            stack: Vec::new(),
+            on_stack: vec![false; size],
+            components: Vec::new(),
+        };
+
+        for node in 0..size {
+            if state.order[node].is_none() {
+                visit(self, kinds, &mut state, node);
+            }
+        }
+
+        state.components
+    }
+
+    /// Groups of courses that end up requiring themselves, sorted. Crosslisted courses that
+    /// require each other show up as a course requiring itself.
+    pub fn cycles(&self) -> Vec<Cycle> {
+        let kinds = [
+            EdgeKind::Prerequisite,
+            EdgeKind::Concurrent,
+            EdgeKind::Corequisite,
+        ];
+
+        let mut cycles: Vec<Cycle> = self
+            .components(&kinds)
+            .into_iter()
+            .filter_map(|component| {
+                let kinds: BTreeSet<EdgeKind> = component
+                    .iter()
+                    .flat_map(|&node| &self.requires[node])
+                    .filter(|(required, _)| component.contains(required))
+                    .map(|(_, kind)| *kind)
+                    .collect();
+
+                // a single course is only a cycle if it requires itself
+                match kinds.is_empty() {
+                    true => None,
+                    false => Some(Cycle {
+                        courses: component
+                            .iter()
+                            .map(|&node| self.courses[node].clone())
+                            .collect(),
+                        kinds,
+                    }),
+                }
+            })
+            .collect();
+
+        cycles.sort_by(|a, b| a.courses.cmp(&b.courses));
+        cycles
+    }
+
+    fn compute_depths(&self, trees: Vec<Vec<&CourseRequirementTree>>) -> Vec<Option<Depth>> {
+        // semesters needed to satisfy `node`, alternatives take the quickest way
+        fn tree_depth(
+            graph: &CourseGraph,
+            depths: &[Option<Depth>],
+            node: NodeRef<CourseRequirementNode>,
+        ) -> Option<Depth> {
+            // quickest first, the ones that can't be reached last
+            let mut children: Vec<Option<Depth>> = node
+                .children()
+                .map(|child| tree_depth(graph, depths, child))
+                .collect();
+            children.sort_by_key(|child| child.map_or(usize::MAX, |child| child.semesters));
+
+            let none = Some(Depth {
+                semesters: 0,
+                next: None,
+            });
+
+            match node.value() {
+                CourseRequirementNode::COURSE(course, _) => {
+                    let required = graph.find(course)?;
+                    depths[required].map(|required_depth| Depth {
+                        semesters: required_depth.semesters + 1,
+                        next: Some(required),
+                    })
+                }
+                CourseRequirementNode::AND => match children.is_empty() {
+                    true => none,
+                    false => children.pop().unwrap(),
+                },
+                CourseRequirementNode::OR => match children.is_empty() {
+                    true => none,
+                    false => children[0],
+                },
+                CourseRequirementNode::AT_LEAST(count) => match *count as usize {
+                    _ if children.is_empty() => none,
+                    0 => none,
+                    // more than there are can't be met
+                    count if count > children.len() => None,
+                    count => children[count - 1],
+                },
+                // standing, enrollment, permission and text don't take a semester
+                _ => none,
+            }
+        }
+
+        // every course starts out unreachable and gets a depth once its prerequisites can be
+        // met by courses that already have one, so the edges going round a prerequisite cycle
+        // are never taken. Rounds go on until no course gets any quicker.
+        let mut depths: Vec<Option<Depth>> = vec![None; self.courses.len()];
+        loop {
+            let mut changed = false;
+
+            for node in 0..self.courses.len() {
+                // crosslisted courses have the same requirements, if they don't the strictest
+                // wins
+                let mut result = Some(Depth {
+                    semesters: 0,
+                    next: None,
+                });
+                for tree in &trees[node] {
+                    result = match (result, tree_depth(self, &depths, tree.tree.root())) {
+                        (Some(a), Some(b)) => Some(if b.semesters > a.semesters { b } else { a }),
+                        _ => None,
+                    };
+                }
+
+                if result.map(|depth| depth.semesters) != depths[node].map(|depth| depth.semesters)
+                {
+                    depths[node] = result;
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                return depths;
+            }
+        }
+    }
+
+    /// Semesters of prerequisites needed before `course` at the very least, `None` if it's not
+    /// in the graph or it can't be reached because of a prerequisite cycle
+    pub fn depth(&self, course: &CourseIdentifier) -> Option<usize> {
+        self.depths[self.find(course)?].map(|depth| depth.semesters)
+    }
+
+    /// The longest chain of prerequisites that can't be avoided before `course`, first course
+    /// to take first, `course` itself last
+    #[allow(dead_code)] // library API, nothing in the CLI needs it
+    pub fn chain(&self, course: &CourseIdentifier) -> Vec<CourseIdentifier> {
+        let mut chain = Vec::new();
+        let mut node = self.find(course);
+
+        while let Some(current) = node {
+            chain.push(self.courses[current].clone());
+            node = self.depths[current].and_then(|depth| depth.next);
+        }
+
+        chain.reverse();
+        chain
+    }
+
+    /// Every course `course` ultimately requires through edges of `kinds`, alternatives
+    /// included, sorted
+    #[allow(dead_code)] // library API, nothing in the CLI needs it
+    pub fn closure(&self, course: &CourseIdentifier, kinds: &[EdgeKind]) -> Vec<CourseIdentifier> {
+        let start = match self.find(course) {
+            Some(start) => start,
+            None => return Vec::new(),
+        };
+
+        let mut seen = BTreeSet::<usize>::new();
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            for &(required, kind) in &self.requires[node] {
+                if kinds.contains(&kind) && seen.insert(required) {
+                    stack.push(required);
+                }
+            }
+        }
+
+        // a course only requires itself through a cycle
+        seen.into_iter()
+            .map(|node| self.courses[node].clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn course(identifier: &str) -> CourseIdentifier {
+        CourseIdentifier::try_from(identifier).unwrap()
+    }
+
+    fn with_prerequisites(identifier: &str, prerequisites: Option<&str>) -> UndergraduateCourse {
+        UndergraduateCourse::with_prerequisites(identifier, prerequisites)
+    }
+
+    #[test]
+    fn depth_goes_around_cycles() {
+        let graph = CourseGraph::new(&[
+            with_prerequisites("CMPSC 121", None),
+            with_prerequisites("CMPSC 131", Some("CMPSC 121 or CMPSC 132")),
+            with_prerequisites("CMPSC 132", Some("CMPSC 131")),
+            with_prerequisites("CMPSC 221", Some("CMPSC 222")),
+            with_prerequisites("CMPSC 222", Some("CMPSC 221 or CMPSC 223")),
+            with_prerequisites("CMPSC 223", Some("CMPSC 222")),
+            with_prerequisites("CMPSC 465", Some("CMPSC 132 and (CMPSC 221 or CMPSC 121)")),
+        ]);
+
+        for (identifier, depth) in [
+            ("CMPSC 121", Some(0)),
+            // the cycle through CMPSC 132 is an alternative
+            ("CMPSC 131", Some(1)),
+            ("CMPSC 132", Some(2)),
+            // every way in goes round
+            ("CMPSC 221", None),
+            ("CMPSC 222", None),
+            ("CMPSC 223", None),
+            ("CMPSC 465", Some(3)),
+        ] {
+            assert_eq!(depth, graph.depth(&course(identifier)), "{identifier}");
+        }
+
+        assert_eq!(
+            vec![
+                course("CMPSC 121"),
+                course("CMPSC 131"),
+                course("CMPSC 132"),
+                course("CMPSC 465")
+            ],
+            graph.chain(&course("CMPSC 465"))
+        );
+    }
+
+    #[test]
+    fn more_than_there_are_cant_be_reached() {
+        let graph = CourseGraph::new(&[
+            with_prerequisites("MATH 140", None),
+            with_prerequisites("MATH 141", None),
+            with_prerequisites(
+                "MATH 230",
+                Some("at least 3 of the following: MATH 140, MATH 141"),
+            ),
+            with_prerequisites(
+                "MATH 231",
+                Some("at least 2 of the following: MATH 140, MATH 141"),
+            ),
+        ]);
+
+        assert_eq!(None, graph.depth(&course("MATH 230")));
+        assert_eq!(Some(1), graph.depth(&course("MATH 231")));
+    }
+
+    #[test]
+    fn empty_groups_take_no_time() {
+        let mut course = with_prerequisites("MATH 140", None);
+        course.requirements.prerequisites = Some(CourseRequirementTree {
+            tree: ego_tree::Tree::new(CourseRequirementNode::AT_LEAST(2)),
+        });
+
+        assert_eq!(
+            Some(0),
+            CourseGraph::new(&[course]).depth(&CourseIdentifier::try_from("MATH 140").unwrap())
+        );
+    }
+}
@@ -1,50 +1,560 @@
+#![allow(clippy::upper_case_acronyms)]
+
 mod bulletin;
+mod cli;
 mod database;
+mod error;
+mod evaluation;
+mod export;
+mod fetch;
+mod graph;
+mod normalize;
+mod planner;
+mod report;
+mod requirement;
 mod scrape;
 mod utility;
 
-use bulletin::UndergraduateCourse;
-use database::SyncUndergraduateCourses;
-use scrape::{ScrapeUndergraduateCourseGroups, ScrapeUndergraduateCourses, Scrappable};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bulletin::{CourseIdentifier, Crosslists, UndergraduateCourse, UndergraduateProgramDetails};
+use clap::Parser;
+use cli::{CacheCommand, Cli, Command};
+use database::{
+    catalog, dependents, StalePolicy, SyncOptions, SyncSummary, SyncUndergraduateCourses,
+};
+use error::{Error, Result};
+use evaluation::{CourseFilter, Transcript};
+use export::{
+    dot::{self, GraphFilter},
+    ExportFormat, ExportUndergraduateCourses, ExportUndergraduatePrograms,
+};
+use fetch::{CacheFetcher, DirectoryFetcher, Fetcher, HttpFetcher};
+use graph::CourseGraph;
+use report::ScrapeReport;
+use scrape::{
+    ScrapeMode, ScrapeUndergraduateCourseGroups, ScrapeUndergraduateCourses,
+    ScrapeUndergraduateProgramDetails, Scrappable,
+};
 
 use crate::{
-    database::{SyncUndergraduatePrograms, Synchronizable},
+    database::{SyncUndergraduateProgramDetails, SyncUndergraduatePrograms, Synchronizable},
     scrape::ScrapeUndergraduatePrograms,
 };
 
 fn main() {
-    let database_url: &str = "./bulletin.db";
+    let cli = Cli::parse();
+
+    if let Err(e) = run(&cli) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(cli: &Cli) -> Result<()> {
+    if let Command::Cache {
+        command: CacheCommand::Clear,
+    } = cli.command
+    {
+        return clear_cache(&cli.cache_dir);
+    }
+
+    // offline runs only read pages that are already in the cache directory
+    let fetcher: Box<dyn Fetcher> = match cli.offline {
+        true => Box::new(DirectoryFetcher::new(&cli.cache_dir)),
+        false => Box::new(CacheFetcher::new(
+            &cli.cache_dir,
+            Duration::from_secs(cli.cache_ttl.saturating_mul(3600)),
+            HttpFetcher,
+        )),
+    };
+    let fetcher = fetcher.as_ref();
+    let database_url: &str = &cli.database;
+
+    // with a report, broken records are skipped instead of stopping the run
+    let mut report = ScrapeReport::new();
+    let mut mode = match cli.report {
+        Some(_) => ScrapeMode::Lenient(&mut report),
+        None => ScrapeMode::Strict,
+    };
+
+    let export = cli
+        .export
+        .as_deref()
+        .map(|directory| (directory, cli.export_format));
+
+    // only drawing the graph works without the database
+    if !matches!(cli.command, Command::Graph { .. }) {
+        for migration in database::migrate(database_url)? {
+            println!("Applied migration {}.", migration);
+        }
+    }
+
+    let result = match cli.command {
+        Command::Programs { details } => {
+            sync_programs(fetcher, database_url, export, details, cli.stale, &mut mode)
+        }
+        Command::Courses { ref subjects } => sync_courses(
+            fetcher,
+            database_url,
+            export,
+            subjects,
+            cli.stale,
+            &mut mode,
+        ),
+        Command::All { details } => {
+            sync_programs(fetcher, database_url, export, details, cli.stale, &mut mode).and_then(
+                |_| sync_courses(fetcher, database_url, export, &[], cli.stale, &mut mode),
+            )
+        }
+        Command::Graph {
+            ref subjects,
+            ref root,
+            depth,
+            ref output,
+        } => graph(fetcher, subjects, root.as_deref(), depth, output, &mut mode),
+        Command::Unlocks { ref course } => unlocks(database_url, course),
+        Command::Eligible {
+            ref completed,
+            standing,
+            ref subjects,
+            ref attributes,
+            credits,
+        } => eligible(
+            database_url,
+            completed,
+            standing,
+            CourseFilter {
+                subjects: subjects.clone(),
+                attributes: attributes.clone(),
+                credits,
+            },
+        ),
+        Command::Plan {
+            ref targets,
+            ref program,
+            ref campus,
+            ref completed,
+            standing,
+            max_credits,
+        } => plan(
+            database_url,
+            targets,
+            program.as_deref(),
+            campus.as_deref(),
+            completed,
+            standing,
+            max_credits,
+        ),
+        Command::Cache { .. } => unreachable!("cache commands return early"),
+    };
+
+    // write the report even if the run failed, so skipped records aren't lost
+    if let Some(ref report_path) = cli.report {
+        report.write(report_path)?;
+
+        if !report.is_empty() {
+            println!(
+                "Skipped {} record(s), see {}",
+                report.failures.len(),
+                report_path.display()
+            );
+        }
+    }
+
+    result
+}
+
+/// Stale rows can only be trusted if no record was skipped, otherwise skipped records
+/// would look like they disappeared from the bulletin
+fn stale_policy(policy: StalePolicy, skipped_before: usize, mode: &ScrapeMode) -> StalePolicy {
+    if mode.skipped() > skipped_before && !matches!(policy, StalePolicy::Keep) {
+        println!("Some records were skipped, stale rows are kept this time.");
+        return StalePolicy::Keep;
+    }
+
+    policy
+}
+
+/// Path and format of the `name` export file in the export directory, which is created if
+/// needed
+fn export_path(
+    export: Option<(&Path, ExportFormat)>,
+    name: &str,
+) -> Result<Option<(String, ExportFormat)>> {
+    let (directory, format) = match export {
+        Some(export) => export,
+        None => return Ok(None),
+    };
+
+    std::fs::create_dir_all(directory).map_err(|source| Error::Io {
+        path: directory.to_path_buf(),
+        source,
+    })?;
+
+    let path = directory.join(format!("{}.{}", name, format.extension()));
+    Ok(Some((path.to_string_lossy().into_owned(), format)))
+}
+
+fn sync_programs(
+    fetcher: &dyn Fetcher,
+    database_url: &str,
+    export: Option<(&Path, ExportFormat)>,
+    details: bool,
+    stale: StalePolicy,
+    mode: &mut ScrapeMode,
+) -> Result<()> {
+    let skipped_before = mode.skipped();
     let programs_scrape_url: &str = &format!("{}/programs", bulletin::BASE_URL);
+    let programs = ScrapeUndergraduatePrograms::scrape(fetcher, programs_scrape_url, mode)?;
 
-    let body = utility::retrieve_document(programs_scrape_url);
-    let programs = ScrapeUndergraduatePrograms::extract_list(&body);
+    let options = SyncOptions {
+        stale: stale_policy(stale, skipped_before, mode),
+        subjects: None,
+        graph: None,
+        ..Default::default()
+    };
 
     // println!("Found {} programs", programs.len());
-    SyncUndergraduatePrograms::sync(database_url, &programs);
+    let summary = SyncUndergraduatePrograms::sync(database_url, &programs, &options)?;
+    print_summary("program", &summary, options.stale);
+    println!("Sync complete.");
+
+    if let Some((path, export_format)) = export_path(export, "programs")? {
+        let options = SyncOptions {
+            export_format,
+            ..Default::default()
+        };
+        ExportUndergraduatePrograms::sync(&path, &programs, &options)?;
+        println!("Exported to {}", path);
+    }
+
+    if details {
+        let skipped_before = mode.skipped();
+        let mut program_details = Vec::<UndergraduateProgramDetails>::new();
+
+        for program in &programs {
+            let details = ScrapeUndergraduateProgramDetails::scrape(fetcher, program, mode);
+            if let Some(details) =
+                mode.skip_page(details, &program.link, || Some(program.link.clone()))?
+            {
+                program_details.push(details);
+            }
+        }
+
+        let options = SyncOptions {
+            stale: stale_policy(options.stale, skipped_before, mode),
+            ..options
+        };
+        let summary =
+            SyncUndergraduateProgramDetails::sync(database_url, &program_details, &options)?;
+        if summary.removed > 0 {
+            println!("Deleted the details of {} program(s).", summary.removed);
+        }
+        println!("Saved the details of {} program(s).", summary.saved);
+    }
+
+    Ok(())
+}
+
+/// Prints what a sync of `kind` (e.g. "course") did besides saving what was scraped
+fn print_summary(kind: &str, summary: &SyncSummary, stale: StalePolicy) {
+    if summary.removed > 0 {
+        println!("{} {} {}(s).", stale, summary.removed, kind);
+    }
+
+    if let Some(run_id) = summary.run_id {
+        println!(
+            "Recorded {} {} change(s) in sync run {}.",
+            summary.changes, kind, run_id
+        );
+    }
+}
 
+/// Scrapes the courses of `subjects` (e.g. "MATH"), or of every subject if empty. Also returns
+/// the subjects that were fully scraped, `None` if every subject was.
+fn scrape_courses(
+    fetcher: &dyn Fetcher,
+    subjects: &[String],
+    mode: &mut ScrapeMode,
+) -> Result<(Vec<UndergraduateCourse>, Option<Vec<String>>)> {
     // undergrad course list
     let url = &format!(
         "{}/university-course-descriptions/undergraduate/",
         bulletin::BASE_URL
     );
-    let html = utility::retrieve_document(url);
-    let course_list = ScrapeUndergraduateCourseGroups::extract_list(&html);
+    let mut course_list = ScrapeUndergraduateCourseGroups::scrape(fetcher, url, mode)?;
+
+    if !subjects.is_empty() {
+        let is_selected = |code: &str| {
+            subjects
+                .iter()
+                .any(|subject| subject.eq_ignore_ascii_case(code))
+        };
+
+        // a typo in a subject code should fail loudly rather than sync nothing
+        if let Some(subject) = subjects.iter().find(|subject| {
+            !course_list
+                .iter()
+                .any(|course| course.code.eq_ignore_ascii_case(subject))
+        }) {
+            return Err(Error::unknown("subject", subject.as_str()));
+        }
+
+        course_list.retain(|course| is_selected(&course.code));
+    }
 
     let mut courses = Vec::<UndergraduateCourse>::new();
+    // subjects whose page couldn't be scraped
+    let mut failed = Vec::<&str>::new();
 
     for course in &(course_list) {
-        // for course in &(course_list[2..=2]) {
-        let html =
-            utility::retrieve_document(&(format!("{}{}", bulletin::BASE_URL, course.raw_link)));
+        let url = format!("{}{}", bulletin::BASE_URL, course.raw_link);
 
         // println!("Extracting {}.", course.code);
-        courses.append(&mut ScrapeUndergraduateCourses::extract_list(&html));
-        // break;
+        let subject_courses = ScrapeUndergraduateCourses::scrape(fetcher, &url, mode);
+        // a single broken subject page only stops a strict run
+        match mode.skip_page(subject_courses, &url, || Some(course.code.clone()))? {
+            Some(mut subject_courses) => courses.append(&mut subject_courses),
+            None => failed.push(&course.code),
+        }
     }
 
+    let scraped = match subjects.is_empty() && failed.is_empty() {
+        true => None,
+        false => Some(
+            course_list
+                .iter()
+                .filter(|course| !failed.contains(&course.code.as_str()))
+                .map(|course| course.code.clone())
+                .collect(),
+        ),
+    };
+
+    Ok((courses, scraped))
+}
+
+/// Scrapes and syncs the courses of `subjects` (e.g. "MATH"), or of every subject if empty
+fn sync_courses(
+    fetcher: &dyn Fetcher,
+    database_url: &str,
+    export: Option<(&Path, ExportFormat)>,
+    subjects: &[String],
+    stale: StalePolicy,
+    mode: &mut ScrapeMode,
+) -> Result<()> {
+    let skipped_before = mode.skipped();
+    let (courses, scraped) = scrape_courses(fetcher, subjects, mode)?;
+
+    // a course that ends up requiring itself can't be taken, usually a parser error
+    let graph = CourseGraph::new(&courses);
+    for cycle in graph.cycles() {
+        if !cycle.is_expected() {
+            eprintln!("Prerequisite cycle: {}", cycle);
+        }
+    }
+
+    // only subjects that were fully scraped can have stale courses
+    let options = SyncOptions {
+        stale: stale_policy(stale, skipped_before, mode),
+        subjects: scraped,
+        graph: Some(&graph),
+        ..Default::default()
+    };
+
     println!("Saving to database...");
-    SyncUndergraduateCourses::sync(database_url, &courses);
+    let summary = SyncUndergraduateCourses::sync(database_url, &courses, &options)?;
+    print_summary("course", &summary, options.stale);
     println!("Saved to database.");
+
+    if let Some((path, export_format)) = export_path(export, "courses")? {
+        let options = SyncOptions {
+            export_format,
+            ..Default::default()
+        };
+        ExportUndergraduateCourses::sync(&path, &courses, &options)?;
+        println!("Exported to {}", path);
+    }
+
+    Ok(())
+}
+
+/// Draws the prerequisites of `subjects`, or of `root` and what it needs
+fn graph(
+    fetcher: &dyn Fetcher,
+    subjects: &[String],
+    root: Option<&str>,
+    depth: Option<usize>,
+    output: &Option<PathBuf>,
+    mode: &mut ScrapeMode,
+) -> Result<()> {
+    let filter = match root {
+        Some(root) => GraphFilter::Prerequisites {
+            root: CourseIdentifier::try_from(root)?,
+            depth,
+        },
+        None if subjects.is_empty() => GraphFilter::All,
+        None => GraphFilter::Subjects(subjects.to_vec()),
+    };
+
+    // a root's prerequisites can be in any subject
+    let (courses, _) = match filter {
+        GraphFilter::Subjects(_) => scrape_courses(fetcher, subjects, mode)?,
+        _ => scrape_courses(fetcher, &[], mode)?,
+    };
+    let graph = dot::prerequisite_graph(&courses, &filter)?;
+
+    match output {
+        Some(path) => std::fs::write(path, graph).map_err(|source| Error::Io {
+            path: path.clone(),
+            source,
+        }),
+        None => {
+            print!("{}", graph);
+            Ok(())
+        }
+    }
+}
+
+/// Lists the courses whose requirements mention `course`
+fn unlocks(database_url: &str, course: &str) -> Result<()> {
+    let course = CourseIdentifier::try_from(course)?;
+    let dependents = dependents::dependents(database_url, &course)?;
+
+    if dependents.is_empty() {
+        println!("No course requires {}.", course);
+    }
+
+    for dependent in dependents {
+        match dependent.mandatory {
+            true => println!("{} ({})", dependent.course, dependent.kind),
+            false => println!(
+                "{} ({}, one of several options)",
+                dependent.course, dependent.kind
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists the courses a student who completed `completed` can take next
+fn eligible(
+    database_url: &str,
+    completed: &[String],
+    standing: u8,
+    filter: CourseFilter,
+) -> Result<()> {
+    let courses = catalog::load_courses(database_url)?;
+    let transcript = Transcript::parse(standing, completed, Crosslists::new(&courses))?;
+    let eligible = evaluation::eligible(&courses, &transcript, &filter)?;
+
+    if eligible.is_empty() {
+        println!("No course can be taken yet.");
+    }
+
+    for course in eligible {
+        let credits = match course.min_credits {
+            Some(min_credits) => format!("{}-{}", min_credits, course.credits),
+            None => course.credits.to_string(),
+        };
+        println!(
+            "{}: {} ({} credits)",
+            course.identifier, course.title, credits
+        );
+    }
+
+    Ok(())
+}
+
+/// Plans the semesters to get to `targets` and the courses of `program`, with only the courses
+/// offered at `campus` if it's set
+fn plan(
+    database_url: &str,
+    targets: &[String],
+    program: Option<&str>,
+    campus: Option<&str>,
+    completed: &[String],
+    standing: u8,
+    max_credits: f32,
+) -> Result<()> {
+    let mut targets = targets
+        .iter()
+        .map(|target| CourseIdentifier::try_from(target.as_str()))
+        .collect::<Result<Vec<CourseIdentifier>>>()?;
+    let courses = catalog::load_courses(database_url)?;
+
+    if let Some(program) = program {
+        // courses the program lists that weren't synced can't be planned for
+        for course in catalog::program_courses(database_url, program)? {
+            match courses.iter().any(|synced| synced.identifier == course) {
+                true if !targets.contains(&course) => targets.push(course),
+                true => (),
+                false => eprintln!("Skipping {}: it isn't synced", course),
+            }
+        }
+    }
+
+    let offered = match campus {
+        Some(campus) => Some(catalog::campus_courses(database_url, campus)?),
+        None => None,
+    };
+
+    let transcript = Transcript::parse(standing, completed, Crosslists::new(&courses))?;
+    let plan = planner::plan(
+        &courses,
+        &targets,
+        transcript,
+        max_credits,
+        offered.as_ref(),
+    )?;
+
+    if plan.semesters.is_empty() && plan.blocked.is_empty() {
+        println!("Every course is already completed.");
+    }
+
+    for (idx, semester) in plan.semesters.iter().enumerate() {
+        println!(
+            "Semester {} (standing {}, {} credits)",
+            idx + 1,
+            semester.standing,
+            semester.credits()
+        );
+        if semester.courses.is_empty() {
+            println!("  nothing, the next courses need a higher standing");
+        }
+        for course in &semester.courses {
+            println!("  {}: {}", course.identifier, course.title);
+        }
+    }
+
+    if !plan.blocked.is_empty() {
+        println!("Can't be scheduled:");
+    }
+    for blocked in &plan.blocked {
+        println!("  {}", blocked);
+    }
+
+    Ok(())
+}
+
+fn clear_cache(cache_dir: &Path) -> Result<()> {
+    match std::fs::remove_dir_all(cache_dir) {
+        Ok(_) => println!("Cleared {}", cache_dir.display()),
+        // nothing cached yet
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+        Err(source) => {
+            return Err(Error::Io {
+                path: cache_dir.to_path_buf(),
+                source,
+            })
+        }
+    }
+
+    Ok(())
 }
 
 //
@@ -0,0 +1,370 @@
+//! Canonical form of requirement trees. The parser keeps the structure of the bulletin's
+//! sentence, e.g. "(MATH 140 OR (MATH 141)) AND [CMPSC 121]" has wrappers that mean nothing;
+//! normalized, it's stored as "CMPSC 121 AND (MATH 140 OR MATH 141)".
+
+use std::{cmp::Ordering, collections::BTreeSet};
+
+use ego_tree::{NodeMut, NodeRef, Tree};
+
+use crate::{
+    bulletin::{CourseRequirementNode, CourseRequirementTree, Crosslists},
+    utility::{minimal_sets, set_product},
+};
+
+/// Owned copy of a subtree, easier to rewrite than the tree itself
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Node {
+    value: CourseRequirementNode,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn from_ref(node: NodeRef<CourseRequirementNode>) -> Self {
+        Self {
+            value: node.value().clone(),
+            children: node.children().map(Node::from_ref).collect(),
+        }
+    }
+
+    fn leaf(value: CourseRequirementNode) -> Self {
+        Self {
+            value,
+            children: Vec::new(),
+        }
+    }
+
+    fn is_group(&self) -> bool {
+        matches!(
+            self.value,
+            CourseRequirementNode::AND
+                | CourseRequirementNode::OR
+                | CourseRequirementNode::AT_LEAST(_)
+        )
+    }
+
+    /// The node with every course replaced by the first of its crosslists, two nodes with the
+    /// same key ask for the same thing
+    fn key(&self, crosslists: &Crosslists) -> Node {
+        Node {
+            value: match &self.value {
+                CourseRequirementNode::COURSE(course, min_grade) => {
+                    CourseRequirementNode::COURSE(crosslists.canonical(course).clone(), *min_grade)
+                }
+                value => value.clone(),
+            },
+            children: self
+                .children
+                .iter()
+                .map(|child| child.key(crosslists))
+                .collect(),
+        }
+    }
+
+    /// Normalizes the subtree, `None` if nothing is left of it
+    fn normalize(self, crosslists: &Crosslists) -> Option<Node> {
+        if !self.is_group() {
+            return match &self.value {
+                CourseRequirementNode::TEXT(text) if text.trim().is_empty() => None,
+                _ => Some(self),
+            };
+        }
+
+        let value = match self.value {
+            CourseRequirementNode::AT_LEAST(0) => return None,
+            CourseRequirementNode::AT_LEAST(1) => CourseRequirementNode::OR,
+            value => value,
+        };
+
+        let mut children = Vec::<Node>::new();
+        for child in self.children {
+            let child = match child.normalize(crosslists) {
+                Some(child) => child,
+                None => continue, // empty groups
+            };
+
+            // AND in AND, OR in OR
+            match child.value == value
+                && matches!(
+                    value,
+                    CourseRequirementNode::AND | CourseRequirementNode::OR
+                ) {
+                true => children.extend(child.children),
+                false => children.push(child),
+            }
+        }
+
+        // leaves first, then groups, both sorted
+        children.sort_by(|a, b| (a.is_group(), a).cmp(&(b.is_group(), b)));
+
+        let mut keys = Vec::<Node>::new();
+        children.retain(|child| {
+            let key = child.key(crosslists);
+            let is_duplicate = keys.contains(&key);
+            keys.push(key);
+            !is_duplicate
+        });
+
+        if let CourseRequirementNode::AT_LEAST(count) = value {
+            match (count as usize).cmp(&children.len()) {
+                // asking for as many as there are is asking for all of them
+                Ordering::Equal => {
+                    return Node {
+                        value: CourseRequirementNode::AND,
+                        children,
+                    }
+                    .normalize(crosslists)
+                }
+                // asking for more than there are (e.g. two of a course and its crosslist) can't
+                // be met, it stays that way instead of asking for fewer
+                Ordering::Greater if !children.is_empty() => return Some(Node { value, children }),
+                _ => (),
+            }
+        }
+
+        match children.len() {
+            0 => None,
+            // a group of one is just that one
+            1 => children.pop(),
+            _ => Some(Node { value, children }),
+        }
+    }
+
+    fn append_to(self, parent: &mut NodeMut<CourseRequirementNode>) {
+        let mut node = parent.append(self.value);
+        for child in self.children {
+            child.append_to(&mut node);
+        }
+    }
+
+    /// Lays the node out as a tree, the root is always a group
+    fn into_tree(node: Option<Node>) -> CourseRequirementTree {
+        let (root, children) = match node {
+            Some(node) if node.is_group() => (node.value, node.children),
+            Some(leaf) => (CourseRequirementNode::AND, vec![leaf]),
+            None => (CourseRequirementNode::AND, vec![]),
+        };
+
+        let mut tree = Tree::new(root);
+        for child in children {
+            child.append_to(&mut tree.root_mut());
+        }
+
+        CourseRequirementTree { tree }
+    }
+}
+
+/// A set of leaves, ANDed in disjunctive normal form, ORed in conjunctive normal form
+type Clause = BTreeSet<CourseRequirementNode>;
+
+/// Every subset of `count` of `nodes`
+fn combinations(count: usize, nodes: &[Node]) -> Vec<Vec<Node>> {
+    if count == 0 {
+        return vec![vec![]];
+    }
+
+    match nodes.split_first() {
+        Some((first, rest)) if rest.len() >= count - 1 => {
+            let mut with_first = combinations(count - 1, rest);
+            for combination in &mut with_first {
+                combination.insert(0, first.clone());
+            }
+
+            with_first.extend(combinations(count, rest));
+            with_first
+        }
+        _ => vec![],
+    }
+}
+
+/// Clauses of `node` in normal form. `outer` is the operator between clauses (OR for disjunctive
+/// normal form), the other one is the operator inside them.
+fn clauses(node: &Node, outer: &CourseRequirementNode) -> Vec<Clause> {
+    let children = || node.children.iter().map(|child| clauses(child, outer));
+
+    let value = match &node.value {
+        // AT LEAST N OF M is either of the ways to pick N (DNF), or each of the ways to pick
+        // M - N + 1 having at least one (CNF)
+        // more than there are can't be picked either way, the group is kept whole as text
+        CourseRequirementNode::AT_LEAST(count) if *count as usize > node.children.len() => {
+            let text = Node::into_tree(Some(node.clone())).to_string();
+            return vec![Clause::from([CourseRequirementNode::TEXT(text)])];
+        }
+        CourseRequirementNode::AT_LEAST(count) => {
+            let count = *count as usize;
+            let (value, size) = match outer {
+                CourseRequirementNode::OR => (CourseRequirementNode::AND, count),
+                _ => (
+                    CourseRequirementNode::OR,
+                    (node.children.len() + 1).saturating_sub(count),
+                ),
+            };
+
+            let groups = combinations(size, &node.children)
+                .into_iter()
+                .map(|children| Node {
+                    value: value.clone(),
+                    children,
+                })
+                .collect();
+
+            return clauses(
+                &Node {
+                    value: outer.clone(),
+                    children: groups,
+                },
+                outer,
+            );
+        }
+        value => value,
+    };
+
+    match value {
+        // clauses of the children side by side
+        value if value == outer => minimal_sets(children().flatten().collect()),
+        // one clause from each child, combined
+        CourseRequirementNode::AND | CourseRequirementNode::OR => children()
+            .fold(vec![Clause::new()], |clauses, child| {
+                set_product(&clauses, &child)
+            }),
+        leaf => vec![Clause::from([leaf.clone()])],
+    }
+}
+
+impl CourseRequirementTree {
+    /// Removes empty groups and groups of one, merges AND in AND and OR in OR, drops duplicates
+    /// (crosslisted courses count as the same course) and sorts what's left, so the same
+    /// requirement always ends up as the same tree.
+    pub fn normalize(&mut self, crosslists: &Crosslists) {
+        *self = Node::into_tree(Node::from_ref(self.tree.root()).normalize(crosslists));
+    }
+
+    /// Disjunctive normal form: an OR of ANDs of leaves
+    #[allow(dead_code)] // library API, nothing in the CLI needs it
+    pub fn to_dnf(&self, crosslists: &Crosslists) -> CourseRequirementTree {
+        self.to_normal_form(CourseRequirementNode::OR, crosslists)
+    }
+
+    /// Conjunctive normal form: an AND of ORs of leaves
+    #[allow(dead_code)] // library API, nothing in the CLI needs it
+    pub fn to_cnf(&self, crosslists: &Crosslists) -> CourseRequirementTree {
+        self.to_normal_form(CourseRequirementNode::AND, crosslists)
+    }
+
+    fn to_normal_form(
+        &self,
+        outer: CourseRequirementNode,
+        crosslists: &Crosslists,
+    ) -> CourseRequirementTree {
+        let node = match Node::from_ref(self.tree.root()).normalize(crosslists) {
+            Some(node) => node,
+            None => return Node::into_tree(None),
+        };
+
+        let inner = match outer {
+            CourseRequirementNode::OR => CourseRequirementNode::AND,
+            _ => CourseRequirementNode::OR,
+        };
+
+        let clauses = clauses(&node, &outer)
+            .into_iter()
+            .map(|clause| Node {
+                value: inner.clone(),
+                children: clause.into_iter().map(Node::leaf).collect(),
+            })
+            .collect();
+
+        let mut tree = Node::into_tree(Some(Node {
+            value: outer,
+            children: clauses,
+        }));
+        tree.normalize(crosslists);
+        tree
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bulletin::{CourseIdentifier, UndergraduateCourse};
+
+    #[test]
+    fn normal_forms() {
+        let crosslists = Crosslists::default();
+
+        // requirement, normalized, disjunctive normal form, conjunctive normal form
+        for (requirement, normalized, dnf, cnf) in [
+            (
+                "(MATH 140 OR (MATH 141)) AND [CMPSC 121]",
+                "CMPSC 121 AND (MATH 140 OR MATH 141)",
+                "[CMPSC 121 AND MATH 140] OR [CMPSC 121 AND MATH 141]",
+                "CMPSC 121 AND (MATH 140 OR MATH 141)",
+            ),
+            (
+                "(MATH 140 or MATH 141) and (PHYS 211 or PHYS 212)",
+                "(MATH 140 OR MATH 141) AND (PHYS 211 OR PHYS 212)",
+                "[MATH 140 AND PHYS 211] OR [MATH 140 AND PHYS 212] OR [MATH 141 AND PHYS 211] OR [MATH 141 AND PHYS 212]",
+                "(MATH 140 OR MATH 141) AND (PHYS 211 OR PHYS 212)",
+            ),
+            (
+                "MATH 140 or MATH 141 and PHYS 211",
+                "MATH 140 OR [MATH 141 AND PHYS 211]",
+                "MATH 140 OR [MATH 141 AND PHYS 211]",
+                "(MATH 140 OR MATH 141) AND (MATH 140 OR PHYS 211)",
+            ),
+            (
+                "at least 2 of the following: MATH 140, MATH 141, PHYS 211",
+                "AT LEAST 2 OF (MATH 140 OR MATH 141 OR PHYS 211)",
+                "[MATH 140 AND MATH 141] OR [MATH 140 AND PHYS 211] OR [MATH 141 AND PHYS 211]",
+                "(MATH 140 OR MATH 141) AND (MATH 140 OR PHYS 211) AND (MATH 141 OR PHYS 211)",
+            ),
+            // absorbed: MATH 140 alone is enough
+            (
+                "MATH 140 and MATH 140 or MATH 140 and PHYS 211",
+                "MATH 140 OR [MATH 140 AND PHYS 211]",
+                "MATH 140",
+                "MATH 140",
+            ),
+            (
+                "at least 1 of the following: MATH 140, MATH 141",
+                "MATH 140 OR MATH 141",
+                "MATH 140 OR MATH 141",
+                "MATH 140 OR MATH 141",
+            ),
+            (
+                "at least 2 of the following: MATH 140, MATH 141",
+                "MATH 140 AND MATH 141",
+                "MATH 140 AND MATH 141",
+                "MATH 140 AND MATH 141",
+            ),
+            // can't be met, and it doesn't get any easier
+            (
+                "at least 3 of the following: MATH 140, MATH 141",
+                "AT LEAST 3 OF (MATH 140 OR MATH 141)",
+                "AT LEAST 3 OF (MATH 140 OR MATH 141)",
+                "AT LEAST 3 OF (MATH 140 OR MATH 141)",
+            ),
+        ] {
+            let tree = CourseRequirementTree::try_from(requirement).unwrap();
+            let mut normalized_tree = tree.clone();
+            normalized_tree.normalize(&crosslists);
+
+            assert_eq!(normalized, normalized_tree.to_string(), "{requirement}");
+            assert_eq!(dnf, tree.to_dnf(&crosslists).to_string(), "{requirement}");
+            assert_eq!(cnf, tree.to_cnf(&crosslists).to_string(), "{requirement}");
+        }
+    }
+
+    #[test]
+    fn crosslists_dont_lower_the_count() {
+        let mut math = UndergraduateCourse::with_prerequisites("MATH 484", None);
+        math.crosslist = Some(vec![CourseIdentifier::try_from("CMPSC 484").unwrap()]);
+        let crosslists = Crosslists::new(&[math]);
+
+        let mut tree =
+            CourseRequirementTree::try_from("at least 2 of the following: MATH 484, CMPSC 484")
+                .unwrap();
+        tree.normalize(&crosslists);
+
+        assert_eq!("AT LEAST 2 OF (CMPSC 484)", tree.to_string());
+    }
+}
@@ -0,0 +1,558 @@
+//! Semester-by-semester plans to get to a set of courses, e.g. everything a student still needs
+//! before CMPSC 465 with at most 15 credits a semester.
+//!
+//! The courses to take are picked first: the targets, then the smallest option of every
+//! requirement tree down from them, reusing courses already picked where it can. They're then
+//! placed one semester at a time, the longest chains of prerequisites first, each course with
+//! the corequisites and concurrent requirements it needs that semester. Courses that can't be
+//! placed are reported with what they're missing, or because they aren't offered at the
+//! student's campus.
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt,
+};
+
+use crate::{
+    bulletin::{CourseIdentifier, UndergraduateCourse},
+    error::{Error, Result},
+    evaluation::{Missing, Transcript},
+    graph::{CourseGraph, EdgeKind},
+};
+
+/// The courses taken in one semester
+pub struct Semester<'a> {
+    /// Semester standing of the student during it
+    pub standing: u8,
+    /// Sorted by identifier
+    pub courses: Vec<&'a UndergraduateCourse>,
+}
+
+impl Semester<'_> {
+    /// Credits of every course, at the top of their range
+    pub fn credits(&self) -> f32 {
+        self.courses
+            .iter()
+            .fold(0.0, |credits, course| credits + course.credits)
+    }
+}
+
+/// Why a course couldn't be placed in any semester
+#[derive(Debug)]
+pub enum BlockReason {
+    /// The course alone is worth more than the credit cap
+    Credits(f32),
+    /// The course with the corequisites and concurrent courses it has to be taken with (the
+    /// course first) is worth more than the credit cap
+    GroupCredits(Vec<CourseIdentifier>, f32),
+    /// The course isn't offered at the campus the plan is for
+    NotOffered,
+    /// Every minimal set of requirements that would have let the course in, smallest first
+    Requirements(Vec<BTreeSet<Missing>>),
+}
+
+#[derive(Debug)]
+pub struct Blocked {
+    pub course: CourseIdentifier,
+    pub reason: BlockReason,
+}
+
+impl fmt::Display for Blocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.reason {
+            BlockReason::Credits(credits) => write!(
+                f,
+                "{} is worth {} credits, more than a semester allows",
+                self.course, credits
+            ),
+            BlockReason::GroupCredits(group, credits) => {
+                let others: Vec<String> =
+                    group[1..].iter().map(|course| course.to_string()).collect();
+                write!(
+                    f,
+                    "{} has to be taken with {}, worth {} credits together, more than a semester allows",
+                    self.course,
+                    others.join(" AND "),
+                    credits
+                )
+            }
+            BlockReason::NotOffered => write!(f, "{} isn't offered at the campus", self.course),
+            BlockReason::Requirements(options) => {
+                let several = options.len() > 1;
+                let options: Vec<String> = options
+                    .iter()
+                    .map(|option| {
+                        let items: Vec<String> =
+                            option.iter().map(|item| item.to_string()).collect();
+                        match several && items.len() > 1 {
+                            true => format!("[{}]", items.join(" AND ")),
+                            false => items.join(" AND "),
+                        }
+                    })
+                    .collect();
+
+                write!(f, "{} needs {}", self.course, options.join(" OR "))
+            }
+        }
+    }
+}
+
+pub struct Plan<'a> {
+    pub semesters: Vec<Semester<'a>>,
+    /// Courses that couldn't be placed, sorted. The plan is complete if it's empty.
+    pub blocked: Vec<Blocked>,
+}
+
+/// The first option made of courses only, the ones a plan can do something about
+fn course_option(options: &[BTreeSet<Missing>]) -> Option<Vec<&CourseIdentifier>> {
+    options
+        .iter()
+        .find(|option| {
+            option
+                .iter()
+                .all(|item| matches!(item, Missing::Course(..)))
+        })
+        .map(|option| {
+            option
+                .iter()
+                .filter_map(|item| match item {
+                    Missing::Course(course, _) => Some(course),
+                    _ => None,
+                })
+                .collect()
+        })
+}
+
+/// Every course that has to be taken to get to `targets`, the targets included
+fn needed_courses(
+    catalog: &HashMap<&CourseIdentifier, &UndergraduateCourse>,
+    targets: Vec<CourseIdentifier>,
+    transcript: &Transcript,
+) -> BTreeSet<CourseIdentifier> {
+    let mut needed: BTreeSet<CourseIdentifier> = targets.iter().cloned().collect();
+    let mut queue = targets;
+
+    while let Some(identifier) = queue.pop() {
+        let course = match catalog.get(&identifier) {
+            Some(course) => course,
+            None => continue,
+        };
+
+        // every other course picked so far counts as passed, standing comes with time
+        let mut assumed = transcript.clone();
+        assumed.standing = u8::MAX;
+        for other in needed.iter().filter(|other| **other != identifier) {
+            assumed.complete(other.clone(), None);
+        }
+
+        let evaluation = course.evaluate(&assumed);
+        // nothing to pick if it needs more than courses, it's reported when it can't be placed
+        for required in course_option(&evaluation.missing).into_iter().flatten() {
+            if needed.insert(required.clone()) {
+                queue.push(required.clone());
+            }
+        }
+    }
+
+    needed
+}
+
+/// How many semesters of `needed` courses have to come after each of them at the very least,
+/// by crosslist canonical identifier
+fn heights(
+    courses: &[UndergraduateCourse],
+    needed: &BTreeSet<CourseIdentifier>,
+    transcript: &Transcript,
+) -> HashMap<CourseIdentifier, usize> {
+    let needed: BTreeSet<&CourseIdentifier> = needed
+        .iter()
+        .map(|course| transcript.crosslists.canonical(course))
+        .collect();
+    let edges: Vec<_> = CourseGraph::new(courses)
+        .edges()
+        .filter(|edge| needed.contains(&edge.course) && needed.contains(&edge.required))
+        .collect();
+
+    let mut heights: HashMap<CourseIdentifier, usize> =
+        needed.iter().map(|course| ((*course).clone(), 0)).collect();
+
+    // longest paths, a cycle stops growing once every course of it went round once
+    for _ in 0..needed.len() {
+        let mut changed = false;
+
+        for edge in &edges {
+            let height = heights[&edge.course]
+                + match edge.kind {
+                    EdgeKind::Prerequisite => 1,
+                    EdgeKind::Concurrent | EdgeKind::Corequisite => 0,
+                };
+
+            if height > heights[&edge.required] {
+                heights.insert(edge.required.clone(), height);
+                changed = true;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    heights
+}
+
+/// `course` along with the courses it has to be taken with this semester, `None` if they can't
+/// all be taken. `ready` are the courses whose prerequisites are met, the transcript has this
+/// semester's courses enrolled.
+fn group<'a>(
+    course: &'a UndergraduateCourse,
+    ready: &[&'a UndergraduateCourse],
+    transcript: &Transcript,
+) -> Option<Vec<&'a UndergraduateCourse>> {
+    let mut transcript = transcript.clone();
+    let mut group = vec![course];
+    transcript.enroll(course.identifier.clone());
+
+    loop {
+        let evaluation = match group
+            .iter()
+            .map(|member| member.evaluate(&transcript))
+            .find(|evaluation| !evaluation.satisfied)
+        {
+            Some(evaluation) => evaluation,
+            None => return Some(group),
+        };
+
+        for required in course_option(&evaluation.missing)? {
+            let required = transcript.crosslists.canonical(required);
+            let course = ready.iter().find(|course| {
+                transcript.crosslists.canonical(&course.identifier) == required
+                    && !transcript.is_taking(&course.identifier)
+            })?;
+
+            group.push(course);
+            transcript.enroll(course.identifier.clone());
+        }
+    }
+}
+
+/// Plans the semesters it takes a student with `transcript` to pass every course of
+/// `targets`, with at most `max_credits` a semester. Courses of a credit range count for the
+/// top of it. With `offered`, only those courses (or a course crosslisted with them) can be
+/// taken.
+pub fn plan<'a>(
+    courses: &'a [UndergraduateCourse],
+    targets: &[CourseIdentifier],
+    mut transcript: Transcript,
+    max_credits: f32,
+    offered: Option<&HashSet<CourseIdentifier>>,
+) -> Result<Plan<'a>> {
+    let catalog: HashMap<&CourseIdentifier, &UndergraduateCourse> = courses
+        .iter()
+        .map(|course| (&course.identifier, course))
+        .collect();
+
+    for target in targets {
+        if !catalog.contains_key(target) {
+            return Err(Error::unknown("course", target.to_string()));
+        }
+    }
+
+    let targets: Vec<CourseIdentifier> = targets
+        .iter()
+        .filter(|target| !transcript.has_passed(target, None))
+        .cloned()
+        .collect();
+    let needed = needed_courses(&catalog, targets, &transcript);
+    let is_offered = |course: &UndergraduateCourse| match offered {
+        Some(offered) => transcript
+            .crosslists
+            .equivalents(&course.identifier)
+            .any(|course| offered.contains(course)),
+        None => true,
+    };
+    let heights = heights(courses, &needed, &transcript);
+
+    let (mut remaining, not_offered): (Vec<&UndergraduateCourse>, Vec<&UndergraduateCourse>) =
+        needed
+            .iter()
+            .filter_map(|course| catalog.get(course).copied())
+            .partition(|course| is_offered(course));
+    // longest chains first, they decide how many semesters it takes
+    remaining.sort_by_key(|course| {
+        let height = heights
+            .get(transcript.crosslists.canonical(&course.identifier))
+            .copied()
+            .unwrap_or_default();
+        (std::cmp::Reverse(height), &course.identifier)
+    });
+
+    let mut semesters = Vec::<Semester>::new();
+
+    while !remaining.is_empty() {
+        transcript.concurrent.clear();
+
+        let ready: Vec<&UndergraduateCourse> = remaining
+            .iter()
+            .copied()
+            .filter(|course| course.credits <= max_credits)
+            .filter(|course| course.evaluate_prerequisites(&transcript).satisfied)
+            .collect();
+
+        let mut taken = Vec::<&UndergraduateCourse>::new();
+        for course in &ready {
+            if transcript.is_taking(&course.identifier) {
+                continue;
+            }
+
+            let group = match group(course, &ready, &transcript) {
+                Some(group) => group,
+                None => continue,
+            };
+            let credits = taken
+                .iter()
+                .chain(&group)
+                .fold(0.0, |credits, course| credits + course.credits);
+            if credits > max_credits {
+                continue;
+            }
+
+            for course in group {
+                transcript.enroll(course.identifier.clone());
+                taken.push(course);
+            }
+        }
+
+        if taken.is_empty() {
+            // a later semester is only worth it if some course is waiting on a standing
+            let standing = transcript.standing;
+            transcript.standing = u8::MAX;
+            let waiting = standing < u8::MAX
+                && remaining.iter().any(|course| {
+                    course.credits <= max_credits
+                        && !ready.iter().any(|r| r.identifier == course.identifier)
+                        && course.evaluate_prerequisites(&transcript).satisfied
+                });
+            transcript.standing = standing;
+
+            if !waiting {
+                break;
+            }
+        }
+
+        for course in &taken {
+            transcript.complete(course.identifier.clone(), None);
+        }
+        remaining.retain(|course| !transcript.is_taking(&course.identifier));
+
+        taken.sort_by(|a, b| a.identifier.cmp(&b.identifier));
+        semesters.push(Semester {
+            standing: transcript.standing,
+            courses: taken,
+        });
+        transcript.standing = transcript.standing.saturating_add(1);
+    }
+
+    transcript.concurrent.clear();
+    let ready: Vec<&UndergraduateCourse> = remaining
+        .iter()
+        .copied()
+        .filter(|course| course.evaluate_prerequisites(&transcript).satisfied)
+        .collect();
+    let mut blocked: Vec<Blocked> = remaining
+        .iter()
+        .map(|course| Blocked {
+            course: course.identifier.clone(),
+            reason: match course.credits > max_credits {
+                true => BlockReason::Credits(course.credits),
+                // ready, but not along with the courses it has to be taken with
+                false => match ready
+                    .iter()
+                    .any(|ready| ready.identifier == course.identifier)
+                    .then(|| group(course, &ready, &transcript))
+                    .flatten()
+                {
+                    Some(group) => BlockReason::GroupCredits(
+                        group
+                            .iter()
+                            .map(|course| course.identifier.clone())
+                            .collect(),
+                        group
+                            .iter()
+                            .fold(0.0, |credits, course| credits + course.credits),
+                    ),
+                    None => BlockReason::Requirements(course.evaluate(&transcript).missing),
+                },
+            },
+        })
+        .chain(not_offered.into_iter().map(|course| Blocked {
+            course: course.identifier.clone(),
+            reason: BlockReason::NotOffered,
+        }))
+        .collect();
+    blocked.sort_by(|a, b| a.course.cmp(&b.course));
+
+    Ok(Plan { semesters, blocked })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bulletin::CourseRequirementTree;
+
+    fn identifier(identifier: &str) -> CourseIdentifier {
+        CourseIdentifier::try_from(identifier).unwrap()
+    }
+
+    fn course(
+        identifier: &str,
+        credits: f32,
+        prerequisites: Option<&str>,
+        corequisites: Option<&str>,
+    ) -> UndergraduateCourse {
+        let mut course = UndergraduateCourse::with_prerequisites(identifier, prerequisites);
+        course.credits = credits;
+        course.requirements.corequisites =
+            corequisites.map(|requirement| CourseRequirementTree::try_from(requirement).unwrap());
+        course
+    }
+
+    /// The courses of every semester, then every blocked course with its reason
+    fn planned(
+        courses: &[UndergraduateCourse],
+        targets: &[&str],
+        standing: u8,
+        max_credits: f32,
+        offered: Option<&HashSet<CourseIdentifier>>,
+    ) -> (Vec<Vec<String>>, Vec<String>) {
+        let targets: Vec<CourseIdentifier> =
+            targets.iter().map(|target| identifier(target)).collect();
+        let plan = plan(
+            courses,
+            &targets,
+            Transcript::new(standing),
+            max_credits,
+            offered,
+        )
+        .unwrap();
+
+        (
+            plan.semesters
+                .iter()
+                .map(|semester| {
+                    semester
+                        .courses
+                        .iter()
+                        .map(|course| course.identifier.to_string())
+                        .collect()
+                })
+                .collect(),
+            plan.blocked
+                .iter()
+                .map(|blocked| blocked.to_string())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn semesters_stay_under_the_credit_cap() {
+        let courses = [
+            course("MATH 140", 4.0, None, None),
+            course("MATH 141", 4.0, Some("MATH 140"), None),
+            course("CMPSC 121", 3.0, None, None),
+            course("CMPSC 122", 3.0, Some("CMPSC 121"), None),
+            course("MATH 230", 4.0, Some("MATH 141"), None),
+        ];
+
+        // the longest chain goes first
+        assert_eq!(
+            (
+                vec![
+                    vec!["CMPSC 121".to_string(), "MATH 140".into()],
+                    vec!["CMPSC 122".into(), "MATH 141".into()],
+                    vec!["MATH 230".into()],
+                ],
+                vec![]
+            ),
+            planned(&courses, &["MATH 230", "CMPSC 122"], 1, 7.0, None)
+        );
+
+        assert_eq!(
+            (
+                vec![],
+                vec!["MATH 140 is worth 4 credits, more than a semester allows".to_string()]
+            ),
+            planned(&courses, &["MATH 140"], 1, 3.0, None)
+        );
+    }
+
+    #[test]
+    fn corequisites_are_taken_together() {
+        let courses = [
+            course("MATH 140", 4.0, None, None),
+            course("PHYS 211", 4.0, None, Some("MATH 140")),
+            course("PHYS 212", 4.0, Some("PHYS 211"), Some("PHYS 213")),
+            course("PHYS 213", 1.0, None, Some("PHYS 212")),
+        ];
+
+        assert_eq!(
+            (
+                vec![
+                    vec!["MATH 140".to_string(), "PHYS 211".into()],
+                    vec!["PHYS 212".into(), "PHYS 213".into()],
+                ],
+                vec![]
+            ),
+            planned(&courses, &["PHYS 212"], 1, 8.0, None)
+        );
+
+        // neither can go without the other
+        assert_eq!(
+            (
+                vec![vec!["MATH 140".to_string()], vec!["PHYS 211".into()]],
+                vec![
+                    "PHYS 212 has to be taken with PHYS 213, worth 5 credits together, more than a semester allows".to_string(),
+                    "PHYS 213 has to be taken with PHYS 212, worth 5 credits together, more than a semester allows".into(),
+                ]
+            ),
+            planned(&courses, &["PHYS 212"], 1, 4.0, None)
+        );
+    }
+
+    #[test]
+    fn semesters_wait_for_a_standing() {
+        let courses = [course("MATH 497", 3.0, Some("5th semester standing"), None)];
+
+        assert_eq!(
+            (vec![vec![], vec![], vec!["MATH 497".to_string()]], vec![]),
+            planned(&courses, &["MATH 497"], 3, 18.0, None)
+        );
+    }
+
+    #[test]
+    fn blocked_courses_say_why() {
+        let courses = [
+            course("MATH 140", 4.0, None, None),
+            course("MATH 141", 4.0, Some("MATH 140"), None),
+            course("MATH 496", 3.0, Some("MATH 141"), None),
+            course("MATH 497", 3.0, Some("permission of the instructor"), None),
+        ];
+        let offered = HashSet::from([
+            identifier("MATH 140"),
+            identifier("MATH 496"),
+            identifier("MATH 497"),
+        ]);
+
+        assert_eq!(
+            (
+                vec![vec!["MATH 140".to_string()]],
+                vec![
+                    "MATH 141 isn't offered at the campus".to_string(),
+                    "MATH 496 needs MATH 141".into(),
+                    "MATH 497 needs PERMISSION OF INSTRUCTOR".into(),
+                ]
+            ),
+            planned(&courses, &["MATH 496", "MATH 497"], 1, 18.0, Some(&offered))
+        );
+    }
+}
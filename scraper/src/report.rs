@@ -0,0 +1,89 @@
+use std::{fmt::Write, fs::File, path::Path};
+
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+
+/// A single record (course block, program card, ...) that was skipped in lenient mode
+#[derive(Debug, Serialize)]
+pub struct ScrapeFailure {
+    pub url: Option<String>,
+    pub identifier: Option<String>, // course identifier or program link, if it could be found
+    pub failed_step: String,        // selector or conversion that failed
+    pub error: String,
+    pub html: String, // raw HTML of the record
+}
+
+/// Collects every record skipped during a lenient run
+#[derive(Debug, Default, Serialize)]
+pub struct ScrapeReport {
+    pub failures: Vec<ScrapeFailure>,
+    #[serde(skip)]
+    current_url: Option<String>,
+}
+
+impl ScrapeReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Page that subsequent failures are attributed to
+    pub fn set_url(&mut self, url: &str) {
+        self.current_url = Some(url.into());
+    }
+
+    pub fn record(&mut self, identifier: Option<String>, error: &Error, html: String) {
+        self.failures.push(ScrapeFailure {
+            url: self.current_url.clone(),
+            identifier,
+            failed_step: error.failed_step(),
+            error: error.to_string(),
+            html,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut buffer = String::new();
+
+        writeln!(buffer, "# Scrape report\n").unwrap();
+        writeln!(buffer, "{} record(s) skipped.", self.failures.len()).unwrap();
+
+        for failure in &self.failures {
+            writeln!(
+                buffer,
+                "\n## {}\n",
+                failure.identifier.as_deref().unwrap_or("Unknown record")
+            )
+            .unwrap();
+
+            if let Some(ref url) = failure.url {
+                writeln!(buffer, "- **URL:** {}", url).unwrap();
+            }
+            writeln!(buffer, "- **Failed at:** {}", failure.failed_step).unwrap();
+            writeln!(buffer, "- **Error:** {}", failure.error).unwrap();
+            writeln!(buffer, "\n```html\n{}\n```", failure.html.trim()).unwrap();
+        }
+
+        buffer
+    }
+
+    /// Writes the report as Markdown if `path` ends with `.md`, otherwise as JSON
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let io_error = |source: std::io::Error| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("md") => std::fs::write(path, self.to_markdown()).map_err(io_error),
+            _ => {
+                let file = File::create(path).map_err(io_error)?;
+                serde_json::to_writer_pretty(file, self).map_err(|e| io_error(e.into()))
+            }
+        }
+    }
+}
@@ -0,0 +1,859 @@
+//! Lexer and grammar for requirement sentences, e.g.
+//! "Enforced Prerequisite at Enrollment: MATH 140 or MATH 141; Concurrent Courses: PHYS 211".
+//!
+//! Works on plain text only: the scraper hands over the text of a requirement paragraph and
+//! gets back one tree per section.
+//!
+//! ```text
+//! sentence    := unknown* (SECTION expression)*
+//! expression  := MIN_GRADE expression | term ("OR" term)*
+//! term        := MIN_GRADE? factor ("AND"? MIN_GRADE? factor)*   -- side by side is AND
+//! factor      := COURSE | CLAUSE | UNKNOWN | AT_LEAST list
+//!              | "(" expression ")" | "[" expression "]"
+//! list        := "(" (factor ("AND" | "OR")?)* ")" | (factor ("AND" | "OR")?)*
+//! ```
+//!
+//! Clauses are phrases that aren't courses, e.g. "FIFTH SEMESTER STANDING" or "PERMISSION OF
+//! INSTRUCTOR". Phrases that aren't recognized either end up in the tree as free text.
+//!
+//! AND binds tighter than OR, like in boolean algebra: `A OR B AND C` is `A OR [B AND C]`.
+//! The bulletin uses parentheses whenever it means otherwise, e.g. `(A OR B) AND C`.
+//!
+//! A minimum grade ("C OR BETTER IN") at the start of a group applies to every course in it. Anywhere
+//! else, it applies to every course after it in its AND operand: `A AND C OR BETTER IN (B OR D) AND
+//! E OR F` needs a C in B or D, and in E, but not in A or F.
+//!
+//! "AT LEAST TWO OF THE FOLLOWING: A, B, OR C" lists its options: AND/OR between them mean
+//! nothing, since the bulletin writes lists with commas. Without parentheses, the list runs to the
+//! end of its group. A minimum grade in a list applies to each option after it.
+//!
+//! Empty operands (dangling AND/OR, empty groups) are skipped and a group left
+//! open at the end is closed implicitly. Unmatched closing parentheses are an error.
+
+use ego_tree::{NodeMut, Tree};
+
+use crate::{
+    bulletin::{
+        CourseIdentifier, CourseRequirementNode, CourseRequirementTree, Grade,
+        UndergraduateCourseRequirements,
+    },
+    error::Error,
+    utility::ReplaceMany,
+};
+
+/// Label that starts a requirement section
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Prerequisite,
+    Concurrent,
+    Corequisite,
+    Recommended,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Course(CourseIdentifier),
+    And,
+    Or,
+    Open,  // ( or [
+    Close, // ) or ]
+    Section(Section),
+    /// "C OR BETTER IN"
+    MinGrade(Grade),
+    /// "AT LEAST TWO OF THE FOLLOWING"
+    AtLeast(u8),
+    /// A recognized non-course phrase, e.g. "FIFTH SEMESTER STANDING"
+    Clause(CourseRequirementNode),
+    /// Consecutive words that aren't any of the above, e.g. "PRIOR EXPOSURE TO R"
+    Unknown(String),
+}
+
+/// Phrases that carry no meaning for the tree (yet...)
+const IGNORED_PHRASES: &[&[&str]] = &[
+    &["A", "GRADE", "OF"], // left over from "A GRADE OF C OR BETTER IN"
+    &["GRADE", "OF"],
+    &["PRIOR", "TO"],
+    &["ENFORCED"],
+    &["AT", "ENROLLMENT"],
+    &["PREPARATION"],
+    &["S"], // left over from "COURSE(S)"
+];
+
+/// Words the bulletin writes in capitals that are never a subject code, e.g. "OF" in
+/// "A MINIMUM OF 6 CREDITS"
+const NOT_SUBJECTS: &[&str] = &[
+    "A", "AN", "AND", "AT", "FOR", "IN", "OF", "OR", "THE", "TO", "WITH",
+];
+
+const ORDINALS: &[&str] = &[
+    "FIRST", "SECOND", "THIRD", "FOURTH", "FIFTH", "SIXTH", "SEVENTH", "EIGHTH", "NINTH", "TENTH",
+];
+
+/// Uppercases the sentence and evens out the spelling the bulletin isn't consistent about
+pub fn normalize(sentence: &str) -> String {
+    let sentence = sentence.to_ascii_uppercase().replace_many(&[
+        ("\u{a0}", " "),
+        // even-though courses are not supposed to have these
+        // https://cim.psu.edu/user-guides/course-management/prerequisites-concurrents-corequisites/
+        // some course descriptions use ; for OR (below) and , for AND (probably courses that haven't been updated in a while)
+        (",", " AND "),
+        ("CONCURRENT COURSES", "CONCURRENT"),
+        ("RECOMMENDED PREPARATIONS", "RECOMMENDED PREPARATION"), // consistency purposes
+        // spelling mistakes
+        ("PRERQUISITE", "PREREQUISITE"),
+        ("PREREQUISTE", "PREREQUISITE"),
+        ("PREQUISITE", "PREREQUISITE"),
+        ("PREREQ ", "PREREQUISITE "),
+        ("-SEMESTER", " SEMESTER"),
+    ]);
+
+    // a ; right before a section label only ends the previous section, it isn't an OR that
+    // would make the sections alternatives
+    let mut parts = sentence.split(';');
+    let mut normalized = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        let label = part
+            .split_whitespace()
+            .map(|word| word.trim_end_matches(':'))
+            .find(|word| *word != "ENFORCED");
+
+        normalized.push_str(match label.and_then(section) {
+            Some(_) => " ",
+            None => " OR ",
+        });
+        normalized.push_str(part);
+    }
+
+    normalized
+}
+
+fn section(word: &str) -> Option<Section> {
+    match word {
+        "PREREQUISITE" | "PREREQUISITES" => Some(Section::Prerequisite),
+        "CONCURRENT" => Some(Section::Concurrent),
+        "COREQUISITE" | "COREQUISITES" => Some(Section::Corequisite),
+        "RECOMMENDED" => Some(Section::Recommended),
+        _ => None,
+    }
+}
+
+const NUMBERS: &[&str] = &[
+    "ONE", "TWO", "THREE", "FOUR", "FIVE", "SIX", "SEVEN", "EIGHT", "NINE", "TEN",
+];
+
+/// "AT LEAST TWO OF THE FOLLOWING COURSES" at the start of `words` -> (2, number of words)
+fn at_least(words: &[&str]) -> Option<(u8, usize)> {
+    let mut i = match words {
+        ["AT", "LEAST", ..] => 2,
+        ["SELECT", ..] | ["ANY", ..] => 1,
+        _ => 0,
+    };
+
+    let count = match NUMBERS
+        .iter()
+        .position(|number| Some(number) == words.get(i))
+    {
+        Some(position) => position as u8 + 1,
+        None => words.get(i)?.parse().ok()?,
+    };
+    i += 1;
+
+    for optional in [
+        &["COURSES"][..],
+        &["COURSE"],
+        &["OF"],
+        &["THE", "FOLLOWING"],
+        &["COURSES"],
+    ] {
+        // "OF" is the one word that has to be there, e.g. "TWO COURSES" alone isn't a list
+        match words[i..].starts_with(optional) {
+            true => i += optional.len(),
+            false if optional == ["OF"] => return None,
+            false => (),
+        }
+    }
+
+    Some((count, i))
+}
+
+/// "FIFTH" or "5TH" -> 5
+fn ordinal(word: &str) -> Option<u8> {
+    if let Some(position) = ORDINALS.iter().position(|ordinal| *ordinal == word) {
+        return Some(position as u8 + 1);
+    }
+
+    let number = word
+        .strip_suffix("ST")
+        .or_else(|| word.strip_suffix("ND"))
+        .or_else(|| word.strip_suffix("RD"))
+        .or_else(|| word.strip_suffix("TH"))?;
+    number.parse().ok()
+}
+
+/// Recognizes the non-course phrases the bulletin uses, e.g. "PERMISSION OF THE INSTRUCTOR"
+fn clause(phrase: &str) -> Option<CourseRequirementNode> {
+    if let Some(semester) = phrase.strip_suffix(" SEMESTER STANDING") {
+        return ordinal(semester).map(CourseRequirementNode::STANDING);
+    }
+
+    if let Some(whom) = phrase.strip_prefix("PERMISSION OF ") {
+        let whom = whom.strip_prefix("THE ").unwrap_or(whom);
+        return Some(CourseRequirementNode::PERMISSION(whom.into()));
+    }
+
+    if let Some(whom) = phrase.strip_suffix(" PERMISSION") {
+        return Some(CourseRequirementNode::PERMISSION(whom.into()));
+    }
+
+    if let Some(program) = phrase.strip_prefix("ENROLLMENT IN ") {
+        let program = program.strip_prefix("THE ").unwrap_or(program);
+        return Some(CourseRequirementNode::ENROLLMENT(program.into()));
+    }
+
+    None
+}
+
+/// Splits a sentence into tokens
+///
+/// # Examples
+///
+/// ```
+/// let tokens = tokenize("Prerequisite: C or better in MATH 140 or (MATH 141; 5th semester standing)");
+///
+/// // [Section(Prerequisite), MinGrade(C), Course(MATH 140), Or, Open, Course(MATH 141), Or,
+/// //  Clause(STANDING(5)), Close]
+/// ```
+pub fn tokenize(sentence: &str) -> Vec<Token> {
+    // subject codes are written in capitals, unlike the prose around them ("completion of 60
+    // credits"), which the uppercased sentence can't tell apart anymore
+    let capitalized: Vec<&str> = sentence
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty() && word.chars().all(|c| c.is_ascii_uppercase()))
+        .collect();
+    let is_subject = |word: &str| {
+        word.chars().all(|c| c.is_ascii_alphabetic())
+            && capitalized.contains(&word)
+            && !NOT_SUBJECTS.contains(&word)
+    };
+
+    let sentence = normalize(sentence).replace_many(&[
+        ("(", " ( "),
+        (")", " ) "),
+        ("[", " [ "),
+        ("]", " ] "),
+        (".", " "),
+        (":", " "),
+    ]);
+    let words: Vec<&str> = sentence.split_whitespace().collect();
+
+    let mut tokens = Vec::<Token>::new();
+    let mut i = 0;
+
+    while i < words.len() {
+        let word = words[i];
+
+        // "C OR BETTER IN", checked first since it contains an OR
+        if words[i + 1..].starts_with(&["OR", "BETTER", "IN"])
+            || words[i + 1..].starts_with(&["OR", "HIGHER", "IN"])
+        {
+            if let Ok(grade) = Grade::try_from(word) {
+                tokens.push(Token::MinGrade(grade));
+                i += 4;
+                continue;
+            }
+        }
+
+        if let Some((count, length)) = at_least(&words[i..]) {
+            tokens.push(Token::AtLeast(count));
+            i += length;
+            continue;
+        }
+
+        if let Some(phrase) = IGNORED_PHRASES
+            .iter()
+            .find(|phrase| words[i..].starts_with(phrase))
+        {
+            i += phrase.len();
+            continue;
+        }
+
+        let token = match word {
+            "(" | "[" => Token::Open,
+            ")" | "]" => Token::Close,
+            "AND" => Token::And,
+            "OR" => Token::Or,
+            _ => match section(word) {
+                Some(section) => Token::Section(section),
+                None => {
+                    // a course is a subject code followed by its number, e.g. "MATH" "140H"
+                    let course = match words.get(i + 1) {
+                        Some(number) if is_subject(word) => {
+                            CourseIdentifier::try_from(format!("{} {}", word, number).as_str()).ok()
+                        }
+                        _ => None,
+                    };
+
+                    match course {
+                        Some(course) => {
+                            i += 1; // the number
+                            Token::Course(course)
+                        }
+                        None => {
+                            // words that weren't recognized are kept together
+                            if let Some(Token::Unknown(phrase)) = tokens.last_mut() {
+                                phrase.push(' ');
+                                phrase.push_str(word);
+                                i += 1;
+                                continue;
+                            }
+
+                            Token::Unknown(word.into())
+                        }
+                    }
+                }
+            },
+        };
+
+        tokens.push(token);
+        i += 1;
+    }
+
+    // phrases are only complete once every word was seen
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            Token::Unknown(phrase) => match clause(&phrase) {
+                Some(node) => Token::Clause(node),
+                None => Token::Unknown(phrase),
+            },
+            token => token,
+        })
+        .collect()
+}
+
+/// A requirement expression as parsed, before it's laid out as a tree
+enum Expression {
+    And(Vec<Expression>),
+    Or(Vec<Expression>),
+    AtLeast(u8, Vec<Expression>),
+    /// Any leaf that isn't an AND/OR, e.g. a course or a semester standing
+    Leaf(CourseRequirementNode),
+}
+
+impl Expression {
+    /// Sets the minimum grade of every course that doesn't have one yet
+    fn with_grade(self, grade: Grade) -> Self {
+        let with_grade = |operands: Vec<Expression>| {
+            operands
+                .into_iter()
+                .map(|operand| operand.with_grade(grade))
+                .collect()
+        };
+
+        match self {
+            Expression::And(operands) => Expression::And(with_grade(operands)),
+            Expression::Or(operands) => Expression::Or(with_grade(operands)),
+            Expression::AtLeast(count, operands) => {
+                Expression::AtLeast(count, with_grade(operands))
+            }
+            Expression::Leaf(CourseRequirementNode::COURSE(course, None)) => {
+                Expression::Leaf(CourseRequirementNode::COURSE(course, Some(grade)))
+            }
+            leaf => leaf,
+        }
+    }
+}
+
+/// Recursive-descent parser for `expression` (see the module documentation)
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(tokens: &'a [Token]) -> Self {
+        Self {
+            tokens,
+            position: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn is_group_end(&self) -> bool {
+        matches!(self.peek(), None | Some(Token::Close))
+    }
+
+    fn expression(&mut self) -> Option<Expression> {
+        // a grade in front of the whole group is for all of it, e.g. "C OR BETTER IN A OR B"
+        if let Some(Token::MinGrade(grade)) = self.peek() {
+            let grade = *grade;
+            self.position += 1;
+            return self
+                .expression()
+                .map(|expression| expression.with_grade(grade));
+        }
+
+        let mut operands = Vec::new();
+
+        loop {
+            if let Some(term) = self.term() {
+                operands.push(term);
+            }
+
+            match self.peek() {
+                Some(Token::Or) => self.position += 1,
+                _ => break,
+            }
+        }
+
+        match operands.len() {
+            0 => None,
+            1 => operands.pop(),
+            _ => Some(Expression::Or(operands)),
+        }
+    }
+
+    fn term(&mut self) -> Option<Expression> {
+        let mut operands = Vec::new();
+        // a grade applies to every operand after it, up to the next OR
+        let mut grade = None;
+
+        while !self.is_group_end() {
+            match self.peek() {
+                Some(Token::Or) => break,
+                Some(Token::And) => self.position += 1,
+                Some(Token::MinGrade(min_grade)) => {
+                    grade = Some(*min_grade);
+                    self.position += 1;
+                }
+                _ => {
+                    if let Some(factor) = self.factor() {
+                        operands.push(match grade {
+                            Some(grade) => factor.with_grade(grade),
+                            None => factor,
+                        });
+                    }
+                }
+            }
+        }
+
+        match operands.len() {
+            0 => None,
+            1 => operands.pop(),
+            _ => Some(Expression::And(operands)),
+        }
+    }
+
+    fn factor(&mut self) -> Option<Expression> {
+        let token = &self.tokens[self.position];
+        self.position += 1;
+
+        match token {
+            Token::Open => {
+                let expression = self.expression();
+
+                // a group still open at the end is closed implicitly
+                if self.peek().is_some() {
+                    self.position += 1;
+                }
+
+                expression
+            }
+            Token::AtLeast(count) => {
+                let operands = self.list();
+
+                match operands.is_empty() {
+                    true => None,
+                    false => Some(Expression::AtLeast(*count, operands)),
+                }
+            }
+            Token::Course(course) => Some(Expression::Leaf(CourseRequirementNode::COURSE(
+                course.clone(),
+                None,
+            ))),
+            Token::Clause(node) => Some(Expression::Leaf(node.clone())),
+            Token::Unknown(phrase) => Some(Expression::Leaf(CourseRequirementNode::TEXT(
+                phrase.clone(),
+            ))),
+            // sections are split off before parsing, grades are read by the term or list
+            // they're in
+            _ => None,
+        }
+    }
+
+    /// The options of "AT LEAST N OF", either a group or the rest of the current one
+    fn list(&mut self) -> Vec<Expression> {
+        let is_group = matches!(self.peek(), Some(Token::Open));
+        if is_group {
+            self.position += 1;
+        }
+
+        let mut operands = Vec::new();
+        // a grade applies to every option after it, each on its own
+        let mut grade = None;
+
+        while !self.is_group_end() {
+            match self.peek() {
+                Some(Token::And | Token::Or) => self.position += 1,
+                Some(Token::MinGrade(min_grade)) => {
+                    grade = Some(*min_grade);
+                    self.position += 1;
+                }
+                _ => {
+                    if let Some(factor) = self.factor() {
+                        operands.push(match grade {
+                            Some(grade) => factor.with_grade(grade),
+                            None => factor,
+                        });
+                    }
+                }
+            }
+        }
+
+        // a group still open at the end is closed implicitly
+        if is_group && self.peek().is_some() {
+            self.position += 1;
+        }
+
+        operands
+    }
+
+    /// Parses every token into one tree
+    fn tree(mut self) -> Result<CourseRequirementTree, Error> {
+        let expression = self.expression();
+
+        // the expression only stops early at a closing parenthesis it didn't open
+        if self.peek().is_some() {
+            return Err(Error::malformed(
+                "requirement",
+                format!("{:?}", self.tokens),
+                "Unmatched closing parenthesis",
+            ));
+        }
+
+        fn append(parent: &mut NodeMut<CourseRequirementNode>, expression: Expression) {
+            let (node, operands) = match expression {
+                Expression::And(operands) => (CourseRequirementNode::AND, operands),
+                Expression::Or(operands) => (CourseRequirementNode::OR, operands),
+                Expression::AtLeast(count, operands) => {
+                    (CourseRequirementNode::AT_LEAST(count), operands)
+                }
+                Expression::Leaf(node) => {
+                    parent.append(node);
+                    return;
+                }
+            };
+
+            let mut node = parent.append(node);
+            for operand in operands {
+                append(&mut node, operand);
+            }
+        }
+
+        // root is always a logic node, a single requirement ends up alone under an AND
+        let (root, operands) = match expression {
+            Some(Expression::Or(operands)) => (CourseRequirementNode::OR, operands),
+            Some(Expression::And(operands)) => (CourseRequirementNode::AND, operands),
+            Some(requirement) => (CourseRequirementNode::AND, vec![requirement]),
+            None => (CourseRequirementNode::AND, vec![]),
+        };
+
+        let mut tree = Tree::new(root);
+        for operand in operands {
+            append(&mut tree.root_mut(), operand);
+        }
+
+        Ok(CourseRequirementTree { tree })
+    }
+}
+
+/// Parses a single requirement, e.g. "MATH 140 OR [MATH 141 AND PHYS 211]"
+pub fn parse_tree(requirement: &str) -> Result<CourseRequirementTree, Error> {
+    Parser::new(&tokenize(requirement)).tree()
+}
+
+pub struct ParsedRequirements {
+    pub requirements: UndergraduateCourseRequirements,
+    /// The prerequisites and concurrent courses are alternatives ("... or Concurrent: ...")
+    pub is_prerequisite_concurrent_separate: bool,
+    /// Phrases (or whole sections) that couldn't be understood, e.g. "PRIOR EXPOSURE TO R".
+    /// Those inside a section are still kept as free text.
+    pub unknown: Vec<String>,
+}
+
+/// Parses a whole requirement sentence of `course` into one tree per section.
+/// Only the first occurrence of each section is kept.
+pub fn parse(sentence: &str, course: &CourseIdentifier) -> ParsedRequirements {
+    let tokens: Vec<Token> = tokenize(sentence)
+        .into_iter()
+        // don't add a course to its own requirements
+        .filter(|token| !matches!(token, Token::Course(other) if other == course))
+        .collect();
+
+    let mut parsed = ParsedRequirements {
+        requirements: UndergraduateCourseRequirements {
+            prerequisites: None,
+            concurrent: None,
+            corequisites: None,
+            recommended: None,
+        },
+        is_prerequisite_concurrent_separate: false,
+        unknown: tokens
+            .iter()
+            .filter_map(|token| match token {
+                Token::Unknown(phrase) => Some(phrase.clone()),
+                _ => None,
+            })
+            .collect(),
+    };
+
+    // (section, index of its label)
+    let sections: Vec<(Section, usize)> = tokens
+        .iter()
+        .enumerate()
+        .filter_map(|(i, token)| match token {
+            Token::Section(section) => Some((*section, i)),
+            _ => None,
+        })
+        .collect();
+
+    for (n, (section, start)) in sections.iter().enumerate() {
+        let end = sections.get(n + 1).map_or(tokens.len(), |(_, end)| *end);
+
+        let previous = start.checked_sub(1).and_then(|i| tokens.get(i));
+        if let (Section::Concurrent, Some(Token::Or)) = (section, previous) {
+            parsed.is_prerequisite_concurrent_separate = true;
+        }
+
+        let section_tokens = &tokens[start + 1..end];
+        if !section_tokens.iter().any(|token| {
+            matches!(
+                token,
+                Token::Course(_) | Token::Clause(_) | Token::Unknown(_)
+            )
+        }) {
+            continue;
+        }
+
+        let requirement = match section {
+            Section::Prerequisite => &mut parsed.requirements.prerequisites,
+            Section::Concurrent => &mut parsed.requirements.concurrent,
+            Section::Corequisite => &mut parsed.requirements.corequisites,
+            Section::Recommended => &mut parsed.requirements.recommended,
+        };
+
+        // in a sense: write-once
+        if requirement.is_none() {
+            match Parser::new(section_tokens).tree() {
+                Ok(tree) => *requirement = Some(tree),
+                Err(e) => parsed.unknown.push(e.to_string()),
+            }
+        }
+    }
+
+    parsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn course(identifier: &str) -> CourseIdentifier {
+        CourseIdentifier::try_from(identifier).unwrap()
+    }
+
+    fn tree(requirement: &str) -> String {
+        parse_tree(requirement).unwrap().to_string()
+    }
+
+    /// Every section of `sentence` as text, in `UndergraduateCourseRequirements` order
+    fn sections(sentence: &str) -> [Option<String>; 4] {
+        let requirements = parse(sentence, &course("MATH 401")).requirements;
+
+        [
+            requirements.prerequisites,
+            requirements.concurrent,
+            requirements.corequisites,
+            requirements.recommended,
+        ]
+        .map(|tree| tree.map(|tree| tree.to_string()))
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        for (requirement, expected) in [
+            (
+                "CMPSC 121 or CMPSC 131 and MATH 140",
+                "CMPSC 121 OR [CMPSC 131 AND MATH 140]",
+            ),
+            (
+                "MATH 140 and MATH 141 or MATH 140H and MATH 141H",
+                "[MATH 140 AND MATH 141] OR [MATH 140H AND MATH 141H]",
+            ),
+            ("MATH 140, MATH 141", "MATH 140 AND MATH 141"),
+        ] {
+            assert_eq!(expected, tree(requirement), "{requirement}");
+        }
+    }
+
+    #[test]
+    fn parentheses_group() {
+        for (requirement, expected) in [
+            (
+                "(MATH 140 or MATH 140H) and PHYS 211 or 5th semester standing",
+                "[(MATH 140 OR MATH 140H) AND PHYS 211] OR 5TH SEMESTER STANDING",
+            ),
+            (
+                "CMPSC 131 and (CMPSC 360 or MATH 311W)",
+                "CMPSC 131 AND (CMPSC 360 OR MATH 311W)",
+            ),
+            // left open at the end
+            (
+                "MATH 140 or (MATH 141 and [PHYS 211 or PHYS 212]",
+                "MATH 140 OR [MATH 141 AND (PHYS 211 OR PHYS 212)]",
+            ),
+        ] {
+            assert_eq!(expected, tree(requirement), "{requirement}");
+        }
+
+        assert!(parse_tree("MATH 140)").is_err());
+    }
+
+    #[test]
+    fn at_least_lists_its_options() {
+        for (requirement, expected) in [
+            (
+                "at least two of the following: MATH 220, MATH 230, or MATH 231",
+                "AT LEAST 2 OF (MATH 220 OR MATH 230 OR MATH 231)",
+            ),
+            (
+                "CMPSC 121 and at least one of the following courses: (MATH 220 or MATH 230) and STAT 200",
+                "CMPSC 121 AND AT LEAST 1 OF (MATH 220 OR MATH 230) AND STAT 200",
+            ),
+            (
+                "Select 2 of the following: STAT 414, STAT 415, or STAT 416",
+                "AT LEAST 2 OF (STAT 414 OR STAT 415 OR STAT 416)",
+            ),
+        ] {
+            assert_eq!(expected, tree(requirement), "{requirement}");
+        }
+    }
+
+    #[test]
+    fn grades_apply_to_the_rest_of_their_operand() {
+        for (requirement, expected) in [
+            (
+                "A grade of C or better in MATH 141 and PHYS 211",
+                "[C OR BETTER IN MATH 141] AND [C OR BETTER IN PHYS 211]",
+            ),
+            (
+                "CMPSC 121 and C or better in (CMPSC 360 or MATH 311W)",
+                "CMPSC 121 AND ([C OR BETTER IN CMPSC 360] OR [C OR BETTER IN MATH 311W])",
+            ),
+            (
+                "C or better in MATH 141 or STAT 200",
+                "[C OR BETTER IN MATH 141] OR [C OR BETTER IN STAT 200]",
+            ),
+            // only for its side of the OR
+            (
+                "MATH 140 and C or better in MATH 141 or STAT 200",
+                "[MATH 140 AND [C OR BETTER IN MATH 141]] OR STAT 200",
+            ),
+            (
+                "at least one of the following: MATH 220, C or better in MATH 141, or MATH 230",
+                "AT LEAST 1 OF (MATH 220 OR [C OR BETTER IN MATH 141] OR [C OR BETTER IN MATH 230])",
+            ),
+        ] {
+            assert_eq!(expected, tree(requirement), "{requirement}");
+        }
+    }
+
+    #[test]
+    fn sections_are_split() {
+        assert_eq!(
+            [Some("MATH 230".into()), Some("MATH 220".into()), None, None],
+            sections("Enforced Prerequisite at Enrollment: MATH 230 Enforced Concurrent at Enrollment: MATH 220")
+        );
+        assert_eq!(
+            [
+                Some("CMPSC 121".into()),
+                None,
+                Some("CMPSC 221".into()),
+                Some("CMPSC 122".into())
+            ],
+            sections("Prerequisite: CMPSC 121. Recommended Preparation: CMPSC 122 Corequisite: CMPSC 221")
+        );
+
+        // only the first occurrence of a section counts
+        assert_eq!(
+            [Some("MATH 140".into()), None, None, None],
+            sections("Prerequisite: MATH 140 Prerequisite: MATH 141")
+        );
+    }
+
+    #[test]
+    fn concurrent_after_or_is_an_alternative() {
+        let parsed = parse(
+            "Enforced Prerequisite at Enrollment: MATH 140 or Enforced Concurrent at Enrollment: MATH 140",
+            &course("MATH 141"),
+        );
+        assert!(parsed.is_prerequisite_concurrent_separate);
+
+        let parsed = parse(
+            "Enforced Prerequisite at Enrollment: MATH 230 Enforced Concurrent at Enrollment: MATH 220",
+            &course("MATH 401"),
+        );
+        assert!(!parsed.is_prerequisite_concurrent_separate);
+    }
+
+    #[test]
+    fn semicolon_before_a_section_only_ends_the_previous_one() {
+        let sentence = "Enforced Prerequisite at Enrollment: MATH 230; Enforced Concurrent at Enrollment: MATH 220";
+        let parsed = parse(sentence, &course("MATH 401"));
+
+        assert!(!parsed.is_prerequisite_concurrent_separate);
+        assert_eq!(
+            [Some("MATH 230".into()), Some("MATH 220".into()), None, None],
+            sections(sentence)
+        );
+
+        // anywhere else it's still an OR
+        assert_eq!(
+            [
+                Some("MATH 140 OR MATH 141".into()),
+                Some("MATH 220".into()),
+                None,
+                None
+            ],
+            sections("Prerequisite: MATH 140; MATH 141 Concurrent Courses: MATH 220")
+        );
+    }
+
+    #[test]
+    fn credit_counts_arent_courses() {
+        for (requirement, expected) in [
+            ("completion of 60 credits", "COMPLETION OF 60 CREDITS"),
+            (
+                "MATH 140 and a minimum of 6 credits in ENGL",
+                "MATH 140 AND A MINIMUM OF 6 CREDITS IN ENGL",
+            ),
+            (
+                "ENGL 15 or a minimum of 3 credits in ENGL 30H",
+                "ENGL 15 OR [A MINIMUM OF 3 CREDITS IN AND ENGL 30H]",
+            ),
+        ] {
+            assert_eq!(expected, tree(requirement), "{requirement}");
+        }
+    }
+
+    #[test]
+    fn a_course_isnt_its_own_requirement() {
+        assert_eq!(
+            [Some("MATH 140".into()), None, None, None],
+            sections("Prerequisite: MATH 140 or MATH 401")
+        );
+    }
+}
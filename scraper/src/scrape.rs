@@ -1,16 +1,114 @@
 use crate::{
     bulletin::{
-        AttributeList, CampusList, College, CourseIdentifier, CourseRequirementTree,
-        FullAttributeList, UndergraduateCourse, UndergraduateCourseDeviantFlags,
+        AttributeList, CampusList, College, CourseIdentifier, FullAttributeList,
+        ProgramRequirement, UndergraduateCourse, UndergraduateCourseDeviantFlags,
         UndergraduateCourseFlags, UndergraduateCourseRequirements, UndergraduateProgram,
-        UndergraduateProgramType, BASE_URL,
+        UndergraduateProgramDetails, UndergraduateProgramType, BASE_URL,
     },
-    utility::{ReplaceMany, TrimAll},
+    error::{Context, Error, Result},
+    fetch::Fetcher,
+    report::ScrapeReport,
+    requirement::{self, ParsedRequirements},
+    utility::TrimAll,
 };
 use scraper::{ElementRef, Selector};
 
 pub trait Scrappable<T> {
-    fn extract_list(html: &str) -> Vec<T>;
+    fn extract_list(html: &str, mode: &mut ScrapeMode) -> Result<Vec<T>>;
+
+    /// Retrieves `url` through `fetcher` and extracts the list from it
+    fn scrape(fetcher: &dyn Fetcher, url: &str, mode: &mut ScrapeMode) -> Result<Vec<T>> {
+        let html = fetcher.fetch(url)?;
+
+        if let ScrapeMode::Lenient(report) = mode {
+            report.set_url(url);
+        }
+
+        Self::extract_list(&html, mode).with_context(|| url)
+    }
+}
+
+/// How a list reacts to a single record (course block, program card) it can't scrape
+pub enum ScrapeMode<'a> {
+    /// The whole list fails
+    Strict,
+    /// The record is skipped and recorded in the report
+    Lenient(&'a mut ScrapeReport),
+}
+
+impl ScrapeMode<'_> {
+    /// Number of records skipped so far (always 0 in strict mode)
+    pub fn skipped(&self) -> usize {
+        match self {
+            ScrapeMode::Strict => 0,
+            ScrapeMode::Lenient(report) => report.failures.len(),
+        }
+    }
+
+    /// Passes `result` through in strict mode. In lenient mode, failures are recorded
+    /// and `None` is returned so the record can be skipped.
+    fn skip_failure<T>(
+        &mut self,
+        result: Result<T>,
+        element: &ElementRef,
+        identifier: impl FnOnce() -> Option<String>,
+    ) -> Result<Option<T>> {
+        match (result, self) {
+            (Ok(value), _) => Ok(Some(value)),
+            (Err(e), ScrapeMode::Strict) => Err(e),
+            (Err(e), ScrapeMode::Lenient(report)) => {
+                report.record(identifier(), &e, element.html());
+                Ok(None)
+            }
+        }
+    }
+
+    /// Same as `skip_failure`, for a whole page at `url` that couldn't be scraped (it may not
+    /// even have been fetched, so no HTML is recorded)
+    pub fn skip_page<T>(
+        &mut self,
+        result: Result<T>,
+        url: &str,
+        identifier: impl FnOnce() -> Option<String>,
+    ) -> Result<Option<T>> {
+        match (result, self) {
+            (Ok(value), _) => Ok(Some(value)),
+            (Err(e), ScrapeMode::Strict) => Err(e),
+            (Err(e), ScrapeMode::Lenient(report)) => {
+                report.set_url(url);
+                report.record(identifier(), &e, String::new());
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Returns the first element matching `selector`
+fn select_first<'a>(element: &ElementRef<'a>, selector: &'static str) -> Result<ElementRef<'a>> {
+    element
+        .select(&Selector::parse(selector).unwrap())
+        .next()
+        .ok_or(Error::MissingElement { selector })
+}
+
+/// Returns the first text node of the first element matching `selector`
+fn select_text<'a>(element: &ElementRef<'a>, selector: &'static str) -> Result<&'a str> {
+    select_first(element, selector)?
+        .text()
+        .next()
+        .ok_or(Error::MissingElement { selector })
+}
+
+/// Returns the first text of a node, or an empty string if it has none (e.g. `<br>` or comments)
+fn node_text<'a>(node: ego_tree::NodeRef<'a, scraper::Node>) -> &'a str {
+    match ElementRef::wrap(node) {
+        Some(element) => element.text().next().unwrap_or_default(),
+        None => node
+            .value()
+            .as_text()
+            .map(|text| &**text)
+            .unwrap_or_default(),
+    }
 }
 
 pub struct ScrapeUndergraduatePrograms;
@@ -26,20 +124,15 @@ struct ProgramDetails<'a> {
 type ProgramTitleDetails<'a> = (&'a str, &'a str);
 
 impl ScrapeUndergraduatePrograms {
-    fn extract_program_details<'a>(element: &'a ElementRef) -> ProgramDetails<'a> {
-        let raw_program_title = element
-            .select(&Selector::parse("span.title").unwrap())
-            .next()
-            .unwrap()
-            .text()
-            .next()
-            .unwrap();
+    fn extract_program_details<'a>(element: &'a ElementRef) -> Result<ProgramDetails<'a>> {
+        let raw_program_title = select_text(element, "span.title")?;
 
         // extract title details
-        let program_keywords = Self::extract_keywords(element);
+        let program_keywords = Self::extract_keywords(element)?;
         let (program_title, raw_program_type) =
-            Self::extract_program_title_details(raw_program_title);
-        let program_type = Self::extract_program_type(element, raw_program_type);
+            Self::extract_program_title_details(raw_program_title)?;
+        let program_type = Self::extract_program_type(element, raw_program_type)
+            .with_context(|| raw_program_title)?;
 
         let program_college = match program_type {
             UndergraduateProgramType::ROTC(_) => None, // ROTC doesn't have college
@@ -50,43 +143,66 @@ impl ScrapeUndergraduatePrograms {
                     Some(College::UniversityCollege)
                 }
                 "Science, B.S. (Behrend)" => Some(College::Behrend),
-                _ => Some(College::from(*program_keywords.get(1).unwrap())),
+                // college is always the second keyword
+                _ => match program_keywords.get(1) {
+                    Some(college) => {
+                        Some(College::try_from(*college).with_context(|| raw_program_title)?)
+                    }
+                    None => {
+                        return Err(Error::MissingElement {
+                            selector: ".item-container > .keyword",
+                        })
+                        .with_context(|| raw_program_title)
+                    }
+                },
             },
         };
 
-        ProgramDetails {
+        Ok(ProgramDetails {
             program_title,
             program_type,
             program_college,
             program_keywords,
-        }
+        })
     }
 
-    fn extract_keywords<'a>(element: &'a ElementRef) -> Vec<&'a str> {
-        let selector = Selector::parse(".item-container > .keyword").unwrap();
-        let raw_keyword_list = element.select(&selector);
+    fn extract_keywords<'a>(element: &'a ElementRef) -> Result<Vec<&'a str>> {
+        let selector = ".item-container > .keyword";
+        let parsed_selector = Selector::parse(selector).unwrap();
+        let raw_keyword_list = element.select(&parsed_selector);
 
         let mut keywords = Vec::new();
 
         for element in raw_keyword_list {
-            keywords.push(element.text().next().unwrap());
+            keywords.push(
+                element
+                    .text()
+                    .next()
+                    .ok_or(Error::MissingElement { selector })?,
+            );
         }
 
-        keywords
+        Ok(keywords)
     }
 
     /// Accepts a program title as a String and returns a tuple of three elements:
     /// (program_title, program_type, program_location)
-    fn extract_program_title_details<'a>(raw_title: &'a str) -> ProgramTitleDetails<'a> {
+    fn extract_program_title_details<'a>(raw_title: &'a str) -> Result<ProgramTitleDetails<'a>> {
         // exceptions ("noise" in data)
-        if let Some(cleaned_result) = Self::manage_noisy_titles(&raw_title) {
-            return cleaned_result;
+        if let Some(cleaned_result) = Self::manage_noisy_titles(raw_title) {
+            return Ok(cleaned_result);
         }
 
         let comma_idx = match raw_title.rfind(", ") {
             Some(idx) => idx,
             // all titles have a comma, and those without are manged in `manage_noisy_titles`.
-            None => panic!("Cannot find comma in title: {}", raw_title),
+            None => {
+                return Err(Error::malformed(
+                    "program title",
+                    raw_title,
+                    "Cannot find comma in title",
+                ))
+            }
         };
 
         let (program_title, program_details) = raw_title.split_at(comma_idx);
@@ -100,14 +216,14 @@ impl ScrapeUndergraduatePrograms {
                 let program_type = program_type.trim();
                 // let program_field = program_field.trim_end_matches(')');
 
-                return (program_title.into(), program_type.into());
+                Ok((program_title, program_type))
             }
             // title + type
-            None => (program_title.into(), program_details.into()),
+            None => Ok((program_title, program_details)),
         }
     }
 
-    fn manage_noisy_titles(raw_title: &str) -> Option<ProgramTitleDetails> {
+    fn manage_noisy_titles(raw_title: &str) -> Option<ProgramTitleDetails<'_>> {
         match raw_title {
             "Science, B.S./Business Administration, M.B.A." => {
                 Some(("Science, B.S./Business Administration, M.B.A.", "B.S."))
@@ -120,16 +236,39 @@ impl ScrapeUndergraduatePrograms {
             "Bachelor of Philosophy Degree" => Some(("Philosophy", "B.Phil.")),
             "Global and International Studies Major" => {
                 // B.S, B.A, B.F.A, etc. (matches students' first major)
-                return Some(("Global and International Studies", "B."));
+                Some(("Global and International Studies", "B."))
             }
             _ => None,
         }
     }
 
+    fn extract_image<'a>(element: &ElementRef<'a>) -> Result<&'a str> {
+        let selector = ".item-container > .image";
+        let style =
+            select_first(element, selector)?
+                .attr("style")
+                .ok_or(Error::MissingElement {
+                    selector: ".item-container > .image[style]",
+                })?;
+
+        // style="background-image: url(/path/to/image.jpg)"
+        match style
+            .split_once("url(")
+            .and_then(|(_, url)| url.split_once(')'))
+        {
+            Some((image, _)) => Ok(image),
+            None => Err(Error::malformed(
+                "program image",
+                style,
+                "Couldn't find url()",
+            )),
+        }
+    }
+
     fn extract_program_type(
         element: &ElementRef,
         raw_program_type: &str,
-    ) -> UndergraduateProgramType {
+    ) -> Result<UndergraduateProgramType> {
         if let Some(up_campus_types) = match raw_program_type {
             "Minor" => Some(UndergraduateProgramType::Minor(raw_program_type.into())),
             "Certificate" => Some(UndergraduateProgramType::Certificate(
@@ -138,7 +277,7 @@ impl ScrapeUndergraduatePrograms {
             "ROTC" => Some(UndergraduateProgramType::ROTC(raw_program_type.into())),
             _ => None,
         } {
-            return up_campus_types;
+            return Ok(up_campus_types);
         }
 
         // campus list element
@@ -151,37 +290,84 @@ impl ScrapeUndergraduatePrograms {
                 let campus_list: Vec<&str> = child
                     .value()
                     .as_text()
-                    .unwrap()
+                    .ok_or(Error::MissingElement {
+                        selector: ".context-overlay > p.list",
+                    })?
                     .trim()
                     .split(", ")
                     .collect();
 
-                let campus_list = CampusList::from(&campus_list);
+                let campus_list = CampusList::try_from(campus_list.as_slice())?;
 
                 if raw_program_type.starts_with("B.") {
-                    return UndergraduateProgramType::BaccalaureateDegree(
+                    return Ok(UndergraduateProgramType::BaccalaureateDegree(
                         raw_program_type.into(),
                         campus_list,
-                    );
+                    ));
                 } else if raw_program_type.starts_with("A.") {
-                    return UndergraduateProgramType::AssociateDegree(
+                    return Ok(UndergraduateProgramType::AssociateDegree(
                         raw_program_type.into(),
                         campus_list,
-                    );
+                    ));
                 } else {
-                    panic!("Unknown program type: {:?}", raw_program_type);
+                    return Err(Error::unknown("program type", raw_program_type));
                 }
             }
         }
 
         // shouldn't hit this because specific-problematic items should be cleaned by
         // `extract_program_title_details` via `manage_noisy_titles`
-        panic!("Unknown program type: {:?}", raw_program_type);
+        Err(Error::unknown("program type", raw_program_type))
+    }
+
+    fn extract_link<'a>(element: &ElementRef<'a>) -> Result<&'a str> {
+        select_first(element, "a")?
+            .attr("href")
+            .ok_or(Error::MissingElement {
+                selector: "a[href]",
+            })
+    }
+
+    fn extract_program(element: &ElementRef) -> Result<UndergraduateProgram> {
+        /* selections */
+
+        let raw_link = Self::extract_link(element)?;
+        let raw_image = Self::extract_image(element).with_context(|| raw_link)?;
+
+        /* parsing */
+
+        let ProgramDetails {
+            program_title,
+
+            program_type, // includes campus list
+            program_college,
+            program_keywords,
+        } = Self::extract_program_details(element).with_context(|| raw_link)?;
+
+        // remove first char '/' from raw_link
+        let program_link = format!("{}/{}", BASE_URL, raw_link.trim_start_matches('/'));
+        // "-med" is a lower resolution of image
+        let program_image = format!(
+            "{}/{}",
+            BASE_URL,
+            raw_image.trim_start_matches('/').replace("-med", "")
+        );
+
+        /* deserialization */
+        Ok(UndergraduateProgram {
+            title: program_title.into(),
+            link: program_link,
+            program_type,
+            image: program_image,
+            college: program_college,
+            keywords: program_keywords.into_iter().map(String::from).collect(),
+            // field: program_field.map(String::from),
+        })
     }
 }
 
 impl Scrappable<UndergraduateProgram> for ScrapeUndergraduatePrograms {
-    fn extract_list(html: &str) -> Vec<UndergraduateProgram> {
+    fn extract_list(html: &str, mode: &mut ScrapeMode) -> Result<Vec<UndergraduateProgram>> {
         // println!("Parsing...");
         let document = scraper::Html::parse_document(html);
 
@@ -190,63 +376,206 @@ impl Scrappable<UndergraduateProgram> for ScrapeUndergraduatePrograms {
         let mut programs: Vec<UndergraduateProgram> = Vec::new();
 
         for element in document.select(&Selector::parse("ul.isotope .item").unwrap()) {
-            /* selections */
+            let program = mode.skip_failure(Self::extract_program(&element), &element, || {
+                Self::extract_link(&element).ok().map(String::from)
+            })?;
 
-            let raw_link = element
-                .select(&Selector::parse("a").unwrap())
-                .next()
-                .unwrap()
-                .attr("href")
-                .unwrap();
+            if let Some(program) = program {
+                programs.push(program);
+            }
+        }
 
-            let raw_image = element
-                .select(&Selector::parse(".item-container > .image").unwrap())
-                .next()
-                .unwrap()
-                .attr("style")
-                .unwrap()
-                .split_once("url(")
-                .unwrap()
-                .1
-                .split_once(")")
-                .unwrap()
-                .0;
-
-            /* parsing */
-
-            let ProgramDetails {
-                program_title,
-
-                program_type, // includes campus list
-                program_college,
-                program_keywords,
-            } = Self::extract_program_details(&element);
-
-            // remove first char '/' from raw_link
-            let program_link = format!("{}/{}", BASE_URL, &raw_link[1..]);
-            // "-med" is a lower resolution of image
-            let program_image = format!("{}/{}", BASE_URL, &raw_image[1..].replace("-med", ""));
-
-            /* deserialization */
-            let program = UndergraduateProgram {
-                title: program_title.into(),
-                link: program_link,
-                program_type,
-                image: program_image,
-                college: program_college,
-                keywords: program_keywords.into_iter().map(String::from).collect(),
-                // field: program_field.map(String::from),
-            };
-
-            programs.push(program);
+        Ok(programs)
+    }
+}
+
+/// Follows the link of a program to its own page, e.g. `/programs/math-bs/`, and reads its
+/// tabs. Pages are built by the bulletin's CMS, each tab is a `#<name>textcontainer`.
+pub struct ScrapeUndergraduateProgramDetails;
+
+impl ScrapeUndergraduateProgramDetails {
+    /// Text of the whole element, on a single line
+    fn element_text(element: &ElementRef) -> String {
+        element
+            .text()
+            .flat_map(str::split_whitespace)
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    /// Text of a tab, one line per heading, paragraph or list item. `None` if the page doesn't
+    /// have the tab or it's empty.
+    fn extract_tab_text(document: &scraper::Html, selector: &'static str) -> Option<String> {
+        let tab = document
+            .select(&Selector::parse(selector).unwrap())
+            .next()?;
+        let paragraph = Selector::parse("p").unwrap();
+
+        let lines: Vec<String> = tab
+            .select(&Selector::parse("h3, h4, p, li").unwrap())
+            // the paragraphs of a list item are lines of their own
+            .filter(|element| {
+                element.value().name() != "li" || element.select(&paragraph).next().is_none()
+            })
+            .map(|element| Self::element_text(&element))
+            .filter(|line| !line.is_empty())
+            .collect();
+
+        match lines.is_empty() {
+            true => None,
+            false => Some(lines.join("\n")),
+        }
+    }
+
+    /// Returns (min_credits, credits) of a requirement row, e.g. "3" or "3-4". Both are `None`
+    /// for rows without credits.
+    fn extract_credits(raw_credits: &str) -> Result<(Option<f32>, Option<f32>)> {
+        let parse_credits = |value: &str| -> Result<f32> {
+            value.trim().parse().map_err(|_| {
+                Error::malformed(
+                    "program requirement credits",
+                    raw_credits,
+                    "Couldn't parse number.",
+                )
+            })
+        };
+
+        match raw_credits.trim() {
+            "" => Ok((None, None)),
+            raw => match raw.split_once('-') {
+                Some((min, max)) => Ok((Some(parse_credits(min)?), Some(parse_credits(max)?))),
+                None => Ok((None, Some(parse_credits(raw)?))),
+            },
+        }
+    }
+
+    /// Reads a row of a degree requirement table. Headings update `section` and, like sums and
+    /// empty rows, return `None`.
+    fn extract_requirement(
+        element: &ElementRef,
+        section: &mut Option<String>,
+    ) -> Result<Option<ProgramRequirement>> {
+        let classes: Vec<&str> = element.value().classes().collect();
+
+        // "Total Credits"
+        if classes.contains(&"listsum") {
+            return Ok(None);
+        }
+        if classes.contains(&"areaheader") || classes.contains(&"areasubheader") {
+            *section = Some(Self::element_text(element)).filter(|text| !text.is_empty());
+            return Ok(None);
+        }
+
+        let cells: Vec<ElementRef> = element.select(&Selector::parse("td").unwrap()).collect();
+        let first_cell = match cells.first() {
+            Some(cell) => cell,
+            None => return Ok(None),
+        };
+
+        let (course, title) = match first_cell.select(&Selector::parse("a").unwrap()).next() {
+            // "MATH 140" followed by its title
+            Some(link) => (
+                Some(CourseIdentifier::try_from(
+                    Self::element_text(&link).as_str(),
+                )?),
+                cells.get(1).map(Self::element_text).unwrap_or_default(),
+            ),
+            // "Select 6 credits of GN courses"
+            None => (None, Self::element_text(first_cell)),
+        };
+
+        if course.is_none() && title.is_empty() {
+            return Ok(None);
+        }
+
+        let raw_credits = match select_first(element, "td.hourscol") {
+            Ok(cell) => Self::element_text(&cell),
+            Err(_) => String::new(),
+        };
+        let (min_credits, credits) = Self::extract_credits(&raw_credits)?;
+
+        Ok(Some(ProgramRequirement {
+            section: section.clone(),
+            course,
+            title,
+            credits,
+            min_credits,
+            is_alternative: classes.contains(&"orclass"),
+        }))
+    }
+
+    fn extract(
+        html: &str,
+        link: &str,
+        mode: &mut ScrapeMode,
+    ) -> Result<UndergraduateProgramDetails> {
+        let document = scraper::Html::parse_document(html);
+
+        // every program page opens on its overview, anything else isn't a program page
+        let overview_selector = "#overviewtextcontainer";
+        if document
+            .select(&Selector::parse(overview_selector).unwrap())
+            .next()
+            .is_none()
+        {
+            return Err(Error::MissingElement {
+                selector: overview_selector,
+            });
+        }
+
+        let mut requirements = Vec::<ProgramRequirement>::new();
+        // headings above the tables, then the heading rows inside them
+        let mut section = None;
+        let selector = Selector::parse(
+            "#programrequirementstextcontainer h3, #programrequirementstextcontainer h4, #programrequirementstextcontainer table.sc_courselist tr",
+        )
+        .unwrap();
+
+        for element in document.select(&selector) {
+            if element.value().name() != "tr" {
+                section = Some(Self::element_text(&element)).filter(|text| !text.is_empty());
+                continue;
+            }
+
+            let requirement = mode.skip_failure(
+                Self::extract_requirement(&element, &mut section),
+                &element,
+                || Some(link.to_string()),
+            )?;
+
+            if let Some(requirement) = requirement.flatten() {
+                requirements.push(requirement);
+            }
+        }
+
+        Ok(UndergraduateProgramDetails {
+            link: link.into(),
+            overview: Self::extract_tab_text(&document, overview_selector),
+            entrance_requirements: Self::extract_tab_text(&document, "#howtogetintextcontainer"),
+            contact: Self::extract_tab_text(&document, "#contacttextcontainer"),
+            requirements,
+        })
+    }
+
+    /// Retrieves the page of `program` through `fetcher` and extracts its details
+    pub fn scrape(
+        fetcher: &dyn Fetcher,
+        program: &UndergraduateProgram,
+        mode: &mut ScrapeMode,
+    ) -> Result<UndergraduateProgramDetails> {
+        let html = fetcher.fetch(&program.link)?;
+
+        if let ScrapeMode::Lenient(report) = mode {
+            report.set_url(&program.link);
         }
 
-        programs
+        Self::extract(&html, &program.link, mode).with_context(|| &program.link)
     }
 }
 
 pub struct ScrapeUndergraduateCourseGroups;
 
+#[allow(dead_code)]
 pub struct CourseGroupIdentifier {
     pub code: String,
     pub title: String,
@@ -254,28 +583,30 @@ pub struct CourseGroupIdentifier {
 }
 
 impl Scrappable<CourseGroupIdentifier> for ScrapeUndergraduateCourseGroups {
-    fn extract_list(html: &str) -> Vec<CourseGroupIdentifier> {
-        let document = scraper::Html::parse_document(&html);
+    fn extract_list(html: &str, _mode: &mut ScrapeMode) -> Result<Vec<CourseGroupIdentifier>> {
+        let document = scraper::Html::parse_document(html);
         let selector = Selector::parse(".az_sitemap li").unwrap();
         let courses = document.select(&selector).skip(27); // skip #, A-Z tags
 
         let mut course_codes = Vec::new();
 
         for course in courses {
-            let course = course
-                .select(&Selector::parse("a").unwrap())
-                .next()
-                .unwrap();
-
-            let (title, code) = course
+            let course = select_first(&course, "a")?;
+            let raw_text = course
                 .text()
                 .next()
-                .unwrap()
-                .rsplit_once(" (")
-                .expect("Couldn't find course code.");
+                .ok_or(Error::MissingElement { selector: "a" })?;
+
+            let (title, code) = raw_text.rsplit_once(" (").ok_or(Error::malformed(
+                "course group",
+                raw_text,
+                "Couldn't find course code.",
+            ))?;
 
             let code = code.trim_end_matches(')');
-            let raw_link = course.attr("href").unwrap();
+            let raw_link = course.attr("href").ok_or(Error::MissingElement {
+                selector: "a[href]",
+            })?;
 
             course_codes.push(CourseGroupIdentifier {
                 code: code.into(),
@@ -284,7 +615,7 @@ impl Scrappable<CourseGroupIdentifier> for ScrapeUndergraduateCourseGroups {
             });
         }
 
-        course_codes
+        Ok(course_codes)
     }
 }
 
@@ -298,37 +629,40 @@ struct CourseExtraDetails {
     flags: UndergraduateCourseFlags,
 }
 
-struct CourseRequirementTreeDetails {
-    requirements: UndergraduateCourseRequirements,
-    flags: UndergraduateCourseFlags,
-}
-
 impl ScrapeUndergraduateCourses {
-    fn extract_course_credits(element: &ElementRef) -> (Option<f32>, f32) {
-        let raw_credits = element
-            .select(&Selector::parse(".course_credits").unwrap())
-            .next()
-            .unwrap()
-            .text()
-            .next()
-            .unwrap()
-            .trim();
+    fn extract_course_credits(element: &ElementRef) -> Result<(Option<f32>, f32)> {
+        let raw_credits = select_text(element, ".course_credits")?.trim();
+
+        let parse_credits = |value: &str| -> Result<f32> {
+            value.trim().parse().map_err(|_| {
+                Error::malformed("course credits", raw_credits, "Couldn't parse number.")
+            })
+        };
 
         match raw_credits.rfind("of") {
             Some(_) => {
                 // ranged credit
-                let (raw_min, raw_max) = raw_credits.split_once(" Credits/Maximum of ").unwrap();
+                let (raw_min, raw_max) = match raw_credits.split_once(" Credits/Maximum of ") {
+                    Some(split) => split,
+                    None => {
+                        return Err(Error::malformed(
+                            "course credits",
+                            raw_credits,
+                            "Couldn't find maximum credits.",
+                        ))
+                    }
+                };
 
-                let max = raw_max.parse().unwrap();
+                let max = parse_credits(raw_max)?;
 
                 let min = match raw_min.split_once("-") {
-                    Some((min, _)) => min.parse().unwrap(),
-                    None => raw_min.parse().unwrap(),
+                    Some((min, _)) => parse_credits(min)?,
+                    None => parse_credits(raw_min)?,
                 };
 
                 match min == max {
-                    true => (None, max),
-                    false => (Some(min), max),
+                    true => Ok((None, max)),
+                    false => Ok((Some(min), max)),
                 }
             }
             None => {
@@ -336,273 +670,29 @@ impl ScrapeUndergraduateCourses {
 
                 match raw_credits.split_once("-") {
                     // ranged credit
-                    Some((min, max)) => (Some(min.parse().unwrap()), max.parse().unwrap()),
+                    Some((min, max)) => Ok((Some(parse_credits(min)?), parse_credits(max)?)),
                     None => {
                         // fixed credit
-                        (None, raw_credits.parse().unwrap())
+                        Ok((None, parse_credits(&raw_credits)?))
                     }
                 }
             }
         }
     }
 
-    // TODO: work on not relying on chunks from HTML. Create an algorithm to accept a single string
-    // IDEA: instead of going char by char consecutively. Go over string multiple times through "filters"
-    //       ex. replace all ; with |, replace all , with &. Parse all "possible" courses, collect deviants.
-    fn create_text_chunks<'a>(element: &'a ElementRef) -> Vec<&'a str> {
-        let mut buffer = Vec::<&str>::new();
-        let mut cursor = Some(element.first_child().unwrap());
-        while let Some(sibling) = cursor {
-            buffer.push(match ElementRef::wrap(sibling) {
-                Some(element) => element.text().next().unwrap(),
-                None => sibling.value().as_text().unwrap(),
-            });
-
-            cursor = sibling.next_sibling();
-        }
-        buffer
-    }
-
-    /// Takes a vector of text chunks from HTML
-    /// TODO: work on using single string only.
-    fn create_requirement_trees(
-        text_chunks: Vec<&str>,
+    /// Parses a requirement paragraph, e.g. "Enforced Prerequisite at Enrollment: MATH 140"
+    fn extract_requirements(
+        element: &ElementRef,
         identifier: &CourseIdentifier,
-    ) -> CourseRequirementTreeDetails {
-        let mut prerequisites: Option<CourseRequirementTree> = None;
-        let mut concurrent: Option<CourseRequirementTree> = None;
-        let mut corequisites: Option<CourseRequirementTree> = None;
-        let mut recommended: Option<CourseRequirementTree> = None;
-
-        let mut flags = UndergraduateCourseFlags {
-            // false by default
-            is_prerequisite_concurrent_separate: false,
-            deviant: UndergraduateCourseDeviantFlags {
-                empty_crosslist: false,
-                unknown_requirement: false,
-            },
-        };
-
-        enum CourseRequirementMode {
-            Prerequisite,
-            Concurrent,
-            Corequisite,
-            Recommended,
-        }
-
-        let mut selected_mode: Option<CourseRequirementMode> = None;
-        let mut requirement_text = String::new();
-
-        // TODO: this is so horrible fix
-        let mut switch_mode = |mode: Option<CourseRequirementMode>, text: String| -> String {
-            if !text.is_empty() {
-                // make sure there any no dangling ORs or ANDS in text
-                let text = {
-                    let mut text = text.as_str();
-                    text = text.trim_start_matches("&");
-                    text = text.trim_end_matches("&");
-                    text = text.trim_start_matches("|");
-                    text = text.trim_end_matches("|");
-                    text
-                };
-
-                // flush current text
-                if let Some(selected_mode) = &selected_mode {
-                    // println!("BEFORE: {}", text);
-
-                    let tree = CourseRequirementTree::try_from(text).ok();
-                    let active_mode = match selected_mode {
-                        CourseRequirementMode::Prerequisite => &mut prerequisites,
-                        CourseRequirementMode::Concurrent => &mut concurrent,
-                        CourseRequirementMode::Corequisite => &mut corequisites,
-                        CourseRequirementMode::Recommended => &mut recommended,
-                    };
-
-                    // only assign if value isn't set before
-                    // in a sense: write-once
-                    if active_mode.is_none() {
-                        *active_mode = tree;
-                    }
-                }
-            }
-
-            // print result
-            // println!(
-            //     "{}{}",
-            //     "Mode\t->\t".yellow(),
-            //     match mode {
-            //         Some(ref mode) => match mode {
-            //             CourseRequirementMode::Prerequisite => "Prerequisite",
-            //             CourseRequirementMode::Concurrent => "Concurrent",
-            //             CourseRequirementMode::Corequisite => "Corequisite",
-            //             CourseRequirementMode::Recommended => "Recommended",
-            //         },
-            //         None => "None",
-            //     }
-            //     .yellow()
-            // );
-
-            selected_mode = mode;
-            String::new()
-        };
-
-        for text_chunk in text_chunks {
-            let text_chunk = text_chunk
-                .to_ascii_uppercase()
-                .replace_many(&[
-                    // modify list
-                    // even-though courses are not supposed to have these
-                    // https://cim.psu.edu/user-guides/course-management/prerequisites-concurrents-corequisites/
-                    // some course descriptions use ; for OR and , for AND (probably courses that haven't been updated in a while)
-                    (";", " OR"),
-                    (",", " AND"),
-                    ("CONCURRENT COURSES", "CONCURRENT"),
-                    ("RECOMMENDED PREPARATIONS", "RECOMMENDED PREPARATION"), // consistency purposes
-                    // spelling mistakes
-                    ("PRERQUISITE", "PREREQUISITE"),
-                    ("PREREQUISTE", "PREREQUISITE"),
-                    ("PREQUISITE", "PREREQUISITE"),
-                    ("PREREQ ", "PREREQUISITE "),
-                ])
-                .trim_all();
-
-            let mut requirement_text_buffer = String::new();
-
-            // TODO: find a better way of doing this
-            for (i, c) in text_chunk.chars().enumerate() {
-                match c {
-                    '(' | ')' | '[' | ']' => {
-                        // println!("{}{}", "LOGIC\t->\t".blue(), c.to_string().blue());
-                        requirement_text.push(c)
-                    }
-                    '.' | ':' => (), // ignore
-                    _ => requirement_text_buffer.push(c),
-                }
-
-                match requirement_text_buffer.trim_all().as_str() {
-                    "AND" => {
-                        // println!("{}", "LOGIC\t->\tAND".blue());
-                        requirement_text.push('&');
-                        requirement_text_buffer.clear();
-                    }
-                    "OR" => {
-                        // println!("{}", "LOGIC\t->\tOR".blue());
-                        requirement_text.push('|');
-                        requirement_text_buffer.clear();
-                    }
-                    // TODO: "AT AT LEAST ONE OF THE FOLLOWING" JUST MEANS TO MAKE A NEW '(' WITH "OR"
-                    "C OR BETTER IN"
-                    | "PRIOR TO"
-                    | "ENFORCED"
-                    | "AT ENROLLMENT"
-                    | "PREPARATION" 
-                    // Specific edge cases
-                    | "PRIOR EXPOSURE TO R PROGRAMMING LANGUAGE" // GEOG 462 
-                    => {
-                        // if this is in buffer ignore it (for now...)
-                        requirement_text_buffer.clear();
-                    }
-                    "PREREQUISITE" => {
-                        requirement_text = switch_mode(
-                            Some(CourseRequirementMode::Prerequisite),
-                            requirement_text,
-                        );
-                        requirement_text_buffer.clear();
-                    }
-                    "CONCURRENT" => {
-                        // check if previous char is `|`, if so,
-                        // prerequisite and concurrent are separate
-                        match requirement_text.chars().last() {
-                            Some('|') => flags.is_prerequisite_concurrent_separate = true,
-                            _ => (),
-                        }
-                        requirement_text =
-                            switch_mode(Some(CourseRequirementMode::Concurrent), requirement_text);
-                        requirement_text_buffer.clear();
-                    }
-                    "COREQUISITE" => {
-                        requirement_text =
-                            switch_mode(Some(CourseRequirementMode::Corequisite), requirement_text);
-                        requirement_text_buffer.clear();
-                    }
-                    "RECOMMENDED" => {
-                        requirement_text =
-                            switch_mode(Some(CourseRequirementMode::Recommended), requirement_text);
-                        requirement_text_buffer.clear();
-                    }
-                    // TODO: this is actually so bad. like for real work on making this better
-                    // ... but it works ... so ¯\_(ಠ_ಠ)_/¯
-                    // this messy if statements checks if current requirement_text_buffer is a course
-                    // however, to prevent false positives for example:
-                    // "MATH 140H" -> if the buffer isn't done filling up it'll mistake "MATH 1",
-                    //                  "MATH 14" or "MATH 140" as a course (depending on how filled the buffer is),
-                    //                  which is not true.
-                    // to fix this, we check if the current buffer is a course AND if the buffer plus
-                    // the next character isn't a course. If this is true, then it must mean we have
-                    // exhausted what the buffer is supposed to hold in terms of a course
-                    text if CourseIdentifier::try_from(text).is_ok()
-                        && (i == &text_chunk.chars().count() - 1
-                            || CourseIdentifier::try_from(
-                                format!("{}{}", text, text_chunk.chars().nth(i + 1).unwrap())
-                                    .as_str(),
-                            )
-                            .is_err()) =>
-                    {
-                        // TODO: remove all '\u{a0}'
-                        let course_text = requirement_text_buffer.trim_all();
-                        if
-                        // don't add a course if its the same as the current one
-                        course_text.replace('\u{a0}', " ") != identifier.to_string().as_str() && 
-                            // if course is already in requirement_text
-                            !requirement_text.contains(format!("{{{course_text}}}").as_str()) &&
-                            // if it contains "ANY"
-                            !course_text.contains("ANY")
-                        {
-                            // println!("ADDING\t->\t{}", course_text);
-                            // wrap all non-logical text is `{}` to preserve spacing
-                            requirement_text.push_str(format!("{{{course_text}}}").as_str());
-                            requirement_text_buffer.clear();
-                        }
-                    }
-                    _ => (),
-                }
-            }
-
-            let requirement_text_buffer: &str = &requirement_text_buffer.trim_all();
-            if !requirement_text_buffer.is_empty() {
-                match requirement_text_buffer {
-                    "S" => (), // ignore S, usually intended
-                    _ => {
-                        // println!(
-                        //     "{}{}",
-                        //     "DEVIANT\t->\t".red(),
-                        //     requirement_text_buffer.red().bold()
-                        // );
-                        // flag course has deviants so further work can be done
-                        flags.deviant.unknown_requirement = true;
-                    }
-                };
-            }
-        }
-
-        // flush `requirement_text`
-        switch_mode(None, requirement_text);
-
-        CourseRequirementTreeDetails {
-            requirements: UndergraduateCourseRequirements {
-                prerequisites,
-                concurrent,
-                corequisites,
-                recommended,
-            },
-            flags,
-        }
+    ) -> ParsedRequirements {
+        let text = element.text().collect::<Vec<_>>().join(" ");
+        requirement::parse(&text, identifier)
     }
 
     fn parse_extra_details(
         element: &ElementRef,
         identifier: &CourseIdentifier,
-    ) -> CourseExtraDetails {
+    ) -> Result<CourseExtraDetails> {
         let selector: Selector = Selector::parse(".courseblockextra .noindent").unwrap();
         let raw_extra_detail_elements = element.select(&selector);
         let mut attribute_list: FullAttributeList = AttributeList::new();
@@ -641,15 +731,20 @@ impl ScrapeUndergraduateCourses {
                     .next()
                     .is_some()
                 {
-                    let text_chunks = Self::create_text_chunks(&raw_extra_detail_element);
-                    let extra_details = Self::create_requirement_trees(text_chunks, identifier);
-                    requirements = extra_details.requirements;
-                    flags = extra_details.flags;
+                    let parsed = Self::extract_requirements(&raw_extra_detail_element, identifier);
+                    requirements = parsed.requirements;
+                    flags.is_prerequisite_concurrent_separate =
+                        parsed.is_prerequisite_concurrent_separate;
+                    // flag course has deviants so further work can be done
+                    flags.deviant.unknown_requirement |= !parsed.unknown.is_empty();
                 } else {
                     // anything here is mostly text-based
 
-                    let raw_detail: &str =
-                        &raw_extra_detail_element.text().next().unwrap().trim_all();
+                    let raw_detail: &str = &raw_extra_detail_element
+                        .text()
+                        .next()
+                        .unwrap_or_default()
+                        .trim_all();
 
                     if Self::is_noisy_extra_detail(raw_detail) {
                         continue;
@@ -662,16 +757,11 @@ impl ScrapeUndergraduateCourses {
                         if raw_extra_detail_element.children().count() > 1 {
                             let mut cursor = raw_extra_detail_element.first_child();
                             while let Some(sibling) = cursor {
-                                let text = match ElementRef::wrap(sibling) {
-                                    Some(element) => element.text().next().unwrap(),
-                                    None => sibling.value().as_text().unwrap(),
-                                }
-                                .to_ascii_uppercase()
-                                .trim_all();
+                                let text = node_text(sibling).to_ascii_uppercase().trim_all();
 
-                                match CourseIdentifier::try_from(text.as_str()).ok() {
-                                    Some(course) => crosslist_buffer.push(course),
-                                    None => (), // ignore everything else
+                                // ignore everything else
+                                if let Ok(course) = CourseIdentifier::try_from(text.as_str()) {
+                                    crosslist_buffer.push(course);
                                 }
 
                                 cursor = sibling.next_sibling();
@@ -685,9 +775,9 @@ impl ScrapeUndergraduateCourses {
                             {
                                 let text = text.to_ascii_uppercase().trim_all();
 
-                                match CourseIdentifier::try_from(text.as_str()).ok() {
-                                    Some(course) => crosslist_buffer.push(course),
-                                    None => (), // ignore everything else
+                                // ignore everything else
+                                if let Ok(course) = CourseIdentifier::try_from(text.as_str()) {
+                                    crosslist_buffer.push(course);
                                 }
                             }
                         }
@@ -708,18 +798,19 @@ impl ScrapeUndergraduateCourses {
                         | "Enforced Prerequisite at Enrollment"
                         | "Enforced Corequisite at Enrollment"
                         | "Recommended Preparation" => {
-                            let text_chunks = Self::create_text_chunks(&raw_extra_detail_element);
-                            let extra_details =
-                                Self::create_requirement_trees(text_chunks, identifier);
-                            requirements = extra_details.requirements;
-                            flags = extra_details.flags;
+                            let parsed =
+                                Self::extract_requirements(&raw_extra_detail_element, identifier);
+                            requirements = parsed.requirements;
+                            flags.is_prerequisite_concurrent_separate =
+                                parsed.is_prerequisite_concurrent_separate;
+                            flags.deviant.unknown_requirement |= !parsed.unknown.is_empty();
                         }
                         _ => {
                             // some attributes are incorrectly spelt issues
                             let attribute = Self::fix_attribute_typos(raw_detail);
 
                             // add actual attributes
-                            attribute_list.add(attribute);
+                            attribute_list.add(attribute)?;
                             //     println!(
                             //         "[{}] -> [{}]",
                             //         attribute.green(),
@@ -778,12 +869,12 @@ impl ScrapeUndergraduateCourses {
         //     println!("Contains empty crosslist");
         // }
 
-        CourseExtraDetails {
+        Ok(CourseExtraDetails {
             attribute_list,
             crosslist,
             requirements,
             flags,
-        }
+        })
     }
 
     fn fix_attribute_typos(attribute: &str) -> &str {
@@ -813,72 +904,286 @@ impl ScrapeUndergraduateCourses {
 
     /// All "noisy" course titles are skipped for now
     fn is_noisy_course_title(title: &str) -> bool {
-        match title {
-            "EDAB TEMPH: Temporary Education Abroad Registration" => true,
-            "EDAB TEMPI: Temporary Education Abroad Registration" => true,
-            _ => false,
+        matches!(
+            title,
+            "EDAB TEMPH: Temporary Education Abroad Registration"
+                | "EDAB TEMPI: Temporary Education Abroad Registration"
+        )
+    }
+
+    fn extract_identifier(element: &ElementRef) -> Result<CourseIdentifier> {
+        let raw_title = select_text(element, ".course_codetitle")?;
+
+        match raw_title.split_once(": ") {
+            Some((raw_identifier, _)) => CourseIdentifier::try_from(raw_identifier),
+            None => Err(Error::malformed(
+                "course title",
+                raw_title,
+                "Couldn't find `:` after the course identifier.",
+            )),
         }
     }
+
+    /// Returns `None` for noisy courses that should be skipped
+    fn extract_course(element: &ElementRef) -> Result<Option<UndergraduateCourse>> {
+        let raw_title = select_text(element, ".course_codetitle")?;
+
+        let description = element
+            .select(&Selector::parse(".courseblockdesc > p").unwrap())
+            .next()
+            .map(|desc| desc.text().next().unwrap_or_default().to_owned());
+
+        // skip noisy courses
+        if Self::is_noisy_course_title(raw_title) {
+            return Ok(None);
+        }
+
+        let identifier = Self::extract_identifier(element)?;
+        let title = match raw_title.split_once(": ") {
+            Some((_, title)) => title.trim_all(),
+            None => unreachable!("identifier was already extracted from the title"),
+        };
+
+        // if identifier.to_string() != "AMST 493" { continue; }
+
+        let (min_credits, credits) =
+            Self::extract_course_credits(element).with_context(|| &identifier)?;
+        let CourseExtraDetails {
+            attribute_list,
+            crosslist,
+            requirements,
+            flags,
+        } = Self::parse_extra_details(element, &identifier).with_context(|| &identifier)?;
+
+        Ok(Some(UndergraduateCourse {
+            identifier,
+            title,
+            description,
+            credits,
+            min_credits,
+            attribute_list,
+            crosslist,
+            requirements,
+            flags,
+        }))
+    }
 }
 
 impl Scrappable<UndergraduateCourse> for ScrapeUndergraduateCourses {
     /// Scrapes a list of undergraduate courses from a program catalog page
-    fn extract_list(html: &str) -> Vec<UndergraduateCourse> {
-        let document = scraper::Html::parse_document(&html);
+    fn extract_list(html: &str, mode: &mut ScrapeMode) -> Result<Vec<UndergraduateCourse>> {
+        let document = scraper::Html::parse_document(html);
         let selector = Selector::parse(".sc_sccoursedescs .courseblock").unwrap();
         let course_elements = document.select(&selector);
         let mut courses = Vec::<UndergraduateCourse>::new();
 
         for course_element in course_elements {
-            let raw_title = course_element
-                .select(&Selector::parse(".course_codetitle").unwrap())
-                .next()
-                .unwrap()
-                .text()
-                .next()
-                .unwrap();
+            let course = mode.skip_failure(
+                Self::extract_course(&course_element),
+                &course_element,
+                || {
+                    Self::extract_identifier(&course_element)
+                        .ok()
+                        .map(|identifier| identifier.to_string())
+                },
+            )?;
+
+            if let Some(course) = course.flatten() {
+                courses.push(course);
+            }
+        }
 
-            let description = match course_element
-                .select(&Selector::parse(".courseblockdesc > p").unwrap())
-                .next()
-            {
-                Some(desc) => Some(desc.text().next().unwrap().to_owned()),
-                None => None,
-            };
+        Ok(courses)
+    }
+}
 
-            // skip noisy courses
-            if Self::is_noisy_course_title(raw_title) {
-                continue;
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::MemoryFetcher;
+
+    const PROGRAM_LINK: &str =
+        "https://bulletins.psu.edu/undergraduate/colleges/science/mathematics-bs/";
+
+    /// A program page the way the bulletin lays it out, one tab per container. `credits` is
+    /// the credits cell of MATH 497.
+    fn program_page(credits: &str) -> String {
+        format!(
+            r#"<html><body>
+            <div id="overviewtextcontainer" class="page_content tab_content">
+                <p>The Mathematics major
+                    offers a broad education.</p>
+                <ul>
+                    <li>Pure mathematics</li>
+                    <li><p>Applied mathematics</p><p>with a statistics option</p></li>
+                </ul>
+            </div>
+            <div id="howtogetintextcontainer" class="page_content tab_content">
+                <h3>Entrance to Major</h3>
+                <p>Students must earn a C or better in MATH 140.</p>
+            </div>
+            <div id="contacttextcontainer" class="page_content tab_content">
+                <h4>University Park</h4>
+                <p>Department of Mathematics<br/>104 McAllister Building</p>
+            </div>
+            <div id="programrequirementstextcontainer" class="page_content tab_content">
+                <h3>Degree Requirements</h3>
+                <table class="sc_courselist"><tbody>
+                    <tr class="even areaheader"><td colspan="2"><span class="courselistcomment areaheader">Prescribed Courses</span></td><td class="hourscol"></td></tr>
+                    <tr class="odd"><td class="codecol"><a href="/search/?P=MATH%20140" class="bubblelink code">MATH&#160;140</a></td><td>Calculus With Analytic Geometry I</td><td class="hourscol">4</td></tr>
+                    <tr class="even orclass"><td class="codecol"><div class="blockindent">or&#160;<a href="/search/?P=MATH%20140H" class="bubblelink code">MATH&#160;140H</a></div></td><td>Honors Calculus With Analytic Geometry I</td><td class="hourscol"></td></tr>
+                    <tr class="odd"><td colspan="2"></td><td class="hourscol"></td></tr>
+                    <tr class="even listsum"><td colspan="2">Total Credits</td><td class="hourscol">4</td></tr>
+                </tbody></table>
+                <h4>Additional Courses</h4>
+                <table class="sc_courselist"><tbody>
+                    <tr class="odd"><td colspan="2"><span class="courselistcomment">Select 6 credits of GN courses</span></td><td class="hourscol">6</td></tr>
+                    <tr class="even"><td class="codecol"><a href="/search/?P=MATH%20497" class="bubblelink code">MATH&#160;497</a></td><td>Special Topics</td><td class="hourscol">{credits}</td></tr>
+                </tbody></table>
+            </div>
+            </body></html>"#
+        )
+    }
 
-            let (raw_identifier, title) = raw_title.split_once(": ").unwrap();
-
-            let title = title.trim_all();
-            let identifier = CourseIdentifier::try_from(raw_identifier).unwrap();
-
-            // if identifier.to_string() != "AMST 493" { continue; }
-
-            let (min_credits, credits) = Self::extract_course_credits(&course_element);
-            let CourseExtraDetails {
-                attribute_list,
-                crosslist,
-                requirements,
-                flags,
-            } = Self::parse_extra_details(&course_element, &identifier);
-
-            courses.push(UndergraduateCourse {
-                identifier,
-                title,
-                description,
-                credits,
-                min_credits,
-                attribute_list,
-                crosslist,
-                requirements,
-                flags,
-            });
-        }
+    fn fetcher(html: String) -> MemoryFetcher {
+        let mut fetcher = MemoryFetcher::new();
+        fetcher.insert(PROGRAM_LINK, html);
+        fetcher
+    }
+
+    fn program() -> UndergraduateProgram {
+        UndergraduateProgram::baccalaureate("Mathematics", PROGRAM_LINK, &["University Park"])
+    }
+
+    /// (section, course, title, min_credits, credits, is_alternative) of every requirement
+    #[allow(clippy::type_complexity)]
+    fn requirements(
+        details: &UndergraduateProgramDetails,
+    ) -> Vec<(
+        Option<&str>,
+        Option<String>,
+        &str,
+        Option<f32>,
+        Option<f32>,
+        bool,
+    )> {
+        details
+            .requirements
+            .iter()
+            .map(|requirement| {
+                (
+                    requirement.section.as_deref(),
+                    requirement.course.as_ref().map(ToString::to_string),
+                    requirement.title.as_str(),
+                    requirement.min_credits,
+                    requirement.credits,
+                    requirement.is_alternative,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn program_details_are_scraped() {
+        let details = ScrapeUndergraduateProgramDetails::scrape(
+            &fetcher(program_page("1-9")),
+            &program(),
+            &mut ScrapeMode::Strict,
+        )
+        .unwrap();
+
+        assert_eq!(PROGRAM_LINK, details.link);
+        assert_eq!(
+            Some("The Mathematics major offers a broad education.\nPure mathematics\nApplied mathematics\nwith a statistics option"),
+            details.overview.as_deref()
+        );
+        assert_eq!(
+            Some("Entrance to Major\nStudents must earn a C or better in MATH 140."),
+            details.entrance_requirements.as_deref()
+        );
+        assert_eq!(
+            Some("University Park\nDepartment of Mathematics 104 McAllister Building"),
+            details.contact.as_deref()
+        );
+
+        let prescribed = Some("Prescribed Courses");
+        let additional = Some("Additional Courses");
+        assert_eq!(
+            vec![
+                (
+                    prescribed,
+                    Some("MATH 140".to_string()),
+                    "Calculus With Analytic Geometry I",
+                    None,
+                    Some(4.0),
+                    false
+                ),
+                (
+                    prescribed,
+                    Some("MATH 140H".to_string()),
+                    "Honors Calculus With Analytic Geometry I",
+                    None,
+                    None,
+                    true
+                ),
+                (
+                    additional,
+                    None,
+                    "Select 6 credits of GN courses",
+                    None,
+                    Some(6.0),
+                    false
+                ),
+                (
+                    additional,
+                    Some("MATH 497".to_string()),
+                    "Special Topics",
+                    Some(1.0),
+                    Some(9.0),
+                    false
+                ),
+            ],
+            requirements(&details)
+        );
+    }
+
+    #[test]
+    fn malformed_requirement_rows_are_skipped_in_lenient_mode() {
+        let fetcher = fetcher(program_page("three"));
+
+        assert!(ScrapeUndergraduateProgramDetails::scrape(
+            &fetcher,
+            &program(),
+            &mut ScrapeMode::Strict
+        )
+        .is_err());
+
+        let mut report = ScrapeReport::new();
+        let details = ScrapeUndergraduateProgramDetails::scrape(
+            &fetcher,
+            &program(),
+            &mut ScrapeMode::Lenient(&mut report),
+        )
+        .unwrap();
+
+        assert_eq!(3, details.requirements.len());
+        assert_eq!(1, report.failures.len());
+        assert_eq!(Some(PROGRAM_LINK), report.failures[0].url.as_deref());
+        assert_eq!(Some(PROGRAM_LINK), report.failures[0].identifier.as_deref());
+        assert_eq!(
+            "program requirement credits parsing",
+            report.failures[0].failed_step
+        );
+    }
+
+    #[test]
+    fn pages_without_an_overview_arent_program_pages() {
+        let result = ScrapeUndergraduateProgramDetails::scrape(
+            &fetcher("<html><body><p>Page not found</p></body></html>".into()),
+            &program(),
+            &mut ScrapeMode::Strict,
+        );
 
-        courses
+        assert!(result.is_err());
     }
 }
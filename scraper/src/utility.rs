@@ -1,49 +1,4 @@
-use std::path::Path;
-
-pub fn retrieve_document(url: &str) -> String {
-    let cache_file = Path::new("./cache")
-        .join(url.replace("https://", ""))
-        .with_extension("html");
-    // let filename = cache_file.file_name().unwrap().to_str().unwrap();
-
-    // if file is older than 1 day, delete cache
-    if let Ok(metadata) = std::fs::metadata(&cache_file) {
-        let created_at = metadata.created().unwrap();
-
-        if created_at
-            < std::time::SystemTime::now() - std::time::Duration::from_secs(60 * 60 * 24 * 3)
-        {
-            // println!("{} file is older than 3 days, deleting...", filename);
-            std::fs::remove_file(&cache_file).unwrap();
-            // println!("{} file deleted.", filename);
-        }
-    }
-
-    // check if index.html file exists, if not make request, and save as cache
-    let body = match &cache_file.exists() {
-        true => {
-            // println!("Found cache [{}], reading file...", filename);
-            std::fs::read_to_string(cache_file).unwrap()
-        }
-        false => {
-            // println!("Making request...");
-            let body = ureq::get(url).call().unwrap().into_string().unwrap();
-            // println!("Got a response.");
-
-            // save to file
-            let mut cache_path = cache_file.to_path_buf();
-
-            cache_path.pop();
-            // println!("Saving as cache [{}]...", filename);
-            std::fs::create_dir_all(cache_path).unwrap();
-            std::fs::write(cache_file, body.clone()).unwrap();
-            body
-        }
-    };
-
-    // println!("Retrieved file.");
-    body
-}
+use std::collections::BTreeSet;
 
 pub trait TrimAll {
     fn trim_all(&self) -> String;
@@ -92,3 +47,48 @@ impl ReplaceMany for str {
         s
     }
 }
+
+/// Drops duplicate sets and sets that contain another one, then sorts them smallest first.
+///
+/// # Examples
+///
+/// ```
+/// let sets = vec![BTreeSet::from([1, 2]), BTreeSet::from([1]), BTreeSet::from([3])];
+///
+/// assert_eq!(vec![BTreeSet::from([1]), BTreeSet::from([3])], minimal_sets(sets));
+/// ```
+pub fn minimal_sets<T: Ord>(mut sets: Vec<BTreeSet<T>>) -> Vec<BTreeSet<T>> {
+    sets.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+    sets.dedup();
+
+    let mut minimal = Vec::<BTreeSet<T>>::new();
+    for set in sets {
+        if !minimal.iter().any(|smaller| smaller.is_subset(&set)) {
+            minimal.push(set);
+        }
+    }
+
+    minimal
+}
+
+/// Returns the minimal unions of one set of `a` with one set of `b`.
+///
+/// # Examples
+///
+/// ```
+/// let a = vec![BTreeSet::from([1]), BTreeSet::from([2])];
+/// let b = vec![BTreeSet::from([3])];
+///
+/// assert_eq!(vec![BTreeSet::from([1, 3]), BTreeSet::from([2, 3])], set_product(&a, &b));
+/// ```
+pub fn set_product<T: Ord + Clone>(a: &[BTreeSet<T>], b: &[BTreeSet<T>]) -> Vec<BTreeSet<T>> {
+    let mut sets = Vec::new();
+
+    for a in a {
+        for b in b {
+            sets.push(a.union(b).cloned().collect());
+        }
+    }
+
+    minimal_sets(sets)
+}